@@ -4,17 +4,23 @@ use revm::{
     interpreter::{opcode, Interpreter},
     Database, EvmContext, Inspector,
 };
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 /// An [Inspector] that collects touched accounts and storage slots.
 ///
 /// This can be used to construct an [AccessList] for a transaction via `eth_createAccessList`
+///
+/// The sender, the recipient and the precompiles are warm per EIP-2929, so they are omitted from
+/// the final access list unless their storage slots were touched, see
+/// [AccessListInspector::with_include_warm_addresses].
 #[derive(Debug, Default)]
 pub struct AccessListInspector {
     /// All addresses that should be excluded from the final accesslist
     excluded: HashSet<Address>,
-    /// All addresses and touched slots
-    access_list: HashMap<Address, BTreeSet<B256>>,
+    /// All addresses and touched slots, ordered so that the final accesslist is deterministic
+    access_list: BTreeMap<Address, BTreeSet<B256>>,
+    /// Whether excluded addresses without storage slots are kept in the final accesslist
+    include_warm_addresses: bool,
 }
 
 impl AccessListInspector {
@@ -34,26 +40,55 @@ impl AccessListInspector {
                 .into_iter()
                 .map(|v| (v.address, v.storage_keys.into_iter().collect()))
                 .collect(),
+            include_warm_addresses: false,
         }
     }
 
+    /// Configures whether the warm addresses, i.e. the sender, the recipient and the precompiles,
+    /// are included in the final access list even if none of their storage slots were touched.
+    ///
+    /// By default, these addresses are only included with their touched storage slots, since the
+    /// slots are not warm. If this is set, they're kept if they were part of the provided access
+    /// list.
+    pub const fn with_include_warm_addresses(mut self, include_warm_addresses: bool) -> Self {
+        self.include_warm_addresses = include_warm_addresses;
+        self
+    }
+
+    /// Returns true if the entry should be part of the final access list.
+    fn is_included(&self, address: &Address, slots: &BTreeSet<B256>) -> bool {
+        self.include_warm_addresses || !slots.is_empty() || !self.excluded.contains(address)
+    }
+
     /// Returns list of addresses and storage keys used by the transaction. It gives you the list of
     /// addresses and storage keys that were touched during execution.
-    pub fn into_access_list(self) -> AccessList {
-        let items = self.access_list.into_iter().map(|(address, slots)| AccessListItem {
-            address,
-            storage_keys: slots.into_iter().collect(),
-        });
+    ///
+    /// The addresses and the storage keys of each address are sorted and unique.
+    pub fn into_access_list(mut self) -> AccessList {
+        let access_list = std::mem::take(&mut self.access_list);
+        let items = access_list
+            .into_iter()
+            .filter(|(address, slots)| self.is_included(address, slots))
+            .map(|(address, slots)| AccessListItem {
+                address,
+                storage_keys: slots.into_iter().collect(),
+            });
         AccessList(items.collect())
     }
 
     /// Returns list of addresses and storage keys used by the transaction. It gives you the list of
     /// addresses and storage keys that were touched during execution.
+    ///
+    /// The addresses and the storage keys of each address are sorted and unique.
     pub fn access_list(&self) -> AccessList {
-        let items = self.access_list.iter().map(|(address, slots)| AccessListItem {
-            address: *address,
-            storage_keys: slots.iter().copied().collect(),
-        });
+        let items = self
+            .access_list
+            .iter()
+            .filter(|(address, slots)| self.is_included(address, slots))
+            .map(|(address, slots)| AccessListItem {
+                address: *address,
+                storage_keys: slots.iter().copied().collect(),
+            });
         AccessList(items.collect())
     }
 }
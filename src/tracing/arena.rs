@@ -1,4 +1,15 @@
-use super::types::{CallTrace, CallTraceNode, TraceMemberOrder};
+use super::{
+    config::TraceStyle,
+    types::{CallTrace, CallTraceNode, CallTraceStep, CallTraceStepStackItem, TraceMemberOrder},
+    utils::{apply_labels, maybe_revert_reason},
+};
+use alloy_primitives::{hex, keccak256, Address, Bytes, Selector, B256, U256};
+use revm::{
+    interpreter::{opcode, OpCode},
+    primitives::SpecId,
+};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 /// An arena of recorded traces.
 ///
@@ -8,12 +19,14 @@ use super::types::{CallTrace, CallTraceNode, TraceMemberOrder};
 pub struct CallTraceArena {
     /// The arena of recorded trace nodes
     pub(crate) arena: Vec<CallTraceNode>,
+    /// The spec id the traces were recorded with.
+    pub(crate) spec_id: Option<SpecId>,
 }
 
 impl Default for CallTraceArena {
     fn default() -> Self {
         // The first node is the root node
-        Self { arena: vec![Default::default()] }
+        Self { arena: vec![Default::default()], spec_id: None }
     }
 }
 
@@ -28,11 +41,330 @@ impl CallTraceArena {
         &mut self.arena
     }
 
+    /// Returns the spec id the traces were recorded with.
+    ///
+    /// This is `None` if nothing was recorded yet.
+    pub const fn spec_id(&self) -> Option<SpecId> {
+        self.spec_id
+    }
+
     /// Consumes the arena and returns the nodes.
     pub fn into_nodes(self) -> Vec<CallTraceNode> {
         self.arena
     }
 
+    /// Sets the decoded label of all calls to an address in the given map.
+    ///
+    /// Labels are used by all output formats, see also [GethTraceBuilder::with_labels] and
+    /// [ParityTraceBuilder::with_labels].
+    ///
+    /// [GethTraceBuilder::with_labels]: super::GethTraceBuilder::with_labels
+    /// [ParityTraceBuilder::with_labels]: super::ParityTraceBuilder::with_labels
+    pub fn apply_labels(&mut self, labels: &HashMap<Address, String>) {
+        apply_labels(&mut self.arena, labels);
+    }
+
+    /// Returns the node of the call at the given parity `traceAddress`, if any.
+    ///
+    /// The empty path resolves to the root call. Like in parity traces, calls that are not part of
+    /// the call graph, such as calls to precompiles recorded with
+    /// [TracingInspectorConfig::exclude_precompile_calls], are not counted. Selfdestructs don't
+    /// have a node.
+    ///
+    /// [TracingInspectorConfig::exclude_precompile_calls]: super::TracingInspectorConfig::exclude_precompile_calls
+    pub fn node_at_trace_address(&self, trace_address: &[usize]) -> Option<&CallTraceNode> {
+        let mut node = self.arena.first()?;
+        for &call_idx in trace_address {
+            node = &self.arena[*node.children.get(call_idx)?];
+        }
+        Some(node)
+    }
+
+    /// Returns all calls with their parity `traceAddress`, in the order the calls were made.
+    ///
+    /// The depth of a call is the length of its trace address. Like in parity traces, calls that
+    /// are not part of the call graph are not included, precompile calls recorded with
+    /// [TracingInspectorConfig::exclude_precompile_calls] are only included if
+    /// `include_precompiles` is set.
+    ///
+    /// [TracingInspectorConfig::exclude_precompile_calls]: super::TracingInspectorConfig::exclude_precompile_calls
+    pub fn flatten(&self, include_precompiles: bool) -> Vec<(Vec<usize>, &CallTrace)> {
+        let children = traceable_children(&self.arena, include_precompiles);
+        // nodes are ordered by their index, which is the order the calls were made
+        trace_addresses(&self.arena, &children, include_precompiles)
+            .into_iter()
+            .zip(&self.arena)
+            .filter(|(_, node)| is_traceable(&self.arena, node, include_precompiles))
+            .map(|(trace_address, node)| (trace_address, &node.trace))
+            .collect()
+    }
+
+    /// Returns the call tree in the nested JSON format used by Tenderly.
+    ///
+    /// Every call is an object with its `call_type`, `from`, `to`, `gas`, `gas_used`, `input`,
+    /// `output`, `value`, `error` and its subcalls as `calls`. Unlike geth's `callTracer`, keys are
+    /// snake case, gas is a number, value is a decimal string, and `error` and `calls` are always
+    /// present.
+    pub fn tenderly_json(&self) -> Value {
+        tenderly_json_node(&self.arena, 0)
+    }
+
+    /// Returns an iterator over the inputs of all calls to the given function selector, in the
+    /// order the calls were made.
+    ///
+    /// Contract creations are ignored.
+    pub fn call_inputs_with_selector(&self, selector: Selector) -> impl Iterator<Item = &Bytes> {
+        self.arena
+            .iter()
+            .filter(move |node| {
+                !node.trace.kind.is_any_create() && node.trace.data.starts_with(selector.as_slice())
+            })
+            .map(|node| &node.trace.data)
+    }
+
+    /// Returns the input of the first call to the given function selector, if any.
+    ///
+    /// See also [Self::call_inputs_with_selector].
+    pub fn first_call_input_with_selector(&self, selector: Selector) -> Option<&Bytes> {
+        self.call_inputs_with_selector(selector).next()
+    }
+
+    /// Returns an iterator over the nodes of all calls that ran out of gas themselves, in the
+    /// order the calls were made.
+    ///
+    /// Calls that failed because of a subcall that ran out of gas are not included, unless they
+    /// ran out of gas as well. See [CallTrace::is_out_of_gas].
+    pub fn out_of_gas_nodes(&self) -> impl Iterator<Item = &CallTraceNode> {
+        self.arena.iter().filter(|node| node.trace.is_out_of_gas())
+    }
+
+    /// Returns an iterator over the nodes of all calls to any of the given addresses, e.g. system
+    /// contracts or predeploys, in the order the calls were made.
+    ///
+    /// The caller of a returned call is the contract that interacted with the protected address,
+    /// see [CallTraceNode::parent]. For delegate calls the address is the delegated to contract.
+    pub fn calls_to_protected<'a>(
+        &'a self,
+        protected: &'a HashSet<Address>,
+    ) -> impl Iterator<Item = &'a CallTraceNode> + 'a {
+        self.arena.iter().filter(move |node| protected.contains(&node.trace.address))
+    }
+
+    /// Returns the gas used per contract, keyed by the address of the executed code.
+    ///
+    /// Every call is only attributed the gas it used itself, excluding the gas used by its
+    /// subcalls. For delegate calls this is the address of the delegated to contract.
+    ///
+    /// If `exclude_precompiles` is set, the gas used by calls to precompiles is not attributed to
+    /// any address. Precompile calls are only detected if they were recorded with
+    /// [TracingInspectorConfig::exclude_precompile_calls].
+    ///
+    /// [TracingInspectorConfig::exclude_precompile_calls]: super::TracingInspectorConfig::exclude_precompile_calls
+    pub fn gas_by_address(&self, exclude_precompiles: bool) -> HashMap<Address, u64> {
+        let mut subcalls_gas = vec![0u64; self.arena.len()];
+        for node in &self.arena {
+            if let Some(parent) = node.parent {
+                subcalls_gas[parent] += node.trace.gas_used;
+            }
+        }
+
+        let mut gas = HashMap::new();
+        for node in &self.arena {
+            if exclude_precompiles && node.trace.maybe_precompile.unwrap_or(false) {
+                continue;
+            }
+            let self_gas = node.trace.gas_used.saturating_sub(subcalls_gas[node.idx]);
+            *gas.entry(node.trace.address).or_default() += self_gas;
+        }
+        gas
+    }
+
+    /// Returns the calls from the root call to the call that originated the revert.
+    ///
+    /// A reverted call bubbled up the revert of a subcall if that subcall reverted with the same
+    /// output, otherwise it is the origin of its own revert, e.g. if it caught the revert of the
+    /// subcall and then reverted with a different reason. The path starts at the shallowest revert
+    /// that was not bubbled up, the first one if multiple reverted at the same depth, and follows
+    /// the bubbled up reverts to their origin. Returns `None` if no call reverted.
+    pub fn revert_path(&self) -> Option<Vec<&CallTraceNode>> {
+        // the last reverted subcall with the same output, i.e. the revert that the call bubbled up
+        let bubbled =
+            |node: &CallTraceNode| {
+                node.children.iter().rev().map(|idx| &self.arena[*idx]).find(|child| {
+                    child.trace.is_revert() && child.trace.output == node.trace.output
+                })
+            };
+
+        let mut is_bubbled = vec![false; self.arena.len()];
+        for node in self.arena.iter().filter(|node| node.trace.is_revert()) {
+            if let Some(child) = bubbled(node) {
+                is_bubbled[child.idx] = true;
+            }
+        }
+
+        let mut origin = self
+            .arena
+            .iter()
+            .filter(|node| node.trace.is_revert() && !is_bubbled[node.idx])
+            .min_by_key(|node| node.trace.depth)?;
+        while let Some(child) = bubbled(origin) {
+            origin = child;
+        }
+
+        let mut path = vec![origin];
+        let mut node = origin;
+        while let Some(parent) = node.parent {
+            node = &self.arena[parent];
+            path.push(node);
+        }
+        path.reverse();
+        Some(path)
+    }
+
+    /// Returns a message describing the [revert path](Self::revert_path), e.g.
+    /// `0xA.foo() -> 0xB.bar() reverted: reason`.
+    ///
+    /// Calls are named by their label and decoded function name if they were decoded, otherwise
+    /// by their address and function selector. The reason is omitted if the revert data can't be
+    /// decoded.
+    pub fn revert_path_message(&self) -> Option<String> {
+        let path = self.revert_path()?;
+        let mut msg =
+            path.iter().map(|node| call_name(&node.trace)).collect::<Vec<_>>().join(" -> ");
+        msg.push_str(" reverted");
+        if let Some(reason) = path.last().and_then(|node| maybe_revert_reason(&node.trace.output)) {
+            msg.push_str(": ");
+            msg.push_str(&reason);
+        }
+        Some(msg)
+    }
+
+    /// Returns an iterator over all steps that attempted a state change in a static context,
+    /// together with the node of the call they were executed in, in the order the calls were made.
+    ///
+    /// See [CallTraceStep::is_static_violation]. This requires steps recording.
+    pub fn static_violations(&self) -> impl Iterator<Item = (&CallTraceNode, &CallTraceStep)> {
+        self.arena.iter().flat_map(|node| {
+            node.trace
+                .steps
+                .iter()
+                .filter(|step| step.is_static_violation())
+                .map(move |step| (node, step))
+        })
+    }
+
+    /// Returns, per contract, the storage slots that were read before they were written.
+    ///
+    /// A slot is included if its first recorded access in the transaction was an `SLOAD`, these
+    /// are the slots the contract's execution potentially depends on.
+    ///
+    /// Note: this relies on the recorded steps and therefore requires
+    /// [TracingInspectorConfig::record_steps](super::TracingInspectorConfig::record_steps) and
+    /// [TracingInspectorConfig::record_state_diff](super::TracingInspectorConfig::record_state_diff)
+    /// to be enabled. Only the first access of a slot is recorded as a
+    /// [StorageChange](super::types::StorageChange), so accesses of warm slots, e.g. slots in the
+    /// access list, are only detected if
+    /// [TracingInspectorConfig::record_stack_snapshots](super::TracingInspectorConfig::record_stack_snapshots)
+    /// records at least the top of the stack, which holds the accessed slot.
+    pub fn storage_reads_before_writes(&self) -> HashMap<Address, HashSet<U256>> {
+        let mut reads = HashMap::<Address, HashSet<U256>>::new();
+        let mut accessed = HashSet::new();
+
+        // process the steps in the order they were executed, see `GethTraceBuilder`
+        let mut step_stack = VecDeque::new();
+        self.arena[0].push_steps_on_stack(&mut step_stack);
+        while let Some(CallTraceStepStackItem { step, call_child_id, .. }) = step_stack.pop_back() {
+            let op = step.op.get();
+            if matches!(op, opcode::SLOAD | opcode::SSTORE) && !step.is_error() {
+                let key = step
+                    .storage_change
+                    .map(|change| change.key)
+                    .or_else(|| step.stack.as_ref().and_then(|stack| stack.last().copied()));
+                if let Some(key) = key {
+                    if accessed.insert((step.contract, key)) && op == opcode::SLOAD {
+                        reads.entry(step.contract).or_default().insert(key);
+                    }
+                }
+            }
+
+            if let Some(call_child_id) = call_child_id {
+                self.arena[call_child_id].push_steps_on_stack(&mut step_stack);
+            }
+        }
+
+        reads
+    }
+
+    /// Returns the calls that a frame made more than once with identical inputs, which might
+    /// indicate a missed caching opportunity.
+    ///
+    /// Calls are identical if they have the same kind, target, value and calldata. Each entry
+    /// contains the index of the calling frame, the first of the identical calls and the number of
+    /// times the call was made, in the order of the first calls. Creates are never considered
+    /// identical.
+    pub fn repeated_calls(&self) -> Vec<(usize, &CallTraceNode, usize)> {
+        let mut repeated = Vec::new();
+        for node in &self.arena {
+            let mut counts = HashMap::<_, (usize, usize)>::new();
+            let mut order = Vec::new();
+            for child in node.children.iter().map(|idx| &self.arena[*idx]) {
+                let trace = &child.trace;
+                if trace.kind.is_any_create() {
+                    continue;
+                }
+                let key = (trace.kind, trace.address, trace.value, trace.data.as_ref());
+                counts
+                    .entry(key)
+                    .or_insert_with(|| {
+                        order.push(key);
+                        (child.idx, 0)
+                    })
+                    .1 += 1;
+            }
+            for key in order {
+                let (first, count) = counts[&key];
+                if count > 1 {
+                    repeated.push((node.idx, &self.arena[first], count));
+                }
+            }
+        }
+        repeated
+    }
+
+    /// Returns the hashes and preimages of all `KECCAK256` steps, in the order they were executed.
+    ///
+    /// Note: the preimages are read from the memory and stack snapshots of the steps and therefore
+    /// require
+    /// [TracingInspectorConfig::record_memory_snapshots](super::TracingInspectorConfig::record_memory_snapshots)
+    /// and
+    /// [TracingInspectorConfig::record_stack_snapshots](super::TracingInspectorConfig::record_stack_snapshots)
+    /// to be enabled, recording at least the top two stack items.
+    pub fn keccak_preimages(&self) -> Vec<(B256, Bytes)> {
+        let mut preimages = Vec::new();
+
+        // process the steps in the order they were executed, see `GethTraceBuilder`
+        let mut step_stack = VecDeque::new();
+        self.arena[0].push_steps_on_stack(&mut step_stack);
+        while let Some(CallTraceStepStackItem { step, call_child_id, .. }) = step_stack.pop_back() {
+            if step.op.get() == opcode::KECCAK256 && !step.is_error() {
+                if let (Some(stack), Some(memory)) = (&step.stack, &step.memory) {
+                    // the top of the stack is the memory offset, followed by the size
+                    let mut args = stack.iter().rev();
+                    if let (Some(offset), Some(size)) = (args.next(), args.next()) {
+                        let preimage = read_memory(memory.as_bytes(), *offset, *size);
+                        preimages.push((keccak256(&preimage), preimage));
+                    }
+                }
+            }
+
+            if let Some(call_child_id) = call_child_id {
+                self.arena[call_child_id].push_steps_on_stack(&mut step_stack);
+            }
+        }
+
+        preimages
+    }
+
     /// Clears the arena
     ///
     /// Note that this method has no effect on the allocated capacity of the arena.
@@ -40,6 +372,87 @@ impl CallTraceArena {
     pub fn clear(&mut self) {
         self.arena.clear();
         self.arena.push(Default::default());
+        self.spec_id = None;
+    }
+
+    /// Removes the recorded steps of all calls while keeping the call structure and the logs
+    /// intact.
+    ///
+    /// This is useful to reduce the size of the arena, e.g. before serializing it, if only the call
+    /// tree is of interest after the steps have been analyzed.
+    pub fn clear_steps(&mut self) {
+        for node in &mut self.arena {
+            node.trace.steps.clear();
+            node.ordering.retain(|order| !matches!(order, TraceMemberOrder::Step(_)));
+        }
+    }
+
+    /// Returns how often each opcode was executed by the call at the given node index and all
+    /// its subcalls.
+    ///
+    /// Note: this requires
+    /// [TracingInspectorConfig::record_steps](super::TracingInspectorConfig::record_steps) to be
+    /// enabled.
+    ///
+    /// # Panics
+    ///
+    /// If the `idx` does not belong to a node.
+    pub fn subtree_opcode_counts(&self, idx: usize) -> HashMap<OpCode, usize> {
+        let mut counts = HashMap::new();
+        let mut stack = vec![idx];
+        while let Some(idx) = stack.pop() {
+            let node = &self.arena[idx];
+            for step in &node.trace.steps {
+                *counts.entry(step.op).or_default() += 1;
+            }
+            stack.extend_from_slice(&node.children);
+        }
+        counts
+    }
+
+    /// Returns the number of all descendants of the node at the given index, i.e. the number of
+    /// calls in its subtree excluding the node itself.
+    ///
+    /// # Panics
+    ///
+    /// If the `idx` does not belong to a node.
+    pub fn descendant_count(&self, idx: usize) -> usize {
+        let mut count = 0;
+        let mut stack = self.arena[idx].children.clone();
+        while let Some(idx) = stack.pop() {
+            count += 1;
+            stack.extend_from_slice(&self.arena[idx].children);
+        }
+        count
+    }
+
+    /// Serializes the arena to MessagePack.
+    ///
+    /// This produces a much more compact encoding than JSON, e.g. for archiving traces.
+    #[cfg(feature = "msgpack")]
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec(self)
+    }
+
+    /// Deserializes an arena from MessagePack, see [Self::to_msgpack].
+    #[cfg(feature = "msgpack")]
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(bytes)
+    }
+
+    /// Encodes the arena in a compact, versioned binary format.
+    ///
+    /// This is smaller and faster to decode than JSON, e.g. for passing traces between services.
+    /// The layout is documented in `src/tracing/binary.rs`.
+    #[cfg(feature = "binary")]
+    pub fn to_binary(&self) -> Vec<u8> {
+        super::binary::encode(self)
+    }
+
+    /// Decodes an arena from the binary format, see [Self::to_binary].
+    #[cfg(feature = "binary")]
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, super::BinaryDecodeError> {
+        super::binary::decode(bytes)
     }
 
     /// Pushes a new trace into the arena, returning the trace ID
@@ -88,6 +501,182 @@ impl CallTraceArena {
             }
         }
     }
+
+    /// Pushes a new trace into the arena as a child of the given parent node, returning the trace
+    /// ID
+    ///
+    /// Unlike [Self::push_trace], this does not look up the parent node by the depth of the trace.
+    pub(crate) fn push_child_trace(
+        &mut self,
+        parent: usize,
+        kind: PushTraceKind,
+        new_trace: CallTrace,
+    ) -> usize {
+        let id = self.arena.len();
+        let node =
+            CallTraceNode { parent: Some(parent), trace: new_trace, idx: id, ..Default::default() };
+        self.arena.push(node);
+
+        if kind.is_attach_to_parent() {
+            let parent = &mut self.arena[parent];
+            let trace_location = parent.children.len();
+            parent.ordering.push(TraceMemberOrder::Call(trace_location));
+            parent.children.push(id);
+        }
+
+        id
+    }
+}
+
+/// Reads `size` bytes at `offset` from the memory, padded with zeros beyond the memory's length.
+fn read_memory(memory: &[u8], offset: U256, size: U256) -> Bytes {
+    let size = size.saturating_to::<usize>();
+    if size == 0 {
+        return Bytes::new();
+    }
+    let offset = offset.saturating_to::<usize>();
+    let mut data = vec![0; size];
+    if let Some(available) = memory.get(offset..) {
+        let len = available.len().min(size);
+        data[..len].copy_from_slice(&available[..len]);
+    }
+    data.into()
+}
+
+/// Returns the name of a call, e.g. `0xA.foo()`, for the revert path message.
+fn call_name(trace: &CallTrace) -> String {
+    let target = match &trace.decoded.label {
+        Some(label) => label.clone(),
+        None => trace.address.to_checksum(None),
+    };
+    if trace.kind.is_any_create() {
+        return format!("new {target}");
+    }
+    let func = match &trace.decoded.call_data {
+        Some(call_data) => call_data.signature.split('(').next().unwrap_or_default().to_string(),
+        None if trace.data.len() >= 4 => hex::encode_prefixed(&trace.data[..4]),
+        None => "fallback".to_string(),
+    };
+    format!("{target}.{func}()")
+}
+
+/// Returns the JSON of a node and its children in the format used by Tenderly.
+fn tenderly_json_node(nodes: &[CallTraceNode], idx: usize) -> Value {
+    let node = &nodes[idx];
+    let trace = &node.trace;
+    let calls =
+        node.children.iter().map(|child| tenderly_json_node(nodes, *child)).collect::<Vec<_>>();
+
+    json!({
+        "call_type": trace.kind.to_str(),
+        "from": trace.caller,
+        "to": trace.address,
+        "gas": trace.gas_limit,
+        "gas_used": trace.gas_used,
+        "input": trace.data,
+        "output": trace.output,
+        "value": trace.value.to_string(),
+        "error": trace.as_error_msg(TraceStyle::Geth),
+        "calls": calls,
+    })
+}
+
+/// Returns true if the node is part of the call graph, i.e. it's the root or a child of its
+/// parent.
+///
+/// Calls that are not recorded in the call graph, like excluded precompile calls or calls to
+/// addresses not included by [TracingInspectorConfig::only_addresses], are not attached.
+///
+/// [TracingInspectorConfig::only_addresses]: super::TracingInspectorConfig::only_addresses
+pub(crate) fn is_attached(nodes: &[CallTraceNode], node: &CallTraceNode) -> bool {
+    node.parent.map_or(true, |parent| nodes[parent].children.contains(&node.idx))
+}
+
+/// Returns true if the node is included in parity traces.
+///
+/// This excludes nodes that represent calls to precompiles, unless `include_precompiles` is set,
+/// and nodes that are not attached to the call graph.
+pub(crate) fn is_traceable(
+    nodes: &[CallTraceNode],
+    node: &CallTraceNode,
+    include_precompiles: bool,
+) -> bool {
+    if node.is_precompile() {
+        include_precompiles
+    } else {
+        is_attached(nodes, node)
+    }
+}
+
+/// Returns the traced child calls of all nodes, in the order they were called.
+///
+/// These are the [CallTraceNode::children] of a node, and its precompile calls if
+/// `include_precompiles` is set.
+pub(crate) fn traceable_children(
+    nodes: &[CallTraceNode],
+    include_precompiles: bool,
+) -> Vec<Vec<usize>> {
+    let mut children = vec![Vec::new(); nodes.len()];
+    // nodes are ordered by their index, so children are pushed in the order they were called
+    for node in nodes.iter().skip(1) {
+        if let Some(parent) = node.parent {
+            if is_traceable(nodes, node, include_precompiles) {
+                children[parent].push(node.idx);
+            }
+        }
+    }
+    children
+}
+
+/// Returns the parity `traceAddress` of the node at `idx`.
+///
+/// The `traceAddress` gives the exact location in the call trace: [index in root, index in first
+/// CALL, index in second CALL, …]. `children` are the [traceable_children] of all nodes.
+///
+/// # Panics
+///
+/// if the `idx` does not belong to a node
+///
+/// Note: if the node of `idx` is not traced, the returned trace address will be empty.
+pub(crate) fn trace_address(
+    nodes: &[CallTraceNode],
+    children: &[Vec<usize>],
+    include_precompiles: bool,
+    idx: usize,
+) -> Vec<usize> {
+    if idx == 0 {
+        // root call has empty traceAddress
+        return vec![];
+    }
+    let mut graph = vec![];
+    let mut node = &nodes[idx];
+    if !is_traceable(nodes, node, include_precompiles) {
+        return graph;
+    }
+    while let Some(parent) = node.parent {
+        // the index of the child call in the arena
+        let child_idx = node.idx;
+        node = &nodes[parent];
+        // find the index of the child call in the parent node
+        let call_idx = children[parent]
+            .iter()
+            .position(|child| *child == child_idx)
+            .expect("traceable child call exists in parent");
+        graph.push(call_idx);
+    }
+    graph.reverse();
+    graph
+}
+
+/// Returns the parity `traceAddress` of all nodes, see [trace_address].
+///
+/// CAUTION: This also includes nodes that are not traced, which have an empty trace address.
+pub(crate) fn trace_addresses(
+    nodes: &[CallTraceNode],
+    children: &[Vec<usize>],
+    include_precompiles: bool,
+) -> Vec<Vec<usize>> {
+    (0..nodes.len()).map(|idx| trace_address(nodes, children, include_precompiles, idx)).collect()
 }
 
 /// How to push a trace into the arena
@@ -105,3 +694,34 @@ impl PushTraceKind {
         matches!(self, Self::PushAndAttachToParent)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracing::types::CallKind;
+    use alloy_primitives::{bytes, hex};
+
+    #[test]
+    fn test_call_inputs_with_selector() {
+        let mut arena = CallTraceArena::default();
+        let selector = Selector::from(hex!("a9059cbb"));
+        let calls = [
+            (0, bytes!("d09de08a")),
+            (1, bytes!("a9059cbb0000000000000000000000000000000000000000000000000000000000000001")),
+            (2, bytes!("a9059cbb0000000000000000000000000000000000000000000000000000000000000002")),
+        ];
+        for (depth, data) in calls {
+            let trace = CallTrace { depth, kind: CallKind::Call, data, ..Default::default() };
+            arena.push_trace(0, PushTraceKind::PushAndAttachToParent, trace);
+        }
+
+        assert_eq!(arena.call_inputs_with_selector(selector).count(), 2);
+        assert_eq!(
+            arena.first_call_input_with_selector(selector),
+            Some(&bytes!(
+                "a9059cbb0000000000000000000000000000000000000000000000000000000000000001"
+            ))
+        );
+        assert!(arena.first_call_input_with_selector(Selector::ZERO).is_none());
+    }
+}
@@ -0,0 +1,60 @@
+//! Bigram tracing inspector
+//!
+//! The bigram tracer counts the pairs of consecutively executed opcodes in the lifetime of a
+//! transaction, which is useful to detect specific execution patterns. Pairs are counted in
+//! execution order, including across call boundaries, so the last opcode executed before a call
+//! forms a pair with the first opcode executed by the callee. The result can be rendered as a map
+//! of `FIRST-SECOND` to number of occurrences entries, for example:
+//!
+//! ```json
+//! {
+//!   "PUSH1-PUSH1": 4,
+//!   "PUSH1-MSTORE": 1,
+//!   "MSTORE-STOP": 1
+//! }
+//! ```
+
+use revm::{
+    interpreter::{Interpreter, OpCode},
+    Database, EvmContext, Inspector,
+};
+use std::collections::HashMap;
+
+/// Bigram tracing inspector that counts all pairs of consecutively executed opcodes.
+#[derive(Clone, Debug, Default)]
+pub struct BigramInspector {
+    /// The map of opcode pairs to number of occurrences entries
+    bigrams: HashMap<(OpCode, OpCode), u64>,
+    /// The previously executed opcode
+    prev: Option<OpCode>,
+}
+
+impl BigramInspector {
+    /// Returns the map of opcode pairs to number of occurrences entries
+    pub const fn bigrams(&self) -> &HashMap<(OpCode, OpCode), u64> {
+        &self.bigrams
+    }
+
+    /// Returns the bigrams as a JSON object of `FIRST-SECOND` to number of occurrences entries.
+    pub fn json(&self) -> serde_json::Value {
+        self.bigrams
+            .iter()
+            .map(|((first, second), count)| (format!("{first}-{second}"), (*count).into()))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+}
+
+impl<DB> Inspector<DB> for BigramInspector
+where
+    DB: Database,
+{
+    fn step(&mut self, interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+        // We always want an OpCode, even it is unknown because it could be an additional opcode
+        // that not a known constant.
+        let op = unsafe { OpCode::new_unchecked(interp.current_opcode()) };
+        if let Some(prev) = self.prev.replace(op) {
+            *self.bigrams.entry((prev, op)).or_default() += 1;
+        }
+    }
+}
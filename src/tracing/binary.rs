@@ -0,0 +1,605 @@
+//! Compact binary encoding of a [CallTraceArena].
+//!
+//! The encoding is meant for passing traces between services, e.g. over IPC, where JSON is too
+//! large. Unlike MessagePack it's not self-describing: the layout is fixed by the format version.
+//!
+//! # Layout
+//!
+//! The encoding starts with the magic bytes `RVTA` followed by the format version as a single
+//! byte, currently `1`. Then follow the spec id of the arena as a single byte (`0xff` if unknown)
+//! and the nodes of the arena.
+//!
+//! All values are encoded as follows, fields are encoded in the order they are declared in:
+//! - unsigned integers and lengths: unsigned LEB128
+//! - signed integers: zigzag encoded LEB128
+//! - `bool`, enums without data, [OpCode] and [InstructionResult]: a single byte
+//! - [Address], [B256]: 20 and 32 bytes
+//! - [U256]: the number of bytes as a single byte, followed by the big endian bytes without leading
+//!   zeros
+//! - bytes and strings: the length followed by the bytes
+//! - sequences: the number of elements followed by the elements
+//! - `Option`: `0` for `None`, `1` followed by the value for `Some`
+//! - [TraceMemberOrder]: `0` for logs, `1` for calls and `2` for steps, followed by the index
+//! - [Duration]: the seconds followed by the subsecond nanoseconds
+
+use crate::tracing::{
+    types::{
+        CallKind, CallLog, CallTrace, CallTraceNode, CallTraceStep, CalldataRead, DecodedCallData,
+        DecodedCallLog, DecodedCallTrace, RecordedMemory, StorageChange, StorageChangeReason,
+        TraceMemberOrder,
+    },
+    CallTraceArena,
+};
+use alloy_primitives::{Address, Bytes, LogData, B256, U256};
+use revm::{
+    interpreter::{InstructionResult, OpCode},
+    primitives::SpecId,
+};
+use std::time::Duration;
+
+/// The magic bytes every encoded arena starts with.
+const MAGIC: &[u8; 4] = b"RVTA";
+
+/// The current version of the encoding.
+const VERSION: u8 = 1;
+
+/// Marker for an arena without a spec id.
+const NO_SPEC_ID: u8 = 0xff;
+
+/// All [InstructionResult]s, used to decode them from their byte representation.
+const INSTRUCTION_RESULTS: &[InstructionResult] = &[
+    InstructionResult::Continue,
+    InstructionResult::Stop,
+    InstructionResult::Return,
+    InstructionResult::SelfDestruct,
+    InstructionResult::ReturnContract,
+    InstructionResult::Revert,
+    InstructionResult::CallTooDeep,
+    InstructionResult::OutOfFunds,
+    InstructionResult::CreateInitCodeStartingEF00,
+    InstructionResult::InvalidEOFInitCode,
+    InstructionResult::CallOrCreate,
+    InstructionResult::OutOfGas,
+    InstructionResult::MemoryOOG,
+    InstructionResult::MemoryLimitOOG,
+    InstructionResult::PrecompileOOG,
+    InstructionResult::InvalidOperandOOG,
+    InstructionResult::OpcodeNotFound,
+    InstructionResult::CallNotAllowedInsideStatic,
+    InstructionResult::StateChangeDuringStaticCall,
+    InstructionResult::InvalidEFOpcode,
+    InstructionResult::InvalidJump,
+    InstructionResult::NotActivated,
+    InstructionResult::StackUnderflow,
+    InstructionResult::StackOverflow,
+    InstructionResult::OutOfOffset,
+    InstructionResult::CreateCollision,
+    InstructionResult::OverflowPayment,
+    InstructionResult::PrecompileError,
+    InstructionResult::NonceOverflow,
+    InstructionResult::CreateContractSizeLimit,
+    InstructionResult::CreateContractStartingWithEF,
+    InstructionResult::CreateInitCodeSizeLimit,
+    InstructionResult::FatalExternalError,
+    InstructionResult::ReturnContractInNotInitEOF,
+    InstructionResult::EOFOpcodeDisabledInLegacy,
+    InstructionResult::EOFFunctionStackOverflow,
+    InstructionResult::EofAuxDataOverflow,
+    InstructionResult::EofAuxDataTooSmall,
+];
+
+/// Errors that can occur when decoding a binary encoded [CallTraceArena].
+#[derive(Clone, Debug, PartialEq, Eq, thiserror::Error)]
+pub enum BinaryDecodeError {
+    /// The input does not start with the magic bytes.
+    #[error("invalid magic bytes")]
+    InvalidMagic,
+    /// The input was encoded with an unsupported version of the format.
+    #[error("unsupported version {0}")]
+    UnsupportedVersion(u8),
+    /// The input ended before the arena was decoded.
+    #[error("unexpected end of input")]
+    UnexpectedEof,
+    /// The input contains a value that is out of range for its type.
+    #[error("invalid {0}")]
+    InvalidValue(&'static str),
+    /// The input contains bytes after the encoded arena.
+    #[error("trailing bytes after the encoded arena")]
+    TrailingBytes,
+}
+
+/// Encodes the arena, see the [module docs](self) for the layout.
+pub(crate) fn encode(arena: &CallTraceArena) -> Vec<u8> {
+    let mut enc = Encoder { buf: Vec::new() };
+    enc.buf.extend_from_slice(MAGIC);
+    enc.u8(VERSION);
+    enc.u8(arena.spec_id.map_or(NO_SPEC_ID, |spec_id| spec_id as u8));
+    enc.seq(&arena.arena, Encoder::node);
+    enc.buf
+}
+
+/// Decodes an arena that was encoded with [encode].
+pub(crate) fn decode(bytes: &[u8]) -> Result<CallTraceArena, BinaryDecodeError> {
+    let mut dec = Decoder { buf: bytes };
+    if dec.take(MAGIC.len())? != MAGIC {
+        return Err(BinaryDecodeError::InvalidMagic);
+    }
+    let version = dec.u8()?;
+    if version != VERSION {
+        return Err(BinaryDecodeError::UnsupportedVersion(version));
+    }
+    let spec_id = match dec.u8()? {
+        NO_SPEC_ID => None,
+        spec_id => {
+            Some(SpecId::try_from_u8(spec_id).ok_or(BinaryDecodeError::InvalidValue("spec id"))?)
+        }
+    };
+    let arena = dec.seq(Decoder::node)?;
+    if !dec.buf.is_empty() {
+        return Err(BinaryDecodeError::TrailingBytes);
+    }
+    Ok(CallTraceArena { arena, spec_id })
+}
+
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn u64(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.u8(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.u8(value as u8);
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.u64(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn usize(&mut self, value: usize) {
+        self.u64(value as u64);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.usize(value.len());
+        self.buf.extend_from_slice(value);
+    }
+
+    fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    fn address(&mut self, value: &Address) {
+        self.buf.extend_from_slice(value.as_slice());
+    }
+
+    fn b256(&mut self, value: &B256) {
+        self.buf.extend_from_slice(value.as_slice());
+    }
+
+    fn u256(&mut self, value: &U256) {
+        let bytes = value.to_be_bytes::<32>();
+        let len = value.byte_len();
+        self.u8(len as u8);
+        self.buf.extend_from_slice(&bytes[32 - len..]);
+    }
+
+    fn option<T>(&mut self, value: Option<&T>, f: impl FnOnce(&mut Self, &T)) {
+        match value {
+            Some(value) => {
+                self.u8(1);
+                f(self, value);
+            }
+            None => self.u8(0),
+        }
+    }
+
+    fn seq<T>(&mut self, values: &[T], mut f: impl FnMut(&mut Self, &T)) {
+        self.usize(values.len());
+        for value in values {
+            f(self, value);
+        }
+    }
+
+    fn node(&mut self, node: &CallTraceNode) {
+        let CallTraceNode { parent, children, idx, trace, logs, ordering } = node;
+        self.option(parent.as_ref(), |enc, parent| enc.usize(*parent));
+        self.seq(children, |enc, child| enc.usize(*child));
+        self.usize(*idx);
+        self.trace(trace);
+        self.seq(logs, Self::log);
+        self.seq(ordering, |enc, member| {
+            let (tag, idx) = match member {
+                TraceMemberOrder::Log(idx) => (0, idx),
+                TraceMemberOrder::Call(idx) => (1, idx),
+                TraceMemberOrder::Step(idx) => (2, idx),
+            };
+            enc.u8(tag);
+            enc.usize(*idx);
+        });
+    }
+
+    fn trace(&mut self, trace: &CallTrace) {
+        let CallTrace {
+            depth,
+            success,
+            caller,
+            origin,
+            address,
+            maybe_precompile,
+            selfdestruct_refund_target,
+            selfdestruct_transferred_value,
+            selfdestruct_gas_refund,
+            kind,
+            create2_salt,
+            value,
+            data,
+            output,
+            init_code_hash,
+            gas_used,
+            gas_limit,
+            interpreter_gas_limit,
+            caller_gas_remaining,
+            delegated_to,
+            status,
+            is_eof,
+            op_count,
+            memory_gas,
+            max_memory,
+            log_count,
+            log_data_size,
+            steps,
+            decoded,
+        } = trace;
+        self.usize(*depth);
+        self.bool(*success);
+        self.address(caller);
+        self.address(origin);
+        self.address(address);
+        self.option(maybe_precompile.as_ref(), |enc, value| enc.bool(*value));
+        self.option(selfdestruct_refund_target.as_ref(), Self::address);
+        self.option(selfdestruct_transferred_value.as_ref(), Self::u256);
+        self.u64(*selfdestruct_gas_refund);
+        self.u8(*kind as u8);
+        self.option(create2_salt.as_ref(), Self::u256);
+        self.u256(value);
+        self.bytes(data);
+        self.bytes(output);
+        self.option(init_code_hash.as_ref(), Self::b256);
+        self.u64(*gas_used);
+        self.u64(*gas_limit);
+        self.u64(*interpreter_gas_limit);
+        self.option(caller_gas_remaining.as_ref(), |enc, gas| enc.u64(*gas));
+        self.option(delegated_to.as_ref(), Self::address);
+        self.u8(*status as u8);
+        self.bool(*is_eof);
+        self.u64(*op_count);
+        self.u64(*memory_gas);
+        self.u64(*max_memory);
+        self.u64(*log_count);
+        self.u64(*log_data_size);
+        self.seq(steps, Self::step);
+
+        let DecodedCallTrace { label, return_data, call_data } = decoded;
+        self.option(label.as_ref(), |enc, value| enc.string(value));
+        self.option(return_data.as_ref(), |enc, value| enc.string(value));
+        self.option(call_data.as_ref(), |enc, DecodedCallData { signature, args }| {
+            enc.string(signature);
+            enc.seq(args, |enc, arg| enc.string(arg));
+        });
+    }
+
+    fn log(&mut self, log: &CallLog) {
+        let CallLog { raw_log, decoded: DecodedCallLog { name, params } } = log;
+        self.seq(raw_log.topics(), Self::b256);
+        self.bytes(&raw_log.data);
+        self.option(name.as_ref(), |enc, value| enc.string(value));
+        self.option(params.as_ref(), |enc, params| {
+            enc.seq(params, |enc, (name, value)| {
+                enc.string(name);
+                enc.string(value);
+            })
+        });
+    }
+
+    fn step(&mut self, step: &CallTraceStep) {
+        let CallTraceStep {
+            depth,
+            pc,
+            op,
+            contract,
+            stack,
+            push_stack,
+            memory,
+            returndata,
+            calldata_read,
+            gas_remaining,
+            gas_refund_counter,
+            gas_used,
+            gas_cost,
+            refund_delta,
+            storage_change,
+            status,
+            reverts_call,
+            duration,
+        } = step;
+        self.u64(*depth);
+        self.usize(*pc);
+        self.u8(op.get());
+        self.address(contract);
+        self.option(stack.as_ref(), |enc, stack| enc.seq(stack, Self::u256));
+        self.option(push_stack.as_ref(), |enc, stack| enc.seq(stack, Self::u256));
+        self.option(memory.as_ref(), |enc, memory| enc.bytes(memory.as_bytes()));
+        self.bytes(returndata);
+        self.option(calldata_read.as_ref(), |enc, CalldataRead { offset, len }| {
+            enc.u256(offset);
+            enc.u256(len);
+        });
+        self.u64(*gas_remaining);
+        self.u64(*gas_refund_counter);
+        self.u64(*gas_used);
+        self.u64(*gas_cost);
+        self.i64(*refund_delta);
+        self.option(storage_change.as_ref(), |enc, change| {
+            let StorageChange { key, value, had_value, reason } = change;
+            enc.u256(key);
+            enc.u256(value);
+            enc.option(had_value.as_ref(), Self::u256);
+            enc.u8(*reason as u8);
+        });
+        self.u8(*status as u8);
+        self.bool(*reverts_call);
+        self.option(duration.as_ref(), |enc, duration| {
+            enc.u64(duration.as_secs());
+            enc.u64(duration.subsec_nanos() as u64);
+        });
+    }
+}
+
+struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], BinaryDecodeError> {
+        if self.buf.len() < len {
+            return Err(BinaryDecodeError::UnexpectedEof);
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, BinaryDecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, BinaryDecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(BinaryDecodeError::InvalidValue("bool")),
+        }
+    }
+
+    fn u64(&mut self) -> Result<u64, BinaryDecodeError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            let bits = (byte & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                return Err(BinaryDecodeError::InvalidValue("integer"));
+            }
+            value |= bits << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BinaryDecodeError::InvalidValue("integer"))
+    }
+
+    fn i64(&mut self) -> Result<i64, BinaryDecodeError> {
+        let value = self.u64()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn usize(&mut self) -> Result<usize, BinaryDecodeError> {
+        self.u64()?.try_into().map_err(|_| BinaryDecodeError::InvalidValue("integer"))
+    }
+
+    fn bytes(&mut self) -> Result<Bytes, BinaryDecodeError> {
+        let len = self.usize()?;
+        Ok(Bytes::copy_from_slice(self.take(len)?))
+    }
+
+    fn string(&mut self) -> Result<String, BinaryDecodeError> {
+        let len = self.usize()?;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| BinaryDecodeError::InvalidValue("string"))
+    }
+
+    fn address(&mut self) -> Result<Address, BinaryDecodeError> {
+        Ok(Address::from_slice(self.take(20)?))
+    }
+
+    fn b256(&mut self) -> Result<B256, BinaryDecodeError> {
+        Ok(B256::from_slice(self.take(32)?))
+    }
+
+    fn u256(&mut self) -> Result<U256, BinaryDecodeError> {
+        let len = self.u8()? as usize;
+        if len > 32 {
+            return Err(BinaryDecodeError::InvalidValue("uint256"));
+        }
+        Ok(U256::from_be_slice(self.take(len)?))
+    }
+
+    fn option<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, BinaryDecodeError>,
+    ) -> Result<Option<T>, BinaryDecodeError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => f(self).map(Some),
+            _ => Err(BinaryDecodeError::InvalidValue("option")),
+        }
+    }
+
+    fn seq<T>(
+        &mut self,
+        mut f: impl FnMut(&mut Self) -> Result<T, BinaryDecodeError>,
+    ) -> Result<Vec<T>, BinaryDecodeError> {
+        let len = self.usize()?;
+        // every element takes at least one byte, this avoids huge allocations for invalid input
+        let mut values = Vec::with_capacity(len.min(self.buf.len()));
+        for _ in 0..len {
+            values.push(f(self)?);
+        }
+        Ok(values)
+    }
+
+    fn instruction_result(&mut self) -> Result<InstructionResult, BinaryDecodeError> {
+        let byte = self.u8()?;
+        INSTRUCTION_RESULTS
+            .iter()
+            .find(|result| **result as u8 == byte)
+            .copied()
+            .ok_or(BinaryDecodeError::InvalidValue("instruction result"))
+    }
+
+    fn node(&mut self) -> Result<CallTraceNode, BinaryDecodeError> {
+        Ok(CallTraceNode {
+            parent: self.option(Self::usize)?,
+            children: self.seq(Self::usize)?,
+            idx: self.usize()?,
+            trace: self.trace()?,
+            logs: self.seq(Self::log)?,
+            ordering: self.seq(|dec| {
+                let tag = dec.u8()?;
+                let idx = dec.usize()?;
+                match tag {
+                    0 => Ok(TraceMemberOrder::Log(idx)),
+                    1 => Ok(TraceMemberOrder::Call(idx)),
+                    2 => Ok(TraceMemberOrder::Step(idx)),
+                    _ => Err(BinaryDecodeError::InvalidValue("trace member order")),
+                }
+            })?,
+        })
+    }
+
+    fn trace(&mut self) -> Result<CallTrace, BinaryDecodeError> {
+        Ok(CallTrace {
+            depth: self.usize()?,
+            success: self.bool()?,
+            caller: self.address()?,
+            origin: self.address()?,
+            address: self.address()?,
+            maybe_precompile: self.option(Self::bool)?,
+            selfdestruct_refund_target: self.option(Self::address)?,
+            selfdestruct_transferred_value: self.option(Self::u256)?,
+            selfdestruct_gas_refund: self.u64()?,
+            kind: match self.u8()? {
+                0 => CallKind::Call,
+                1 => CallKind::StaticCall,
+                2 => CallKind::CallCode,
+                3 => CallKind::DelegateCall,
+                4 => CallKind::AuthCall,
+                5 => CallKind::Create,
+                6 => CallKind::Create2,
+                _ => return Err(BinaryDecodeError::InvalidValue("call kind")),
+            },
+            create2_salt: self.option(Self::u256)?,
+            value: self.u256()?,
+            data: self.bytes()?,
+            output: self.bytes()?,
+            init_code_hash: self.option(Self::b256)?,
+            gas_used: self.u64()?,
+            gas_limit: self.u64()?,
+            interpreter_gas_limit: self.u64()?,
+            caller_gas_remaining: self.option(Self::u64)?,
+            delegated_to: self.option(Self::address)?,
+            status: self.instruction_result()?,
+            is_eof: self.bool()?,
+            op_count: self.u64()?,
+            memory_gas: self.u64()?,
+            max_memory: self.u64()?,
+            log_count: self.u64()?,
+            log_data_size: self.u64()?,
+            steps: self.seq(Self::step)?,
+            decoded: DecodedCallTrace {
+                label: self.option(Self::string)?,
+                return_data: self.option(Self::string)?,
+                call_data: self.option(|dec| {
+                    Ok(DecodedCallData { signature: dec.string()?, args: dec.seq(Self::string)? })
+                })?,
+            },
+        })
+    }
+
+    fn log(&mut self) -> Result<CallLog, BinaryDecodeError> {
+        let topics = self.seq(Self::b256)?;
+        let data = self.bytes()?;
+        Ok(CallLog {
+            raw_log: LogData::new_unchecked(topics, data),
+            decoded: DecodedCallLog {
+                name: self.option(Self::string)?,
+                params: self.option(|dec| dec.seq(|dec| Ok((dec.string()?, dec.string()?))))?,
+            },
+        })
+    }
+
+    fn step(&mut self) -> Result<CallTraceStep, BinaryDecodeError> {
+        Ok(CallTraceStep {
+            depth: self.u64()?,
+            pc: self.usize()?,
+            // SAFETY: unknown opcodes are recorded as well, see `TracingInspector::start_step`
+            op: unsafe { OpCode::new_unchecked(self.u8()?) },
+            contract: self.address()?,
+            stack: self.option(|dec| dec.seq(Self::u256))?,
+            push_stack: self.option(|dec| dec.seq(Self::u256))?,
+            memory: self.option(|dec| Ok(RecordedMemory(dec.bytes()?)))?,
+            returndata: self.bytes()?,
+            calldata_read: self
+                .option(|dec| Ok(CalldataRead { offset: dec.u256()?, len: dec.u256()? }))?,
+            gas_remaining: self.u64()?,
+            gas_refund_counter: self.u64()?,
+            gas_used: self.u64()?,
+            gas_cost: self.u64()?,
+            refund_delta: self.i64()?,
+            storage_change: self.option(|dec| {
+                Ok(StorageChange {
+                    key: dec.u256()?,
+                    value: dec.u256()?,
+                    had_value: dec.option(Self::u256)?,
+                    reason: match dec.u8()? {
+                        0 => StorageChangeReason::SLOAD,
+                        1 => StorageChangeReason::SSTORE,
+                        2 => StorageChangeReason::TLOAD,
+                        3 => StorageChangeReason::TSTORE,
+                        _ => return Err(BinaryDecodeError::InvalidValue("storage change reason")),
+                    },
+                })
+            })?,
+            status: self.instruction_result()?,
+            reverts_call: self.bool()?,
+            duration: self.option(|dec| {
+                let secs = dec.u64()?;
+                let nanos = dec.u64()?;
+                if nanos >= 1_000_000_000 {
+                    return Err(BinaryDecodeError::InvalidValue("duration"));
+                }
+                Ok(Duration::new(secs, nanos as u32))
+            })?,
+        })
+    }
+}
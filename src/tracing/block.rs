@@ -0,0 +1,141 @@
+//! Helpers for tracing all transactions of a block with a single [TracingInspector].
+
+use crate::tracing::{
+    GethTraceBuilder, ParityTraceBuilder, TracingInspector, TracingInspectorConfig,
+};
+use alloy_rpc_types::{
+    trace::{
+        geth::{CallConfig, GethTrace, TraceResult},
+        parity::LocalizedTransactionTrace,
+    },
+    TransactionInfo,
+};
+use revm::{
+    inspector_handle_register,
+    primitives::{EVMError, EnvWithHandlerCfg, ExecutionResult, ResultAndState, TxEnv},
+    Database, DatabaseCommit,
+};
+
+/// Traces a sequence of transactions, e.g. all transactions of a block, with a single
+/// [TracingInspector].
+///
+/// The inspector is [fused](TracingInspector::fuse) before every transaction, so the allocations
+/// of the previous transaction are reused instead of creating a new inspector per transaction.
+/// The state changes of every transaction are committed to the database before the next
+/// transaction is executed.
+#[derive(Clone, Debug, Default)]
+pub struct BlockTracer {
+    inspector: TracingInspector,
+}
+
+impl BlockTracer {
+    /// Returns a new instance for the given config
+    pub fn new(config: TracingInspectorConfig) -> Self {
+        Self { inspector: TracingInspector::new(config) }
+    }
+
+    /// Returns the inspector that is used to trace the transactions.
+    ///
+    /// After tracing this holds the traces of the last transaction.
+    pub const fn inspector(&self) -> &TracingInspector {
+        &self.inspector
+    }
+
+    /// Consumes the type and returns the inspector.
+    pub fn into_inspector(self) -> TracingInspector {
+        self.inspector
+    }
+
+    /// Executes all transactions on top of the given env and returns the parity traces of all
+    /// transactions, as returned by `trace_block`.
+    ///
+    /// The [TransactionInfo] of every transaction is used to localize its traces.
+    pub fn trace_block_parity<DB, I>(
+        &mut self,
+        db: &mut DB,
+        env: EnvWithHandlerCfg,
+        txs: I,
+    ) -> Result<Vec<LocalizedTransactionTrace>, EVMError<DB::Error>>
+    where
+        DB: Database + DatabaseCommit,
+        I: IntoIterator<Item = (TxEnv, TransactionInfo)>,
+    {
+        let mut traces = Vec::new();
+        self.trace_block_with(db, env, txs, |inspector, _, info| {
+            let builder = ParityTraceBuilder::new(
+                inspector.traces.arena.clone(),
+                inspector.traces.spec_id,
+                inspector.config.clone(),
+            );
+            traces.extend(builder.into_localized_transaction_traces_iter(info));
+        })?;
+        Ok(traces)
+    }
+
+    /// Executes all transactions on top of the given env and returns the geth call traces of all
+    /// transactions, as returned by `debug_traceBlock` with the `callTracer`.
+    ///
+    /// The hash of every transaction's [TransactionInfo] is attached to its result.
+    pub fn trace_block_geth<DB, I>(
+        &mut self,
+        db: &mut DB,
+        env: EnvWithHandlerCfg,
+        txs: I,
+        opts: CallConfig,
+    ) -> Result<Vec<TraceResult>, EVMError<DB::Error>>
+    where
+        DB: Database + DatabaseCommit,
+        I: IntoIterator<Item = (TxEnv, TransactionInfo)>,
+    {
+        let mut results = Vec::new();
+        self.trace_block_with(db, env, txs, |inspector, result, info| {
+            let builder =
+                GethTraceBuilder::new(inspector.traces.arena.clone(), inspector.config.clone())
+                    .with_spec_id(inspector.traces.spec_id)
+                    .with_steps_truncated(inspector.steps_truncated);
+            let frame = builder.geth_call_traces(opts, result.gas_used());
+            results.push(TraceResult::Success {
+                result: GethTrace::CallTracer(frame),
+                tx_hash: info.hash,
+            });
+        })?;
+        Ok(results)
+    }
+
+    /// Executes all transactions and invokes `f` with the inspector after every transaction.
+    ///
+    /// The root trace's gas used is set to the gas used by the transaction before `f` is called.
+    fn trace_block_with<DB, I, F>(
+        &mut self,
+        db: &mut DB,
+        env: EnvWithHandlerCfg,
+        txs: I,
+        mut f: F,
+    ) -> Result<(), EVMError<DB::Error>>
+    where
+        DB: Database + DatabaseCommit,
+        I: IntoIterator<Item = (TxEnv, TransactionInfo)>,
+        F: FnMut(&TracingInspector, &ExecutionResult, TransactionInfo),
+    {
+        let mut evm = revm::Evm::builder()
+            .with_db(db)
+            .with_external_context(&mut self.inspector)
+            .with_env_with_handler_cfg(env)
+            .append_handler_register(inspector_handle_register)
+            .build();
+
+        for (tx, info) in txs {
+            *evm.tx_mut() = tx;
+            evm.context.external.fuse();
+
+            let ResultAndState { result, state } = evm.transact()?;
+            evm.db_mut().commit(state);
+
+            let inspector = &mut *evm.context.external;
+            inspector.set_transaction_gas_used(result.gas_used());
+            f(inspector, &result, info);
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,8 @@
 //! Geth trace builder
 
 use crate::tracing::{
-    types::{CallTraceNode, CallTraceStepStackItem},
-    utils::load_account_code,
+    types::{CallTraceNode, CallTraceStepStackItem, TraceMemberOrder},
+    utils::{apply_labels, load_account_code},
     TracingInspectorConfig,
 };
 use alloy_primitives::{Address, Bytes, B256, U256};
@@ -10,7 +10,11 @@ use alloy_rpc_types::trace::geth::{
     AccountChangeKind, AccountState, CallConfig, CallFrame, DefaultFrame, DiffMode,
     GethDefaultTracingOptions, PreStateConfig, PreStateFrame, PreStateMode, StructLog,
 };
-use revm::{db::DatabaseRef, primitives::ResultAndState};
+use revm::{
+    db::DatabaseRef,
+    precompile::{PrecompileSpecId, Precompiles},
+    primitives::{ResultAndState, SpecId},
+};
 use std::collections::{BTreeMap, HashMap, VecDeque};
 
 /// A type for creating geth style traces
@@ -18,63 +22,186 @@ use std::collections::{BTreeMap, HashMap, VecDeque};
 pub struct GethTraceBuilder {
     /// Recorded trace nodes.
     nodes: Vec<CallTraceNode>,
+    /// The spec id the traces were recorded with.
+    spec_id: Option<SpecId>,
     /// How the traces were recorded
     _config: TracingInspectorConfig,
+    /// Where selfdestruct frames are placed in the call tracer output.
+    selfdestruct_placement: SelfdestructPlacement,
+    /// Whether steps were dropped while recording the traces.
+    steps_truncated: bool,
+    /// Whether recorded but excluded precompile calls are included in the call tracer output.
+    include_precompiles: bool,
+    /// Whether all calls to precompiles are excluded from the call tracer output.
+    exclude_precompiles: bool,
+    /// Whether precompile accounts are excluded from the prestate tracer output.
+    exclude_prestate_precompiles: bool,
+    /// Whether the memory size is included in the struct logs.
+    include_memory_size: bool,
 }
 
 impl GethTraceBuilder {
     /// Returns a new instance of the builder
     pub fn new(nodes: Vec<CallTraceNode>, _config: TracingInspectorConfig) -> Self {
-        Self { nodes, _config }
+        Self {
+            nodes,
+            spec_id: None,
+            _config,
+            selfdestruct_placement: Default::default(),
+            steps_truncated: false,
+            include_precompiles: false,
+            exclude_precompiles: false,
+            exclude_prestate_precompiles: false,
+            include_memory_size: false,
+        }
+    }
+
+    /// Sets the spec id the traces were recorded with.
+    ///
+    /// This is set by
+    /// [TracingInspector::into_geth_builder](crate::tracing::TracingInspector::into_geth_builder).
+    pub const fn with_spec_id(mut self, spec_id: Option<SpecId>) -> Self {
+        self.spec_id = spec_id;
+        self
+    }
+
+    /// Returns the spec id the traces were recorded with, if known.
+    pub const fn spec_id(&self) -> Option<SpecId> {
+        self.spec_id
+    }
+
+    /// Configures where selfdestruct frames are placed in the call tracer output.
+    ///
+    /// See [SelfdestructPlacement].
+    pub const fn with_selfdestruct_placement(mut self, placement: SelfdestructPlacement) -> Self {
+        self.selfdestruct_placement = placement;
+        self
+    }
+
+    /// Marks whether steps were dropped while recording the traces, for example because
+    /// [TracingInspectorConfig::max_steps] was reached.
+    pub const fn with_steps_truncated(mut self, steps_truncated: bool) -> Self {
+        self.steps_truncated = steps_truncated;
+        self
+    }
+
+    /// Configures whether calls to precompiles are included in the call tracer output of
+    /// [Self::geth_call_traces].
+    ///
+    /// Calls to precompiles are recorded but not attached to their parent call if the traces were
+    /// recorded with [TracingInspectorConfig::exclude_precompile_calls]. If this is set, these
+    /// calls are added to the call frame of their parent call.
+    pub const fn with_include_precompiles(mut self, include_precompiles: bool) -> Self {
+        self.include_precompiles = include_precompiles;
+        self
+    }
+
+    /// Configures whether calls to precompiles are excluded from the call tracer output of
+    /// [Self::geth_call_traces], regardless of how the traces were recorded.
+    ///
+    /// This is the counterpart of [TracingInspectorConfig::exclude_precompile_calls] for already
+    /// recorded traces, since [CallConfig] has no such option. Like at recording time, calls
+    /// that transfer value are kept. This takes precedence over [Self::with_include_precompiles].
+    pub const fn with_exclude_precompiles(mut self, exclude_precompiles: bool) -> Self {
+        self.exclude_precompiles = exclude_precompiles;
+        self
+    }
+
+    /// Configures whether precompile accounts are excluded from the output of
+    /// [Self::geth_prestate_traces].
+    ///
+    /// Precompiles that received a value transfer are still included.
+    pub const fn with_exclude_prestate_precompiles(mut self, exclude: bool) -> Self {
+        self.exclude_prestate_precompiles = exclude;
+        self
+    }
+
+    /// Configures whether the struct logs of [Self::geth_traces] include the memory size,
+    /// `memSize`.
+    ///
+    /// The memory size is only included if memory is enabled in the [GethDefaultTracingOptions] and
+    /// was recorded, see [TracingInspectorConfig::record_memory_snapshots].
+    pub const fn with_include_memory_size(mut self, include_memory_size: bool) -> Self {
+        self.include_memory_size = include_memory_size;
+        self
+    }
+
+    /// Sets the decoded label of all calls to an address in the given map.
+    ///
+    /// See also [CallTraceArena::apply_labels](crate::tracing::CallTraceArena::apply_labels).
+    pub fn with_labels(mut self, labels: &HashMap<Address, String>) -> Self {
+        apply_labels(&mut self.nodes, labels);
+        self
+    }
+
+    /// Returns the recorded trace nodes.
+    pub fn nodes(&self) -> &[CallTraceNode] {
+        &self.nodes
+    }
+
+    /// Returns the gas used by the execution of the top-level call.
+    ///
+    /// Unlike the receipt gas used, i.e. [ExecutionResult::gas_used], this excludes the intrinsic
+    /// gas of the transaction. It is reduced by the gas refund, but the refund is capped relative
+    /// to the execution gas only, so with a refund this differs from the receipt gas used by more
+    /// than the intrinsic gas.
+    ///
+    /// Note: this returns the gas used of the root trace, so if it was overwritten with
+    /// [TracingInspector::set_transaction_gas_used](crate::tracing::TracingInspector::set_transaction_gas_used)
+    /// this is the receipt gas used instead.
+    ///
+    /// [ExecutionResult::gas_used]: revm::primitives::ExecutionResult::gas_used
+    pub fn execution_gas_used(&self) -> u64 {
+        self.nodes.first().map(|node| node.trace.gas_used).unwrap_or_default()
+    }
+
+    /// Returns true if the recorded steps are incomplete, in which case the struct logs of
+    /// [Self::geth_traces] are truncated.
+    pub const fn steps_truncated(&self) -> bool {
+        self.steps_truncated
     }
 
-    /// Fill in the geth trace with all steps of the trace and its children traces in the order they
-    /// appear in the transaction.
-    fn fill_geth_trace(
+    /// Returns an iterator over the struct logs of all steps in the order they appear in the
+    /// transaction.
+    ///
+    /// Unlike [Self::geth_traces], this creates the struct logs lazily, e.g. for streaming them
+    /// without holding all of them in memory.
+    ///
+    /// If [GethDefaultTracingOptions::limit] is set to a non-zero value, only the first `limit`
+    /// steps are returned, like geth does.
+    ///
+    /// The storage of `TLOAD` and `TSTORE` struct logs is the accessed transient storage of the
+    /// contract, which is tracked separately from the contract storage.
+    ///
+    /// The struct logs end early if [TracingInspectorConfig::max_steps] was reached while
+    /// tracing, check [Self::steps_truncated] to tell this apart from a complete trace.
+    pub fn struct_logs_iter(
         &self,
-        main_trace_node: &CallTraceNode,
-        opts: &GethDefaultTracingOptions,
-        storage: &mut HashMap<Address, BTreeMap<B256, B256>>,
-        struct_logs: &mut Vec<StructLog>,
-    ) {
+        opts: GethDefaultTracingOptions,
+    ) -> impl Iterator<Item = StructLog> + '_ {
         // A stack with all the steps of the trace and all its children's steps.
         // This is used to process the steps in the order they appear in the transactions.
         // Steps are grouped by their Call Trace Node, in order to process them all in the order
         // they appear in the transaction, we need to process steps of call nodes when they appear.
         // When we find a call step, we push all the steps of the child trace on the stack, so they
         // are processed next. The very next step is the last item on the stack
-        let mut step_stack = VecDeque::with_capacity(main_trace_node.trace.steps.len());
-
-        main_trace_node.push_steps_on_stack(&mut step_stack);
-
-        // Iterate over the steps inside the given trace
-        while let Some(CallTraceStepStackItem { trace_node, step, call_child_id }) =
-            step_stack.pop_back()
-        {
-            let mut log = step.convert_to_geth_struct_log(opts);
-
-            // Fill in memory and storage depending on the options
-            if opts.is_storage_enabled() {
-                let contract_storage = storage.entry(step.contract).or_default();
-                if let Some(change) = step.storage_change {
-                    contract_storage.insert(change.key.into(), change.value.into());
-                    log.storage = Some(contract_storage.clone());
-                }
-            }
-
-            if opts.is_return_data_enabled() {
-                log.return_data = Some(trace_node.trace.output.clone());
-            }
-
-            // Add step to geth trace
-            struct_logs.push(log);
+        let mut step_stack = VecDeque::new();
+        if let Some(main_trace_node) = self.nodes.first() {
+            step_stack.reserve(main_trace_node.trace.steps.len());
+            main_trace_node.push_steps_on_stack(&mut step_stack);
+        }
 
-            // If the step is a call, we first push all the steps of the child trace on the stack,
-            // so they are processed next
-            if let Some(call_child_id) = call_child_id {
-                let child_trace = &self.nodes[call_child_id];
-                child_trace.push_steps_on_stack(&mut step_stack);
-            }
+        // a limit of zero means no limit
+        let limit =
+            opts.limit.filter(|limit| *limit > 0).map_or(usize::MAX, |limit| limit as usize);
+
+        StructLogIter {
+            builder: self,
+            opts,
+            step_stack,
+            storage: HashMap::new(),
+            transient_storage: HashMap::new(),
+            remaining: limit,
         }
     }
 
@@ -82,6 +209,10 @@ impl GethTraceBuilder {
     ///
     /// This expects the gas used and return value for the
     /// [ExecutionResult](revm::primitives::ExecutionResult) of the executed transaction.
+    ///
+    /// [DefaultFrame] has no field to mark incomplete struct logs, so if
+    /// [TracingInspectorConfig::max_steps] was configured, callers must check
+    /// [Self::steps_truncated] to know whether the struct logs are truncated.
     pub fn geth_traces(
         &self,
         receipt_gas_used: u64,
@@ -95,9 +226,7 @@ impl GethTraceBuilder {
         let main_trace_node = &self.nodes[0];
         let main_trace = &main_trace_node.trace;
 
-        let mut struct_logs = Vec::new();
-        let mut storage = HashMap::new();
-        self.fill_geth_trace(main_trace_node, &opts, &mut storage, &mut struct_logs);
+        let struct_logs = self.struct_logs_iter(opts).collect();
 
         DefaultFrame {
             // If the top-level trace succeeded, then it was a success
@@ -112,9 +241,11 @@ impl GethTraceBuilder {
     ///
     /// This decodes all call frames from the recorded traces.
     ///
-    /// This expects the gas used and return value for the
-    /// [ExecutionResult](revm::primitives::ExecutionResult) of the executed transaction.
-    pub fn geth_call_traces(&self, opts: CallConfig, gas_used: u64) -> CallFrame {
+    /// This expects the receipt gas used of the
+    /// [ExecutionResult](revm::primitives::ExecutionResult) of the executed transaction, which
+    /// includes the intrinsic gas. Only the root frame reports this value, all subcalls report the
+    /// gas used by their execution, see also [Self::execution_gas_used].
+    pub fn geth_call_traces(&self, opts: CallConfig, receipt_gas_used: u64) -> CallFrame {
         if self.nodes.is_empty() {
             return Default::default();
         }
@@ -123,10 +254,11 @@ impl GethTraceBuilder {
         // first fill up the root
         let main_trace_node = &self.nodes[0];
         let mut root_call_frame = main_trace_node.geth_empty_call_frame(include_logs);
-        root_call_frame.gas_used = U256::from(gas_used);
+        root_call_frame.gas_used = U256::from(receipt_gas_used);
 
         // selfdestructs are not recorded as individual call traces but are derived from
-        // the call trace and are added as additional `CallFrame` objects to the parent call
+        // the call trace and are added as additional `CallFrame` objects to the parent call.
+        // The root call has no parent, so this is always a child of the root frame.
         if let Some(selfdestruct) = main_trace_node.geth_selfdestruct_call_trace() {
             root_call_frame.calls.push(selfdestruct);
         }
@@ -139,31 +271,58 @@ impl GethTraceBuilder {
         // traces are identified by their index in the arena
         // so we can populate the call frame tree by walking up the call tree
         let mut call_frames = Vec::with_capacity(self.nodes.len());
-        call_frames.push((0, root_call_frame));
+        call_frames.push((0, root_call_frame, None));
 
         for (idx, trace) in self.nodes.iter().enumerate().skip(1) {
+            // include logs only if call and all its parents were successful
+            let include_logs = include_logs && !self.call_or_parent_failed(trace);
+            let mut call_frame = trace.geth_empty_call_frame(include_logs);
+
             // selfdestructs are not recorded as individual call traces but are derived from
-            // the call trace and are added as additional `CallFrame` objects to the parent call
+            // the call trace and are added as additional `CallFrame` objects
+            let mut inline_selfdestruct = None;
             if let Some(selfdestruct) = trace.geth_selfdestruct_call_trace() {
-                call_frames.last_mut().expect("not empty").1.calls.push(selfdestruct);
+                match self.selfdestruct_placement {
+                    SelfdestructPlacement::Child => call_frame.calls.push(selfdestruct),
+                    SelfdestructPlacement::Inline => inline_selfdestruct = Some(selfdestruct),
+                }
             }
 
-            // include logs only if call and all its parents were successful
-            let include_logs = include_logs && !self.call_or_parent_failed(trace);
-            call_frames.push((idx, trace.geth_empty_call_frame(include_logs)));
+            call_frames.push((idx, call_frame, inline_selfdestruct));
+        }
+
+        let mut attached = self.attached_nodes(self.include_precompiles);
+        if self.exclude_precompiles {
+            let precompiles = self.precompiles();
+            for node in self.nodes.iter().skip(1) {
+                if node.is_precompile()
+                    || (precompiles.contains(&node.trace.address)
+                        && node.trace.value.is_zero()
+                        && !node.kind().is_any_create())
+                {
+                    attached[node.idx] = false;
+                }
+            }
         }
 
         // pop the _children_ calls frame and move it to the parent
         // this will roll up the child frames to their parent; this works because `child idx >
         // parent idx`
         loop {
-            let (idx, call) = call_frames.pop().expect("call frames not empty");
+            let (idx, call, inline_selfdestruct) =
+                call_frames.pop().expect("call frames not empty");
             let node = &self.nodes[idx];
             if let Some(parent) = node.parent {
+                if !attached[idx] {
+                    continue;
+                }
                 let parent_frame = &mut call_frames[parent];
                 // we need to ensure that calls are in order they are called: the last child node is
                 // the last call, but since we walk up the tree, we need to always
                 // insert at position 0
+                if let Some(selfdestruct) = inline_selfdestruct {
+                    parent_frame.1.calls.insert(0, selfdestruct);
+                }
                 parent_frame.1.calls.insert(0, call);
             } else {
                 debug_assert!(call_frames.is_empty(), "only one root node has no parent");
@@ -172,6 +331,99 @@ impl GethTraceBuilder {
         }
     }
 
+    /// Generate geth-style call frames for the call tracer together with the order in which the
+    /// logs and subcalls of each frame were executed.
+    ///
+    /// This is the same as [Self::geth_call_traces], but the subcalls of a frame are returned in
+    /// [OrderedCallFrame::calls] instead of [CallFrame::calls], so logs and calls can be rendered
+    /// in execution order.
+    ///
+    /// Note: precompile calls are not part of the ordering and are never included, regardless of
+    /// [Self::with_include_precompiles].
+    pub fn geth_call_traces_ordered(
+        &self,
+        opts: CallConfig,
+        receipt_gas_used: u64,
+    ) -> OrderedCallFrame {
+        if self.nodes.is_empty() {
+            return Default::default();
+        }
+
+        let include_logs = opts.with_log.unwrap_or_default();
+        let only_top_call = opts.only_top_call.unwrap_or_default();
+
+        let mut call_frames = Vec::with_capacity(self.nodes.len());
+        for (idx, node) in self.nodes.iter().enumerate() {
+            // include logs only if call and all its parents were successful
+            let include_logs = include_logs && !self.call_or_parent_failed(node);
+            let mut frame = node.geth_empty_call_frame(include_logs);
+
+            // selfdestructs are not part of the ordering, so they're always added as a child
+            if let Some(selfdestruct) = node.geth_selfdestruct_call_trace() {
+                frame.calls.push(selfdestruct);
+            }
+
+            let ordering = node
+                .ordering
+                .iter()
+                .copied()
+                .filter(|member| match member {
+                    TraceMemberOrder::Log(_) => include_logs,
+                    TraceMemberOrder::Call(_) => !only_top_call,
+                    TraceMemberOrder::Step(_) => false,
+                })
+                .collect();
+
+            call_frames.push((idx, OrderedCallFrame { frame, ordering, calls: Vec::new() }));
+
+            if only_top_call {
+                break;
+            }
+        }
+        call_frames[0].1.frame.gas_used = U256::from(receipt_gas_used);
+
+        let attached = self.attached_nodes(false);
+
+        // roll up the child frames to their parent, see [Self::geth_call_traces]
+        loop {
+            let (idx, call) = call_frames.pop().expect("call frames not empty");
+            if let Some(parent) = self.nodes[idx].parent {
+                if !attached[idx] {
+                    continue;
+                }
+                call_frames[parent].1.calls.insert(0, call);
+            } else {
+                debug_assert!(call_frames.is_empty(), "only one root node has no parent");
+                return call;
+            }
+        }
+    }
+
+    /// Returns the precompiles of the spec the traces were recorded with, or the latest ones if
+    /// the spec is unknown.
+    fn precompiles(&self) -> &'static Precompiles {
+        self.spec_id.map_or_else(Precompiles::latest, |spec_id| {
+            Precompiles::new(PrecompileSpecId::from_spec_id(spec_id))
+        })
+    }
+
+    /// Returns whether each node is part of the call graph.
+    ///
+    /// Calls that are not attached to their parent, e.g. excluded precompile calls, are not part of
+    /// the call graph, unless `include_precompiles` is set and the call is a precompile call.
+    fn attached_nodes(&self, include_precompiles: bool) -> Vec<bool> {
+        let mut attached = vec![false; self.nodes.len()];
+        for node in &self.nodes {
+            for child in &node.children {
+                attached[*child] = true;
+            }
+            if include_precompiles && node.is_precompile() {
+                attached[node.idx] = true;
+            }
+        }
+        attached
+    }
+
     /// Returns true if the given trace or any of its parents failed.
     fn call_or_parent_failed(&self, node: &CallTraceNode) -> bool {
         if node.trace.is_error() {
@@ -204,7 +456,10 @@ impl GethTraceBuilder {
         prestate_config: PreStateConfig,
         db: DB,
     ) -> Result<PreStateFrame, DB::Error> {
-        let account_diffs = state.iter().map(|(addr, acc)| (*addr, acc));
+        let account_diffs = state
+            .iter()
+            .filter(|(addr, _)| !self.is_excluded_prestate_account(addr))
+            .map(|(addr, acc)| (*addr, acc));
 
         if prestate_config.is_default_mode() {
             let mut prestate = PreStateMode::default();
@@ -226,7 +481,7 @@ impl GethTraceBuilder {
             Ok(PreStateFrame::Default(prestate))
         } else {
             let mut state_diff = DiffMode::default();
-            let mut account_change_kinds = HashMap::with_capacity(account_diffs.len());
+            let mut account_change_kinds = HashMap::with_capacity(state.len());
             for (addr, changed_acc) in account_diffs {
                 let db_acc = db.basic_ref(addr)?.unwrap_or_default();
 
@@ -274,6 +529,19 @@ impl GethTraceBuilder {
         }
     }
 
+    /// Returns true if the account should not be included in the prestate tracer output.
+    ///
+    /// This is the case for precompiles if [Self::with_exclude_prestate_precompiles] is set and
+    /// the precompile was not called with value.
+    fn is_excluded_prestate_account(&self, address: &Address) -> bool {
+        self.exclude_prestate_precompiles
+            && self.precompiles().contains(address)
+            && !self
+                .nodes
+                .iter()
+                .any(|node| node.trace.address == *address && !node.trace.value.is_zero())
+    }
+
     /// Returns the difference between the pre and post state of the transaction depending on the
     /// kind of changes of that account (pre,post)
     fn diff_traces(
@@ -302,3 +570,103 @@ impl GethTraceBuilder {
         });
     }
 }
+
+/// Where the `SELFDESTRUCT` pseudo frame is placed in the geth call tracer output.
+///
+/// Selfdestructs are not separate calls, clients differ in how they represent them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelfdestructPlacement {
+    /// The selfdestruct frame is added as the last child of the frame that executed it.
+    ///
+    /// This is geth's behavior.
+    #[default]
+    Child,
+    /// The selfdestruct frame is added inline, directly after the frame that executed it, in the
+    /// parent's calls.
+    ///
+    /// Note: a selfdestruct of the root call is always added as a child, since the root call has
+    /// no parent.
+    Inline,
+}
+
+/// A geth call frame together with the order in which its logs and subcalls were executed.
+///
+/// See [GethTraceBuilder::geth_call_traces_ordered].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OrderedCallFrame {
+    /// The call frame.
+    ///
+    /// Subcalls are not included in [CallFrame::calls], which only contains the selfdestruct
+    /// frame, if any.
+    pub frame: CallFrame,
+    /// The order of the logs and subcalls of this frame.
+    ///
+    /// [TraceMemberOrder::Log] indexes into the logs of [Self::frame] and
+    /// [TraceMemberOrder::Call] indexes into [Self::calls]. Logs are omitted if they are not
+    /// included in the frame, steps are never included.
+    pub ordering: Vec<TraceMemberOrder>,
+    /// The subcalls of this frame, in the order they were called.
+    pub calls: Vec<OrderedCallFrame>,
+}
+
+/// Iterator over the struct logs of a transaction, see [GethTraceBuilder::struct_logs_iter].
+struct StructLogIter<'a> {
+    builder: &'a GethTraceBuilder,
+    opts: GethDefaultTracingOptions,
+    /// The steps that are yet to be processed, the next step is the last item.
+    step_stack: VecDeque<CallTraceStepStackItem<'a>>,
+    /// The storage of each contract accumulated by the processed steps.
+    storage: HashMap<Address, BTreeMap<B256, B256>>,
+    /// The transient storage of each contract accumulated by the processed steps.
+    transient_storage: HashMap<Address, BTreeMap<B256, B256>>,
+    /// The number of struct logs that may still be returned.
+    remaining: usize,
+}
+
+impl Iterator for StructLogIter<'_> {
+    type Item = StructLog;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let CallTraceStepStackItem { trace_node, step, call_child_id } =
+            self.step_stack.pop_back()?;
+        self.remaining -= 1;
+
+        let mut log = step.convert_to_geth_struct_log(&self.opts);
+
+        if self.builder.include_memory_size && self.opts.is_memory_enabled() {
+            log.memory_size = step.memory.as_ref().map(|memory| memory.len() as u64);
+        }
+
+        // Fill in memory and storage depending on the options
+        if self.opts.is_storage_enabled() {
+            if let Some(change) = step.storage_change {
+                // `TLOAD` and `TSTORE` report the transient storage, which is tracked separately so
+                // it doesn't shadow the contract storage
+                let storage = if change.reason.is_transient() {
+                    &mut self.transient_storage
+                } else {
+                    &mut self.storage
+                };
+                let contract_storage = storage.entry(step.contract).or_default();
+                contract_storage.insert(change.key.into(), change.value.into());
+                log.storage = Some(contract_storage.clone());
+            }
+        }
+
+        if self.opts.is_return_data_enabled() {
+            log.return_data = Some(trace_node.trace.output.clone());
+        }
+
+        // If the step is a call, we first push all the steps of the child trace on the stack,
+        // so they are processed next
+        if let Some(call_child_id) = call_child_id {
+            let child_trace = &self.builder.nodes[call_child_id];
+            child_trace.push_steps_on_stack(&mut self.step_stack);
+        }
+
+        Some(log)
+    }
+}
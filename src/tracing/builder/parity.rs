@@ -1,35 +1,96 @@
-use super::walker::CallTraceNodeWalkerBF;
 use crate::tracing::{
+    arena,
     types::{CallTraceNode, CallTraceStep},
-    utils::load_account_code,
+    utils::{apply_labels, load_account_code, maybe_revert_reason},
     TracingInspectorConfig,
 };
-use alloy_primitives::{Address, U64};
+use alloy_primitives::{Address, Bytes, B256, U64};
 use alloy_rpc_types::{trace::parity::*, TransactionInfo};
 use revm::{
     db::DatabaseRef,
     interpreter::{opcode, OpCode},
     primitives::{Account, ExecutionResult, ResultAndState, SpecId, KECCAK_EMPTY},
 };
-use std::collections::{HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    convert::Infallible,
+};
 
 /// A type for creating parity style traces
 ///
-/// Note: Parity style traces always ignore calls to precompiles.
+/// Note: Parity style traces ignore calls to precompiles, unless
+/// [ParityTraceBuilder::with_include_precompiles] is set.
 #[derive(Clone, Debug)]
 pub struct ParityTraceBuilder {
     /// Recorded trace nodes
     nodes: Vec<CallTraceNode>,
+    /// The spec id the traces were recorded with.
+    spec_id: Option<SpecId>,
+    /// Whether recorded calls to precompiles are included in the traces.
+    include_precompiles: bool,
+    /// Whether call results with an empty output are omitted.
+    omit_empty_call_outputs: bool,
+    /// Whether decoded revert reasons are included in the errors of reverted traces.
+    include_revert_reasons: bool,
 }
 
 impl ParityTraceBuilder {
     /// Returns a new instance of the builder
     pub fn new(
         nodes: Vec<CallTraceNode>,
-        _spec_id: Option<SpecId>,
+        spec_id: Option<SpecId>,
         _config: TracingInspectorConfig,
     ) -> Self {
-        Self { nodes }
+        Self {
+            nodes,
+            spec_id,
+            include_precompiles: false,
+            omit_empty_call_outputs: false,
+            include_revert_reasons: false,
+        }
+    }
+
+    /// Returns the spec id the traces were recorded with, if known.
+    pub const fn spec_id(&self) -> Option<SpecId> {
+        self.spec_id
+    }
+
+    /// Configures whether calls to precompiles are included in the traces.
+    ///
+    /// Calls to precompiles are recorded but not attached to their parent call if the traces were
+    /// recorded with [TracingInspectorConfig::exclude_precompile_calls]. If this is set, these
+    /// calls are included in the traces with their own `traceAddress`, and are counted in the
+    /// `subtraces` of their parent.
+    pub const fn with_include_precompiles(mut self, include_precompiles: bool) -> Self {
+        self.include_precompiles = include_precompiles;
+        self
+    }
+
+    /// Configures whether the `result` of call traces with an empty output is omitted.
+    ///
+    /// By default, the result of such calls is included with empty `output` bytes. If this is
+    /// set, the `result` of these traces is `None` instead.
+    pub const fn with_omit_empty_call_outputs(mut self, omit_empty_call_outputs: bool) -> Self {
+        self.omit_empty_call_outputs = omit_empty_call_outputs;
+        self
+    }
+
+    /// Configures whether the decoded revert reason is included in the `error` of reverted traces.
+    ///
+    /// By default, the error of a reverted trace is `Reverted`. If this is set and the revert data
+    /// can be decoded, for example an `Error(string)` revert, the error is `Reverted: <reason>`
+    /// instead.
+    pub const fn with_include_revert_reasons(mut self, include_revert_reasons: bool) -> Self {
+        self.include_revert_reasons = include_revert_reasons;
+        self
+    }
+
+    /// Sets the decoded label of all calls to an address in the given map.
+    ///
+    /// See also [CallTraceArena::apply_labels](crate::tracing::CallTraceArena::apply_labels).
+    pub fn with_labels(mut self, labels: &HashMap<Address, String>) -> Self {
+        apply_labels(&mut self.nodes, labels);
+        self
     }
 
     /// Returns a list of all addresses that appeared as callers.
@@ -42,76 +103,66 @@ impl ParityTraceBuilder {
     /// The root trace's gasUsed should mirror the actual gas used by the transaction.
     ///
     /// This allows setting it manually by consuming the execution result's gas for example.
+    ///
+    /// This expects the receipt gas used, i.e. [ExecutionResult::gas_used], which includes the
+    /// intrinsic gas of the transaction. All other traces keep the gas used by their execution.
+    ///
+    /// [ExecutionResult::gas_used]: revm::primitives::ExecutionResult::gas_used
     #[inline]
-    pub fn set_transaction_gas_used(&mut self, gas_used: u64) {
+    pub fn set_transaction_gas_used(&mut self, receipt_gas_used: u64) {
         if let Some(node) = self.nodes.first_mut() {
-            node.trace.gas_used = gas_used;
+            node.trace.gas_used = receipt_gas_used;
         }
     }
 
     /// Convenience function for [ParityTraceBuilder::set_transaction_gas_used] that consumes the
     /// type.
     #[inline]
-    pub fn with_transaction_gas_used(mut self, gas_used: u64) -> Self {
-        self.set_transaction_gas_used(gas_used);
+    pub fn with_transaction_gas_used(mut self, receipt_gas_used: u64) -> Self {
+        self.set_transaction_gas_used(receipt_gas_used);
         self
     }
 
     /// Returns the trace addresses of all call nodes in the set
     ///
-    /// Each entry in the returned vector represents the [Self::trace_address] of the corresponding
-    /// node in the nodes set.
+    /// Each entry in the returned vector represents the `traceAddress` of the corresponding node in
+    /// the nodes set.
     ///
-    /// CAUTION: This also includes precompiles, which have an empty trace address.
+    /// CAUTION: This also includes nodes that are not traced, like precompiles, which have an empty
+    /// trace address.
     fn trace_addresses(&self) -> Vec<Vec<usize>> {
-        let mut all_addresses = Vec::with_capacity(self.nodes.len());
-        for idx in 0..self.nodes.len() {
-            all_addresses.push(self.trace_address(idx));
-        }
-        all_addresses
+        let children = self.traceable_children();
+        arena::trace_addresses(&self.nodes, &children, self.include_precompiles)
     }
 
     /// Returns the `traceAddress` of the node in the arena
     ///
-    /// The `traceAddress` field of all returned traces, gives the exact location in the call trace
-    /// [index in root, index in first CALL, index in second CALL, …].
-    ///
-    /// # Panics
+    /// `children` are the [Self::traceable_children] of all nodes, see [arena::trace_address].
+    fn trace_address(&self, children: &[Vec<usize>], idx: usize) -> Vec<usize> {
+        arena::trace_address(&self.nodes, children, self.include_precompiles, idx)
+    }
+
+    /// Returns the traced child calls of all nodes, in the order they were called.
     ///
-    /// if the `idx` does not belong to a node
+    /// These are the [CallTraceNode::children] of a node, and its precompile calls if
+    /// [Self::with_include_precompiles] is set.
+    fn traceable_children(&self) -> Vec<Vec<usize>> {
+        arena::traceable_children(&self.nodes, self.include_precompiles)
+    }
+
+    /// Returns true if the node is included in the traces.
     ///
-    /// Note: if the call node of `idx` is a precompile, the returned trace address will be empty.
-    fn trace_address(&self, idx: usize) -> Vec<usize> {
-        if idx == 0 {
-            // root call has empty traceAddress
-            return vec![];
-        }
-        let mut graph = vec![];
-        let mut node = &self.nodes[idx];
-        if node.is_precompile() {
-            return graph;
-        }
-        while let Some(parent) = node.parent {
-            // the index of the child call in the arena
-            let child_idx = node.idx;
-            node = &self.nodes[parent];
-            // find the index of the child call in the parent node
-            let call_idx = node
-                .children
-                .iter()
-                .position(|child| *child == child_idx)
-                .expect("non precompile child call exists in parent");
-            graph.push(call_idx);
-        }
-        graph.reverse();
-        graph
+    /// This excludes nodes that represent calls to precompiles, unless
+    /// [Self::with_include_precompiles] is set, and nodes that are not attached to the call graph.
+    fn is_traceable(&self, node: &CallTraceNode) -> bool {
+        arena::is_traceable(&self.nodes, node, self.include_precompiles)
     }
 
     /// Returns an iterator over all nodes to trace
     ///
-    /// This excludes nodes that represent calls to precompiles.
+    /// See [Self::is_traceable].
     fn iter_traceable_nodes(&self) -> impl Iterator<Item = &CallTraceNode> {
-        self.nodes.iter().filter(|node| !node.is_precompile())
+        self.nodes.iter().filter(|node| self.is_traceable(node))
     }
 
     /// Returns an iterator over all recorded traces  for `trace_transaction`
@@ -183,26 +234,22 @@ impl ParityTraceBuilder {
     ) -> Result<TraceResults, DB::Error> {
         let ResultAndState { ref result, ref state } = res;
 
-        let breadth_first_addresses = if trace_types.contains(&TraceType::VmTrace) {
-            CallTraceNodeWalkerBF::new(&self.nodes)
-                .map(|node| node.trace.address)
-                .collect::<Vec<_>>()
+        // the vm trace is created with the code filled in, so it's excluded from the other traces
+        let mut trace_types = trace_types.clone();
+        let vm_trace = if trace_types.remove(&TraceType::VmTrace) {
+            Some(self.vm_trace_with_code(&db)?)
         } else {
-            vec![]
+            None
         };
 
-        let mut trace_res = self.into_trace_results(result, trace_types);
+        let mut trace_res = self.into_trace_results(result, &trace_types);
+        trace_res.vm_trace = vm_trace;
 
         // check the state diff case
         if let Some(ref mut state_diff) = trace_res.state_diff {
             populate_state_diff(state_diff, &db, state.iter())?;
         }
 
-        // check the vm trace case
-        if let Some(ref mut vm_trace) = trace_res.vm_trace {
-            populate_vm_trace_bytecodes(&db, vm_trace, breadth_first_addresses)?;
-        }
-
         Ok(trace_res)
     }
 
@@ -228,11 +275,19 @@ impl ParityTraceBuilder {
 
         let mut traces = Vec::with_capacity(if with_traces { self.nodes.len() } else { 0 });
 
+        let children = self.traceable_children();
         for node in self.iter_traceable_nodes() {
-            let trace_address = self.trace_address(node.idx);
+            let trace_address = self.trace_address(&children, node.idx);
 
             if with_traces {
-                let trace = node.parity_transaction_trace(trace_address);
+                let mut trace = node.parity_transaction_trace(trace_address);
+                trace.subtraces = children[node.idx].len();
+                if self.omit_empty_call_outputs {
+                    omit_empty_call_output(&mut trace);
+                }
+                if self.include_revert_reasons {
+                    include_revert_reason(node, &mut trace);
+                }
                 traces.push(trace);
 
                 // check if the trace node is a selfdestruct
@@ -265,14 +320,30 @@ impl ParityTraceBuilder {
     /// Returns an iterator over all recorded traces  for `trace_transaction`
     pub fn into_transaction_traces_iter(self) -> impl Iterator<Item = TransactionTrace> {
         let trace_addresses = self.trace_addresses();
+        let subtraces = self.traceable_children().into_iter().map(|children| children.len());
+        let traceable = self.nodes.iter().map(|node| self.is_traceable(node)).collect::<Vec<_>>();
+        let omit_empty_call_outputs = self.omit_empty_call_outputs;
+        let include_revert_reasons = self.include_revert_reasons;
         TransactionTraceIter {
             next_selfdestruct: None,
             iter: self
                 .nodes
                 .into_iter()
                 .zip(trace_addresses)
-                .filter(|(node, _)| !node.is_precompile())
-                .map(|(node, trace_address)| (node.parity_transaction_trace(trace_address), node)),
+                .zip(subtraces)
+                .zip(traceable)
+                .filter(|(_, traceable)| *traceable)
+                .map(move |(((node, trace_address), subtraces), _)| {
+                    let mut trace = node.parity_transaction_trace(trace_address);
+                    trace.subtraces = subtraces;
+                    if omit_empty_call_outputs {
+                        omit_empty_call_output(&mut trace);
+                    }
+                    if include_revert_reasons {
+                        include_revert_reason(&node, &mut trace);
+                    }
+                    (trace, node)
+                }),
         }
     }
 
@@ -281,6 +352,43 @@ impl ParityTraceBuilder {
         self.into_transaction_traces_iter().collect()
     }
 
+    /// Returns the raw traces of the transaction that match the given address filters, as used by
+    /// `trace_filter`.
+    ///
+    /// A trace matches if its sender is in `from` and its recipient is in `to`, a missing filter
+    /// matches everything. The sender and recipient are:
+    /// - calls: `from` and `to` of the action
+    /// - creates: `from` of the action and the created address, if any
+    /// - selfdestructs: the destroyed address and the refund address
+    ///
+    /// The retained traces keep the trace addresses they have in the unfiltered traces.
+    pub fn filtered(
+        self,
+        from: Option<&HashSet<Address>>,
+        to: Option<&HashSet<Address>>,
+    ) -> Vec<TransactionTrace> {
+        self.into_transaction_traces_iter()
+            .filter(|trace| {
+                let (sender, recipient) = match &trace.action {
+                    Action::Call(call) => (call.from, Some(call.to)),
+                    Action::Create(create) => (
+                        create.from,
+                        match &trace.result {
+                            Some(TraceOutput::Create(res)) => Some(res.address),
+                            _ => None,
+                        },
+                    ),
+                    Action::Selfdestruct(selfdestruct) => {
+                        (selfdestruct.address, Some(selfdestruct.refund_address))
+                    }
+                    Action::Reward(reward) => (reward.author, None),
+                };
+                from.map_or(true, |from| from.contains(&sender))
+                    && to.map_or(true, |to| recipient.is_some_and(|to_addr| to.contains(&to_addr)))
+            })
+            .collect()
+    }
+
     /// Returns the last recorded step
     #[inline]
     fn last_step(&self) -> Option<&CallTraceStep> {
@@ -295,15 +403,40 @@ impl ParityTraceBuilder {
 
     /// Creates a VM trace by walking over `CallTraceNode`s
     ///
-    /// does not have the code fields filled in
+    /// does not have the code fields filled in, see [Self::vm_trace_with_code]
     pub fn vm_trace(&self) -> VmTrace {
-        self.nodes.first().map(|node| self.make_vm_trace(node)).unwrap_or_default()
+        self.nodes
+            .first()
+            .map(|node| {
+                self.make_vm_trace(node, |_| Ok::<_, Infallible>(Bytes::new()))
+                    .unwrap_or_else(|never| match never {})
+            })
+            .unwrap_or_default()
     }
 
-    /// Returns a VM trace without the code filled in
+    /// Creates a VM trace by walking over `CallTraceNode`s, with the code of each call loaded
+    /// from the [DatabaseRef].
+    ///
+    /// The `db` should point to the beginning of the transaction. The code of every unique code
+    /// hash is only fetched once.
+    pub fn vm_trace_with_code<DB: DatabaseRef>(&self, db: DB) -> Result<VmTrace, DB::Error> {
+        let mut codes = HashMap::new();
+        match self.nodes.first() {
+            Some(node) => {
+                self.make_vm_trace(node, |node| load_vm_trace_code(&db, node, &mut codes))
+            }
+            None => Ok(Default::default()),
+        }
+    }
+
+    /// Returns a VM trace with the code of each call set by `load_code`
     ///
     /// Iteratively creates a VM trace by traversing the recorded nodes in the arena
-    fn make_vm_trace(&self, start: &CallTraceNode) -> VmTrace {
+    fn make_vm_trace<E>(
+        &self,
+        start: &CallTraceNode,
+        mut load_code: impl FnMut(&CallTraceNode) -> Result<Bytes, E>,
+    ) -> Result<VmTrace, E> {
         let mut child_idx_stack = Vec::with_capacity(self.nodes.len());
         let mut sub_stack = VecDeque::with_capacity(self.nodes.len());
 
@@ -342,7 +475,7 @@ impl ParityTraceBuilder {
                     match current.parent {
                         Some(parent) => {
                             sub_stack.push_back(Some(VmTrace {
-                                code: Default::default(),
+                                code: load_code(current)?,
                                 ops: instructions,
                             }));
 
@@ -356,7 +489,9 @@ impl ParityTraceBuilder {
             }
         };
 
-        VmTrace { code: Default::default(), ops: instructions }
+        let mut trace = VmTrace { code: load_code(start)?, ops: instructions };
+        populate_vm_trace_idx(&mut trace);
+        Ok(trace)
     }
 
     /// Creates a VM instruction from a [CallTraceStep] and a [VmTrace] for the subcall if there is
@@ -366,10 +501,14 @@ impl ParityTraceBuilder {
         step: &CallTraceStep,
         maybe_sub_call: Option<VmTrace>,
     ) -> VmInstruction {
-        let maybe_storage = step.storage_change.map(|storage_change| StorageDelta {
-            key: storage_change.key,
-            val: storage_change.value,
-        });
+        // the transient storage is not part of the contract storage
+        let maybe_storage = step
+            .storage_change
+            .filter(|storage_change| !storage_change.reason.is_transient())
+            .map(|storage_change| StorageDelta {
+                key: storage_change.key,
+                val: storage_change.value,
+            });
 
         let maybe_memory = step
             .memory
@@ -394,6 +533,40 @@ impl ParityTraceBuilder {
     }
 }
 
+/// Populates the [VmInstruction::idx] of all instructions in the given [VmTrace].
+///
+/// Like parity, the index of an instruction is its position in its trace, prefixed by the index of
+/// the instruction that created the sub-trace, e.g. `"3-0"` is the first instruction of the
+/// sub-trace of the call at index `"3"`.
+fn populate_vm_trace_idx(trace: &mut VmTrace) {
+    let mut stack = vec![(trace, String::new())];
+    while let Some((trace, prefix)) = stack.pop() {
+        for (i, op) in trace.ops.iter_mut().enumerate() {
+            let idx = format!("{prefix}{i}");
+            if let Some(sub) = op.sub.as_mut() {
+                stack.push((sub, format!("{idx}-")));
+            }
+            op.idx = Some(idx);
+        }
+    }
+}
+
+/// Removes the `result` of the trace if it's a call with an empty output.
+fn omit_empty_call_output(trace: &mut TransactionTrace) {
+    if matches!(&trace.result, Some(TraceOutput::Call(out)) if out.output.is_empty()) {
+        trace.result = None;
+    }
+}
+
+/// Appends the decoded revert reason to the `error` of the trace if the call reverted.
+fn include_revert_reason(node: &CallTraceNode, trace: &mut TransactionTrace) {
+    if node.trace.is_revert() {
+        if let Some(reason) = maybe_revert_reason(&node.trace.output) {
+            trace.error = Some(format!("Reverted: {reason}"));
+        }
+    }
+}
+
 /// An iterator for [TransactionTrace]s
 struct TransactionTraceIter<Iter> {
     iter: Iter,
@@ -423,41 +596,26 @@ where
     }
 }
 
-/// addresses are presorted via breadth first walk thru [CallTraceNode]s, this  can be done by a
-/// walker in [crate::tracing::builder::walker]
+/// Loads the code of the call node's account for the [VmTrace] from the `db`
 ///
-/// iteratively fill the [VmTrace] code fields
-pub(crate) fn populate_vm_trace_bytecodes<DB, I>(
+/// Codes are cached by their hash in `codes`, so every code is only fetched once.
+///
+/// Returns empty bytes if the account has no code.
+fn load_vm_trace_code<DB: DatabaseRef>(
     db: DB,
-    trace: &mut VmTrace,
-    breadth_first_addresses: I,
-) -> Result<(), DB::Error>
-where
-    DB: DatabaseRef,
-    I: IntoIterator<Item = Address>,
-{
-    let mut stack: VecDeque<&mut VmTrace> = VecDeque::new();
-    stack.push_back(trace);
-
-    let mut addrs = breadth_first_addresses.into_iter();
-
-    while let Some(curr_ref) = stack.pop_front() {
-        for op in curr_ref.ops.iter_mut() {
-            if let Some(sub) = op.sub.as_mut() {
-                stack.push_back(sub);
-            }
-        }
-
-        let addr = addrs.next().expect("there should be an address");
-
-        let db_acc = db.basic_ref(addr)?.unwrap_or_default();
-
-        let code_hash = if db_acc.code_hash != KECCAK_EMPTY { db_acc.code_hash } else { continue };
-
-        curr_ref.code = db.code_by_hash_ref(code_hash)?.original_bytes();
+    node: &CallTraceNode,
+    codes: &mut HashMap<B256, Bytes>,
+) -> Result<Bytes, DB::Error> {
+    let db_acc = db.basic_ref(node.trace.address)?.unwrap_or_default();
+    if db_acc.code_hash == KECCAK_EMPTY {
+        return Ok(Bytes::new());
     }
-
-    Ok(())
+    if let Some(code) = codes.get(&db_acc.code_hash) {
+        return Ok(code.clone());
+    }
+    let code = db.code_by_hash_ref(db_acc.code_hash)?.original_bytes();
+    codes.insert(db_acc.code_hash, code.clone());
+    Ok(code)
 }
 
 /// Loops over all state accounts in the accounts diff that contains all accounts that are included
@@ -537,6 +695,16 @@ where
                     to: U64::from(changed_acc.info.nonce),
                 })
             };
+
+            // compare the code hashes so the bytecode is only loaded if the code changed
+            entry.code = if db_acc.code_hash == changed_acc.info.code_hash {
+                Delta::Unchanged
+            } else {
+                Delta::Changed(ChangedType {
+                    from: load_account_code(&db, &db_acc).unwrap_or_default(),
+                    to: load_account_code(&db, &changed_acc.info).unwrap_or_default(),
+                })
+            };
         }
     }
 
@@ -0,0 +1,128 @@
+//! Tracing inspector that notifies about completed call frames.
+
+use crate::tracing::{types::CallTraceNode, TracingInspector};
+use alloy_primitives::{Address, Log, U256};
+use revm::{
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter},
+    Database, EvmContext, Inspector,
+};
+
+/// A [TracingInspector] that invokes a callback with every call frame once it completed.
+///
+/// The callback is invoked on [Inspector::call_end] and [Inspector::create_end], after the frame
+/// was filled with the result of the call. Frames complete in reverse order of their start, so a
+/// frame's subcalls are always passed to the callback before the frame itself.
+///
+/// This allows processing frames incrementally, e.g. streaming them into a database while the
+/// transaction is still executing.
+#[derive(Clone, Debug)]
+pub struct FrameCallbackInspector<F> {
+    inspector: TracingInspector,
+    on_frame: F,
+}
+
+impl<F> FrameCallbackInspector<F>
+where
+    F: FnMut(&CallTraceNode),
+{
+    /// Returns a new instance that records traces with the given inspector and invokes `on_frame`
+    /// for every completed frame.
+    pub const fn new(inspector: TracingInspector, on_frame: F) -> Self {
+        Self { inspector, on_frame }
+    }
+
+    /// Returns the wrapped inspector.
+    pub const fn inspector(&self) -> &TracingInspector {
+        &self.inspector
+    }
+
+    /// Returns a mutable reference to the wrapped inspector.
+    pub fn inspector_mut(&mut self) -> &mut TracingInspector {
+        &mut self.inspector
+    }
+
+    /// Consumes the type and returns the wrapped inspector.
+    pub fn into_inspector(self) -> TracingInspector {
+        self.inspector
+    }
+}
+
+impl<DB, F> Inspector<DB> for FrameCallbackInspector<F>
+where
+    DB: Database,
+    F: FnMut(&CallTraceNode),
+{
+    #[inline]
+    fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspector.initialize_interp(interp, context);
+    }
+
+    #[inline]
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspector.step(interp, context);
+    }
+
+    #[inline]
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspector.step_end(interp, context);
+    }
+
+    #[inline]
+    fn log(&mut self, context: &mut EvmContext<DB>, log: &Log) {
+        self.inspector.log(context, log);
+    }
+
+    #[inline]
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.inspector.call(context, inputs)
+    }
+
+    #[inline]
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        let idx = self.inspector.last_trace_idx();
+        let outcome = self.inspector.call_end(context, inputs, outcome);
+        (self.on_frame)(&self.inspector.traces.arena[idx]);
+        outcome
+    }
+
+    #[inline]
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.inspector.create(context, inputs)
+    }
+
+    #[inline]
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        let idx = self.inspector.last_trace_idx();
+        let outcome = self.inspector.create_end(context, inputs, outcome);
+        (self.on_frame)(&self.inspector.traces.arena[idx]);
+        outcome
+    }
+
+    #[inline]
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        <TracingInspector as Inspector<DB>>::selfdestruct(
+            &mut self.inspector,
+            contract,
+            target,
+            value,
+        );
+    }
+}
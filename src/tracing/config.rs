@@ -1,4 +1,4 @@
-use alloy_primitives::U256;
+use alloy_primitives::{Address, U256};
 use alloy_rpc_types::trace::{
     geth::{CallConfig, GethDefaultTracingOptions, PreStateConfig},
     parity::TraceType,
@@ -53,7 +53,7 @@ impl OpcodeFilter {
 ///
 /// Use [TracingInspectorConfig::default_parity] or [TracingInspectorConfig::default_geth] to get
 /// the default configs for specific styles of traces.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
 pub struct TracingInspectorConfig {
     /// Whether to record every individual opcode level step.
     pub record_steps: bool,
@@ -72,10 +72,46 @@ pub struct TracingInspectorConfig {
     pub exclude_precompile_calls: bool,
     /// Whether to record logs
     pub record_logs: bool,
+    /// Whether to record the calldata regions read by `CALLDATALOAD` and `CALLDATACOPY` steps.
+    pub record_calldata_reads: bool,
+    /// Whether to record the gas refund delta of each step.
+    pub record_gas_refunds: bool,
+    /// Whether to count the executed opcodes of each call.
+    pub record_op_counts: bool,
+    /// Whether to record the peak memory size of each call, see
+    /// [CallTrace::max_memory](crate::tracing::types::CallTrace::max_memory).
+    pub record_max_memory: bool,
+    /// Whether to record the gas refunded for a pre-London selfdestruct, see
+    /// [CallTrace::selfdestruct_gas_refund](crate::tracing::types::CallTrace::selfdestruct_gas_refund).
+    pub record_selfdestruct_refunds: bool,
+    /// Whether to measure the wall time of each step.
+    pub record_step_durations: bool,
+    /// Whether to verify the addresses of created contracts, see
+    /// [TracingInspectorConfig::set_verify_create_addresses].
+    pub verify_create_addresses: bool,
+    /// Whether to record the targets of EIP-7702 delegation designators, see
+    /// [TracingInspectorConfig::set_record_delegations].
+    pub record_delegations: bool,
+    /// Whether to record the gas left in the caller when it executed a call instruction, see
+    /// [CallTrace::caller_gas_remaining](crate::tracing::types::CallTrace::caller_gas_remaining).
+    pub record_caller_gas: bool,
+    /// The maximum number of steps to record across all calls.
+    ///
+    /// Once reached, no further steps are recorded. If `None`, all steps are recorded.
+    pub max_steps: Option<usize>,
+    /// The number of steps to preallocate for each recorded call.
+    ///
+    /// If `None`, the steps of a call are allocated as they're recorded.
+    pub step_capacity_hint: Option<usize>,
+    /// Optional set of addresses to record calls for. If provided, only calls to these addresses
+    /// are included in the call graph.
+    pub only_addresses: Option<HashSet<Address>>,
 }
 
 impl TracingInspectorConfig {
     /// Returns a config with everything enabled.
+    ///
+    /// Step durations are not measured, see [TracingInspectorConfig::set_record_step_durations].
     pub const fn all() -> Self {
         Self {
             record_steps: true,
@@ -86,6 +122,18 @@ impl TracingInspectorConfig {
             record_opcodes_filter: None,
             exclude_precompile_calls: false,
             record_logs: true,
+            record_calldata_reads: true,
+            record_gas_refunds: true,
+            record_op_counts: true,
+            record_max_memory: true,
+            record_selfdestruct_refunds: true,
+            record_step_durations: false,
+            verify_create_addresses: false,
+            record_delegations: false,
+            record_caller_gas: false,
+            max_steps: None,
+            step_capacity_hint: None,
+            only_addresses: None,
         }
     }
 
@@ -100,6 +148,18 @@ impl TracingInspectorConfig {
             exclude_precompile_calls: false,
             record_logs: false,
             record_opcodes_filter: None,
+            record_calldata_reads: false,
+            record_gas_refunds: false,
+            record_op_counts: false,
+            record_max_memory: false,
+            record_selfdestruct_refunds: false,
+            record_step_durations: false,
+            verify_create_addresses: false,
+            record_delegations: false,
+            record_caller_gas: false,
+            max_steps: None,
+            step_capacity_hint: None,
+            only_addresses: None,
         }
     }
 
@@ -116,6 +176,18 @@ impl TracingInspectorConfig {
             exclude_precompile_calls: true,
             record_logs: false,
             record_opcodes_filter: None,
+            record_calldata_reads: false,
+            record_gas_refunds: false,
+            record_op_counts: false,
+            record_max_memory: false,
+            record_selfdestruct_refunds: false,
+            record_step_durations: false,
+            verify_create_addresses: false,
+            record_delegations: false,
+            record_caller_gas: false,
+            max_steps: None,
+            step_capacity_hint: None,
+            only_addresses: None,
         }
     }
 
@@ -135,6 +207,18 @@ impl TracingInspectorConfig {
             exclude_precompile_calls: false,
             record_logs: false,
             record_opcodes_filter: None,
+            record_calldata_reads: false,
+            record_gas_refunds: false,
+            record_op_counts: false,
+            record_max_memory: false,
+            record_selfdestruct_refunds: false,
+            record_step_durations: false,
+            verify_create_addresses: false,
+            record_delegations: false,
+            record_caller_gas: false,
+            max_steps: None,
+            step_capacity_hint: None,
+            only_addresses: None,
         }
     }
 
@@ -157,16 +241,19 @@ impl TracingInspectorConfig {
     ///
     /// This will configure the output of geth's default
     /// [StructLogTracer](alloy_rpc_types::trace::geth::DefaultFrame) according to the given config.
+    ///
+    /// Memory, stack and storage are only recorded if they are included in the output, so nothing
+    /// is captured that the [StructLog](alloy_rpc_types::trace::geth::StructLog)s would discard.
     #[inline]
     pub fn from_geth_config(config: &GethDefaultTracingOptions) -> Self {
         Self {
-            record_memory_snapshots: config.enable_memory.unwrap_or_default(),
-            record_stack_snapshots: if config.disable_stack.unwrap_or_default() {
-                StackSnapshotType::None
-            } else {
+            record_memory_snapshots: config.is_memory_enabled(),
+            record_stack_snapshots: if config.is_stack_enabled() {
                 StackSnapshotType::Full
+            } else {
+                StackSnapshotType::None
             },
-            record_state_diff: !config.disable_storage.unwrap_or_default(),
+            record_state_diff: config.is_storage_enabled(),
             ..Self::default_geth()
         }
     }
@@ -292,15 +379,256 @@ impl TracingInspectorConfig {
         self
     }
 
+    /// Disable recording of calldata reads
+    pub const fn disable_record_calldata_reads(self) -> Self {
+        self.set_record_calldata_reads(false)
+    }
+
+    /// Enable recording of calldata reads
+    pub const fn record_calldata_reads(self) -> Self {
+        self.set_record_calldata_reads(true)
+    }
+
+    /// Configure whether the tracer should record the calldata regions read by `CALLDATALOAD`
+    /// and `CALLDATACOPY` steps.
+    ///
+    /// This requires steps recording.
+    pub const fn set_record_calldata_reads(mut self, record_calldata_reads: bool) -> Self {
+        self.record_calldata_reads = record_calldata_reads;
+        self
+    }
+
+    /// Disable recording of gas refund deltas
+    pub const fn disable_record_gas_refunds(self) -> Self {
+        self.set_record_gas_refunds(false)
+    }
+
+    /// Enable recording of gas refund deltas
+    pub const fn record_gas_refunds(self) -> Self {
+        self.set_record_gas_refunds(true)
+    }
+
+    /// Configure whether the tracer should record the change of the gas refund counter of each
+    /// step, see [CallTraceStep::refund_delta](crate::tracing::types::CallTraceStep::refund_delta).
+    ///
+    /// This requires steps recording. Refund deltas are also recorded if state diffs are recorded.
+    pub const fn set_record_gas_refunds(mut self, record_gas_refunds: bool) -> Self {
+        self.record_gas_refunds = record_gas_refunds;
+        self
+    }
+
+    /// Disable counting of executed opcodes per call
+    pub const fn disable_record_op_counts(self) -> Self {
+        self.set_record_op_counts(false)
+    }
+
+    /// Enable counting of executed opcodes per call
+    pub const fn record_op_counts(self) -> Self {
+        self.set_record_op_counts(true)
+    }
+
+    /// Configure whether the tracer should count the opcodes executed by each call, see
+    /// [CallTrace::op_count](crate::tracing::types::CallTrace::op_count).
+    ///
+    /// Unlike most other settings, this does not require steps recording.
+    pub const fn set_record_op_counts(mut self, record_op_counts: bool) -> Self {
+        self.record_op_counts = record_op_counts;
+        self
+    }
+
+    /// Disable recording of the peak memory size per call
+    pub const fn disable_record_max_memory(self) -> Self {
+        self.set_record_max_memory(false)
+    }
+
+    /// Enable recording of the peak memory size per call
+    pub const fn record_max_memory(self) -> Self {
+        self.set_record_max_memory(true)
+    }
+
+    /// Configure whether the tracer should record the peak memory size of each call, see
+    /// [CallTrace::max_memory](crate::tracing::types::CallTrace::max_memory).
+    ///
+    /// Like [TracingInspectorConfig::set_record_op_counts], this does not require steps or
+    /// memory snapshots recording.
+    pub const fn set_record_max_memory(mut self, record_max_memory: bool) -> Self {
+        self.record_max_memory = record_max_memory;
+        self
+    }
+
+    /// Disable recording of pre-London selfdestruct refunds
+    pub const fn disable_record_selfdestruct_refunds(self) -> Self {
+        self.set_record_selfdestruct_refunds(false)
+    }
+
+    /// Enable recording of pre-London selfdestruct refunds
+    pub const fn record_selfdestruct_refunds(self) -> Self {
+        self.set_record_selfdestruct_refunds(true)
+    }
+
+    /// Configure whether the tracer should record the gas refunded for a selfdestruct, see
+    /// [CallTrace::selfdestruct_gas_refund](crate::tracing::types::CallTrace::selfdestruct_gas_refund).
+    ///
+    /// This is only relevant for tracing blocks before the London hardfork and does not require
+    /// steps recording.
+    pub const fn set_record_selfdestruct_refunds(
+        mut self,
+        record_selfdestruct_refunds: bool,
+    ) -> Self {
+        self.record_selfdestruct_refunds = record_selfdestruct_refunds;
+        self
+    }
+
+    /// Disable measuring of step durations
+    pub const fn disable_record_step_durations(self) -> Self {
+        self.set_record_step_durations(false)
+    }
+
+    /// Enable measuring of step durations
+    pub const fn record_step_durations(self) -> Self {
+        self.set_record_step_durations(true)
+    }
+
+    /// Configure whether the tracer should measure the wall time of each step, see
+    /// [CallTraceStep::duration](crate::tracing::types::CallTraceStep::duration).
+    ///
+    /// This requires steps recording. Measuring adds overhead to every recorded step and makes
+    /// traces of the same transaction differ, so this is only meant for profiling and is not
+    /// enabled by any of the presets, including [TracingInspectorConfig::all].
+    pub const fn set_record_step_durations(mut self, record_step_durations: bool) -> Self {
+        self.record_step_durations = record_step_durations;
+        self
+    }
+
+    /// Disable verification of created addresses
+    pub const fn disable_verify_create_addresses(self) -> Self {
+        self.set_verify_create_addresses(false)
+    }
+
+    /// Enable verification of created addresses
+    pub const fn verify_create_addresses(self) -> Self {
+        self.set_verify_create_addresses(true)
+    }
+
+    /// Configure whether the tracer should verify the addresses of created contracts.
+    ///
+    /// If enabled, the tracer panics if the address of a `CREATE` does not match
+    /// `keccak256(rlp([sender, nonce]))[12:]`, or if the address of a successful create differs
+    /// from the address the interpreter created the contract at. This is only meant for testing.
+    pub const fn set_verify_create_addresses(mut self, verify_create_addresses: bool) -> Self {
+        self.verify_create_addresses = verify_create_addresses;
+        self
+    }
+
+    /// Disable recording of EIP-7702 delegation targets
+    pub const fn disable_record_delegations(self) -> Self {
+        self.set_record_delegations(false)
+    }
+
+    /// Enable recording of EIP-7702 delegation targets
+    pub const fn record_delegations(self) -> Self {
+        self.set_record_delegations(true)
+    }
+
+    /// Configure whether the tracer should record the target of the
+    /// [EIP-7702](https://eips.ethereum.org/EIPS/eip-7702) delegation designator in the code of
+    /// each called account, see
+    /// [CallTrace::delegated_to](crate::tracing::types::CallTrace::delegated_to).
+    ///
+    /// This is only done for Prague and later. It requires loading the code of the called account
+    /// before the call is executed, which may be an additional database read per call.
+    pub const fn set_record_delegations(mut self, record_delegations: bool) -> Self {
+        self.record_delegations = record_delegations;
+        self
+    }
+
+    /// Disable recording of the gas left in the caller
+    pub const fn disable_record_caller_gas(self) -> Self {
+        self.set_record_caller_gas(false)
+    }
+
+    /// Enable recording of the gas left in the caller
+    pub const fn record_caller_gas(self) -> Self {
+        self.set_record_caller_gas(true)
+    }
+
+    /// Configure whether the tracer should record the gas the caller had left when it executed
+    /// each call instruction, see
+    /// [CallTrace::caller_gas_remaining](crate::tracing::types::CallTrace::caller_gas_remaining).
+    ///
+    /// This can be used to debug out of gas errors of calls that got less gas than requested
+    /// because of the 63/64 rule of EIP-150.
+    pub const fn set_record_caller_gas(mut self, record_caller_gas: bool) -> Self {
+        self.record_caller_gas = record_caller_gas;
+        self
+    }
+
+    /// Limit the number of recorded steps to `max_steps`
+    pub const fn max_steps(self, max_steps: usize) -> Self {
+        self.set_max_steps(Some(max_steps))
+    }
+
+    /// Configure the maximum number of steps the tracer should record across all calls.
+    ///
+    /// Once the limit is reached, no further steps are recorded, but calls are still tracked.
+    /// Whether this happened is reported by
+    /// [TracingInspector::steps_truncated](crate::tracing::TracingInspector::steps_truncated) and
+    /// [GethTraceBuilder::steps_truncated](crate::tracing::GethTraceBuilder::steps_truncated), the
+    /// geth struct logs themselves don't indicate it.
+    pub const fn set_max_steps(mut self, max_steps: Option<usize>) -> Self {
+        self.max_steps = max_steps;
+        self
+    }
+
+    /// Preallocate `step_capacity_hint` steps for each recorded call.
+    pub const fn with_step_capacity_hint(self, step_capacity_hint: usize) -> Self {
+        self.set_step_capacity_hint(Some(step_capacity_hint))
+    }
+
+    /// Configure the number of steps the tracer should preallocate for each recorded call.
+    ///
+    /// This avoids reallocations while recording the steps of calls that execute up to this many
+    /// steps, at the cost of allocating the full capacity for every call, e.g. an estimate based
+    /// on the size of the executed bytecode. This has no effect if steps are not recorded.
+    pub const fn set_step_capacity_hint(mut self, step_capacity_hint: Option<usize>) -> Self {
+        self.step_capacity_hint = step_capacity_hint;
+        self
+    }
+
+    /// Only include calls to the given addresses in the call graph.
+    ///
+    /// See [TracingInspectorConfig::set_only_addresses].
+    pub fn with_only_addresses(self, addresses: impl IntoIterator<Item = Address>) -> Self {
+        self.set_only_addresses(Some(addresses.into_iter().collect()))
+    }
+
+    /// Configure the addresses calls should be recorded for.
+    ///
+    /// If set, only calls whose execution address (the caller for delegate calls) is in the set
+    /// are included in the call graph, calls to other addresses are still tracked but detached
+    /// from the graph. Calls made by a detached call are attached to its closest included
+    /// ancestor. The root call is always included.
+    pub fn set_only_addresses(mut self, only_addresses: Option<HashSet<Address>>) -> Self {
+        self.only_addresses = only_addresses;
+        self
+    }
+
     /// If [OpcodeFilter] is configured, returns whether the given opcode should be recorded.
     /// Otherwise, always returns true.
     #[inline]
     pub fn should_record_opcode(&self, op: OpCode) -> bool {
         self.record_opcodes_filter.as_ref().map_or(true, |filter| filter.is_enabled(op))
     }
+
+    /// If a set of addresses is configured, returns whether calls to the given address should be
+    /// included in the call graph. Otherwise, always returns true.
+    #[inline]
+    pub fn should_record_address(&self, address: &Address) -> bool {
+        self.only_addresses.as_ref().map_or(true, |addresses| addresses.contains(address))
+    }
 }
 
-/// How much of the stack to record. Nothing, just the items pushed, or the full stack
+/// How much of the stack to record. Nothing, just the items pushed, the top items or the full stack
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum StackSnapshotType {
     /// Don't record stack snapshots
@@ -310,6 +638,11 @@ pub enum StackSnapshotType {
     Pushes,
     /// Record the full stack
     Full,
+    /// Record only the given number of items at the top of the stack.
+    ///
+    /// The recorded stack has the same order as the full stack, i.e. the last item is the top of
+    /// the stack, so indices into the recorded stack are only meaningful relative to the top.
+    Top(usize),
 }
 
 impl StackSnapshotType {
@@ -319,6 +652,12 @@ impl StackSnapshotType {
         matches!(self, Self::Full)
     }
 
+    /// Returns true if this is the [StackSnapshotType::Top] variant
+    #[inline]
+    pub const fn is_top(self) -> bool {
+        matches!(self, Self::Top(_))
+    }
+
     /// Returns true if this is the [StackSnapshotType::Pushes] variant
     #[inline]
     pub const fn is_pushes(self) -> bool {
@@ -334,7 +673,6 @@ pub(crate) enum TraceStyle {
     /// Parity style tracer
     Parity,
     /// Geth style tracer
-    #[allow(dead_code)]
     Geth,
 }
 
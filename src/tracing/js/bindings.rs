@@ -20,10 +20,12 @@ use boa_engine::{
 use boa_gc::{empty_trace, Finalize, Trace};
 use revm::{
     interpreter::{
-        opcode::{PUSH0, PUSH32},
+        opcode::{
+            CALL, CALLCODE, CREATE, CREATE2, DELEGATECALL, LOG0, LOG4, PUSH0, PUSH32, STATICCALL,
+        },
         OpCode, SharedMemory, Stack,
     },
-    primitives::{AccountInfo, Bytecode, EvmState, KECCAK_EMPTY},
+    primitives::{AccountInfo, Bytecode, EvmState, BLOCK_HASH_HISTORY, KECCAK_EMPTY},
     DatabaseRef,
 };
 use std::{cell::RefCell, rc::Rc};
@@ -176,6 +178,8 @@ pub(crate) struct StepLog {
     pub(crate) error: Option<String>,
     /// The contract object available to the js inspector
     pub(crate) contract: Contract,
+    /// The return data of the last call before step execution
+    pub(crate) returndata: Bytes,
 }
 
 impl StepLog {
@@ -194,6 +198,7 @@ impl StepLog {
             refund,
             error,
             contract,
+            returndata,
         } = self;
         let obj = JsObject::default();
 
@@ -220,6 +225,16 @@ impl StepLog {
         let get_cost = js_value_getter!(cost, ctx);
         let get_refund = js_value_getter!(refund, ctx);
         let get_depth = js_value_getter!(depth, ctx);
+        let returndata = to_byte_array_value(returndata, ctx)?;
+        let get_returndata = FunctionObjectBuilder::new(
+            ctx.realm(),
+            NativeFunction::from_copy_closure_with_captures(
+                move |_this, _args, returndata, _ctx| Ok(returndata.clone()),
+                returndata,
+            ),
+        )
+        .length(0)
+        .build();
 
         obj.set(js_string!("getPC"), get_pc, false, ctx)?;
         obj.set(js_string!("getError"), get_error, false, ctx)?;
@@ -227,6 +242,7 @@ impl StepLog {
         obj.set(js_string!("getCost"), get_cost, false, ctx)?;
         obj.set(js_string!("getDepth"), get_depth, false, ctx)?;
         obj.set(js_string!("getRefund"), get_refund, false, ctx)?;
+        obj.set(js_string!("getReturnData"), get_returndata, false, ctx)?;
 
         Ok(obj)
     }
@@ -267,7 +283,7 @@ impl MemoryRef {
                 move |_this, args, memory, ctx| {
                     let start = args.get_or_undefined(0).to_number(ctx)?;
                     let end = args.get_or_undefined(1).to_number(ctx)?;
-                    if end < start || start < 0. || (end as usize) < memory.len() {
+                    if end < start || start < 0. || (end as usize) > memory.len() {
                         return Err(JsError::from_native(JsNativeError::typ().with_message(
                             format!(
                                 "tracer accessed out of bound memory: offset {start}, end {end}"
@@ -376,6 +392,9 @@ impl OpObj {
         let obj = JsObject::default();
         let value = self.0;
         let is_push = (PUSH0..=PUSH32).contains(&value);
+        let is_call = matches!(value, CALL | CALLCODE | DELEGATECALL | STATICCALL);
+        let is_create = matches!(value, CREATE | CREATE2);
+        let is_log = (LOG0..=LOG4).contains(&value);
 
         let to_number = FunctionObjectBuilder::new(
             context.realm(),
@@ -391,6 +410,29 @@ impl OpObj {
         .length(0)
         .build();
 
+        let is_call = FunctionObjectBuilder::new(
+            context.realm(),
+            NativeFunction::from_copy_closure(move |_this, _args, _ctx| Ok(JsValue::from(is_call))),
+        )
+        .length(0)
+        .build();
+
+        let is_create = FunctionObjectBuilder::new(
+            context.realm(),
+            NativeFunction::from_copy_closure(move |_this, _args, _ctx| {
+                Ok(JsValue::from(is_create))
+            }),
+        )
+        .length(0)
+        .build();
+
+        let is_log = FunctionObjectBuilder::new(
+            context.realm(),
+            NativeFunction::from_copy_closure(move |_this, _args, _ctx| Ok(JsValue::from(is_log))),
+        )
+        .length(0)
+        .build();
+
         let to_string = FunctionObjectBuilder::new(
             context.realm(),
             NativeFunction::from_copy_closure(move |_this, _args, _ctx| {
@@ -407,6 +449,9 @@ impl OpObj {
         obj.set(js_string!("toNumber"), to_number, false, context)?;
         obj.set(js_string!("toString"), to_string, false, context)?;
         obj.set(js_string!("isPush"), is_push, false, context)?;
+        obj.set(js_string!("isCall"), is_call, false, context)?;
+        obj.set(js_string!("isCreate"), is_create, false, context)?;
+        obj.set(js_string!("isLog"), is_log, false, context)?;
         Ok(obj)
     }
 }
@@ -661,11 +706,16 @@ pub(crate) struct JsEvmContext {
     /// Target of the transaction
     pub(crate) to: Option<Address>,
     pub(crate) input: Bytes,
-    /// Gas limit
+    /// Number, gas available to the top-level call, i.e. the gas limit minus the intrinsic gas
+    ///
+    /// Note: this used to be the unmodified gas limit of the transaction, which is now available
+    /// as `txGasLimit`.
     pub(crate) gas: u64,
+    /// Number, gas limit of the transaction
+    pub(crate) tx_gas_limit: u64,
     /// Number, amount of gas used in executing the transaction (excludes txdata costs)
     pub(crate) gas_used: u64,
-    /// Number, gas price configured in the transaction being executed
+    /// Number, effective gas price of the transaction being executed
     pub(crate) gas_price: u64,
     /// Number, intrinsic gas for the transaction being executed
     pub(crate) intrinsic_gas: u64,
@@ -673,6 +723,10 @@ pub(crate) struct JsEvmContext {
     pub(crate) value: U256,
     /// Number, block number
     pub(crate) block: u64,
+    /// Number, chain id of the network
+    pub(crate) chain_id: u64,
+    /// big.int, base fee of the block, 0 for blocks before London
+    pub(crate) base_fee: U256,
     pub(crate) output: Bytes,
     /// Number, block number
     pub(crate) time: String,
@@ -687,11 +741,14 @@ impl JsEvmContext {
             to,
             input,
             gas,
+            tx_gas_limit,
             gas_used,
             gas_price,
             intrinsic_gas,
             value,
             block,
+            chain_id,
+            base_fee,
             output,
             time,
             transaction_ctx,
@@ -710,11 +767,14 @@ impl JsEvmContext {
 
         obj.set(js_string!("input"), to_byte_array(input, ctx)?, false, ctx)?;
         obj.set(js_string!("gas"), gas, false, ctx)?;
+        obj.set(js_string!("txGasLimit"), tx_gas_limit, false, ctx)?;
         obj.set(js_string!("gasUsed"), gas_used, false, ctx)?;
         obj.set(js_string!("gasPrice"), gas_price, false, ctx)?;
         obj.set(js_string!("intrinsicGas"), intrinsic_gas, false, ctx)?;
         obj.set(js_string!("value"), to_bigint(value, ctx)?, false, ctx)?;
         obj.set(js_string!("block"), block, false, ctx)?;
+        obj.set(js_string!("chainId"), chain_id, false, ctx)?;
+        obj.set(js_string!("baseFee"), to_bigint(base_fee, ctx)?, false, ctx)?;
         obj.set(js_string!("output"), to_byte_array(output, ctx)?, false, ctx)?;
         obj.set(js_string!("time"), js_string!(time), false, ctx)?;
         if let Some(block_hash) = transaction_ctx.block_hash {
@@ -738,8 +798,12 @@ pub(crate) struct EvmDbRef {
 }
 
 impl EvmDbRef {
-    /// Creates a new evm and db JS object.
-    pub(crate) fn new<'a, 'b, DB>(state: &'a EvmState, db: &'b DB) -> (Self, EvmDbGuard<'a, 'b>)
+    /// Creates a new evm and db JS object for the block with the given number.
+    pub(crate) fn new<'a, 'b, DB>(
+        state: &'a EvmState,
+        db: &'b DB,
+        block_number: U256,
+    ) -> (Self, EvmDbGuard<'a, 'b>)
     where
         DB: DatabaseRef,
         DB::Error: std::fmt::Display,
@@ -762,7 +826,7 @@ impl EvmDbRef {
 
         let (db, db_guard) = GcDb::new(js_db);
 
-        let inner = EvmDbRefInner { state, db };
+        let inner = EvmDbRefInner { state, db, block_number };
         let this = Self { inner: Rc::new(inner) };
         let guard = EvmDbGuard { _state_guard: state_guard, _db_guard: db_guard };
         (this, guard)
@@ -785,11 +849,12 @@ impl EvmDbRef {
         }
     }
 
-    fn read_code(&self, address: JsValue, ctx: &mut Context) -> JsResult<JsUint8Array> {
+    /// Returns the bytecode of the account, or `None` if the account has no code.
+    fn read_bytecode(&self, address: JsValue, ctx: &mut Context) -> JsResult<Option<Bytecode>> {
         let acc = self.read_basic(address, ctx)?;
         let code_hash = acc.map(|acc| acc.code_hash).unwrap_or(KECCAK_EMPTY);
         if code_hash == KECCAK_EMPTY {
-            return JsUint8Array::from_iter(std::iter::empty(), ctx);
+            return Ok(None);
         }
 
         let Some(Ok(bytecode)) = self.inner.db.0.with_inner(|db| db.code_by_hash_ref(code_hash))
@@ -800,7 +865,18 @@ impl EvmDbRef {
             ));
         };
 
-        to_byte_array(bytecode.bytecode().to_vec(), ctx)
+        Ok(Some(bytecode))
+    }
+
+    fn read_code(&self, address: JsValue, ctx: &mut Context) -> JsResult<JsUint8Array> {
+        match self.read_bytecode(address, ctx)? {
+            Some(bytecode) => to_byte_array(bytecode.bytecode().to_vec(), ctx),
+            None => JsUint8Array::from_iter(std::iter::empty(), ctx),
+        }
+    }
+
+    fn read_code_size(&self, address: JsValue, ctx: &mut Context) -> JsResult<usize> {
+        Ok(self.read_bytecode(address, ctx)?.map(|bytecode| bytecode.len()).unwrap_or_default())
     }
 
     fn read_state(
@@ -829,6 +905,32 @@ impl EvmDbRef {
         to_byte_array(value.0, ctx)
     }
 
+    /// Returns the hash of the block with the given number.
+    ///
+    /// Like the `BLOCKHASH` opcode, this returns the zero hash for blocks that are not among the
+    /// 256 most recent blocks.
+    fn read_block_hash(&self, number: JsValue, ctx: &mut Context) -> JsResult<JsUint8Array> {
+        let number = number.to_number(ctx)?;
+        let current = self.inner.block_number;
+        // anything that isn't a block number is out of range as well
+        let number =
+            if number.is_finite() && number >= 0.0 { U256::from(number as u64) } else { current };
+        if number >= current || current - number > U256::from(BLOCK_HASH_HISTORY) {
+            return to_byte_array(B256::ZERO.0, ctx);
+        }
+
+        let res = self.inner.db.0.with_inner(|db| db.block_hash_ref(number));
+        let hash = match res {
+            Some(Ok(hash)) => hash,
+            _ => {
+                return Err(JsError::from_native(JsNativeError::error().with_message(format!(
+                    "Failed to read block hash of block {number} from database",
+                ))))
+            }
+        };
+        to_byte_array(hash.0, ctx)
+    }
+
     pub(crate) fn into_js_object(self, ctx: &mut Context) -> JsResult<JsObject> {
         let obj = JsObject::default();
         let exists = FunctionObjectBuilder::new(
@@ -889,6 +991,19 @@ impl EvmDbRef {
         .length(1)
         .build();
 
+        let get_code_size = FunctionObjectBuilder::new(
+            ctx.realm(),
+            NativeFunction::from_copy_closure_with_captures(
+                move |_this, args, db, ctx| {
+                    let val = args.get_or_undefined(0).clone();
+                    Ok(JsValue::from(db.read_code_size(val, ctx)?))
+                },
+                self.clone(),
+            ),
+        )
+        .length(1)
+        .build();
+
         let get_state = FunctionObjectBuilder::new(
             ctx.realm(),
             NativeFunction::from_copy_closure_with_captures(
@@ -897,16 +1012,31 @@ impl EvmDbRef {
                     let slot = args.get_or_undefined(1).clone();
                     Ok(db.read_state(addr, slot, ctx)?.into())
                 },
-                self,
+                self.clone(),
             ),
         )
         .length(2)
         .build();
 
+        let get_block_hash = FunctionObjectBuilder::new(
+            ctx.realm(),
+            NativeFunction::from_copy_closure_with_captures(
+                move |_this, args, db, ctx| {
+                    let number = args.get_or_undefined(0).clone();
+                    Ok(db.read_block_hash(number, ctx)?.into())
+                },
+                self,
+            ),
+        )
+        .length(1)
+        .build();
+
         obj.set(js_string!("getBalance"), get_balance, false, ctx)?;
         obj.set(js_string!("getNonce"), get_nonce, false, ctx)?;
         obj.set(js_string!("getCode"), get_code, false, ctx)?;
+        obj.set(js_string!("getCodeSize"), get_code_size, false, ctx)?;
         obj.set(js_string!("getState"), get_state, false, ctx)?;
+        obj.set(js_string!("getBlockHash"), get_block_hash, false, ctx)?;
         obj.set(js_string!("exists"), exists, false, ctx)?;
         Ok(obj)
     }
@@ -922,6 +1052,8 @@ unsafe impl Trace for EvmDbRef {
 struct EvmDbRefInner {
     state: StateRef,
     db: GcDb<Box<dyn DatabaseRef<Error = String> + 'static>>,
+    /// The number of the block that is executed, used to resolve `BLOCKHASH` semantics.
+    block_number: U256,
 }
 
 /// Guard the inner references, once this value is dropped the inner reference is also removed.
@@ -1057,7 +1189,7 @@ mod tests {
         let mut db = CacheDB::new(EmptyDB::new());
         let state = EvmState::default();
         {
-            let (db, guard) = EvmDbRef::new(&state, &db);
+            let (db, guard) = EvmDbRef::new(&state, &db, U256::ZERO);
             let addr = Address::default();
             let addr = JsValue::from(js_string!(addr.to_string()));
             let db = db.into_js_object(&mut context).unwrap();
@@ -1073,7 +1205,7 @@ mod tests {
         db.insert_account_info(addr, Default::default());
 
         {
-            let (db, guard) = EvmDbRef::new(&state, &db);
+            let (db, guard) = EvmDbRef::new(&state, &db, U256::ZERO);
             let addr = JsValue::from(js_string!(addr.to_string()));
             let db = db.into_js_object(&mut context).unwrap();
             let res = f.call(&result, &[db.clone().into(), addr.clone()], &mut context).unwrap();
@@ -1088,6 +1220,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_evm_db_code_size() {
+        let mut context = Context::default();
+
+        let result = context
+            .eval(Source::from_bytes("(function(db, addr) { return db.getCodeSize(addr) })"))
+            .unwrap();
+        let f = result.as_callable().unwrap();
+
+        let mut db = CacheDB::new(EmptyDB::new());
+        let contract = Address::with_last_byte(1);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x01, 0x00]));
+        db.insert_account_info(contract, AccountInfo::new(U256::ZERO, 0, code.hash_slow(), code));
+        let state = EvmState::default();
+
+        let (db, _guard) = EvmDbRef::new(&state, &db, U256::ZERO);
+        let db = db.into_js_object(&mut context).unwrap();
+
+        let addr = JsValue::from(js_string!(Address::ZERO.to_string()));
+        let res = f.call(&result, &[db.clone().into(), addr], &mut context).unwrap();
+        assert_eq!(res.as_number(), Some(0.0));
+
+        let addr = JsValue::from(js_string!(contract.to_string()));
+        let res = f.call(&result, &[db.into(), addr], &mut context).unwrap();
+        assert_eq!(res.as_number(), Some(3.0));
+    }
+
     #[test]
     fn test_evm_db_gc_captures() {
         let mut context = Context::default();
@@ -1114,7 +1273,7 @@ mod tests {
         let db = CacheDB::new(EmptyDB::new());
         let state = EvmState::default();
         {
-            let (db_ref, guard) = EvmDbRef::new(&state, &db);
+            let (db_ref, guard) = EvmDbRef::new(&state, &db, U256::ZERO);
             let js_db = db_ref.into_js_object(&mut context).unwrap();
             let _res = setup_fn.call(&(obj.clone().into()), &[js_db.into()], &mut context).unwrap();
             assert!(obj.get(js_string!("db"), &mut context).unwrap().is_object());
@@ -1170,6 +1329,7 @@ mod tests {
             refund: 0,
             error: None,
             contract: Default::default(),
+            returndata: Default::default(),
         };
 
         let js_step = step.into_js_object(&mut context).unwrap();
@@ -1262,6 +1422,7 @@ mod tests {
             refund: 0,
             error: None,
             contract: Default::default(),
+            returndata: Default::default(),
         };
 
         let js_step = step.into_js_object(&mut context).unwrap();
@@ -21,7 +21,8 @@ pub(crate) const BIG_INT_JS: &str = include_str!("bigint.js");
 /// If that fails it uses boa's to_json function to convert the value to a JSON object
 ///
 /// We use `JSON.stringify` so that `toJSON` properties are used when converting the value to JSON,
-/// this ensures the `bigint` is serialized properly.
+/// this ensures the `bigint` is serialized properly. Like in geth, byte arrays are serialized as
+/// `0x` prefixed hex strings, see [json_stringify].
 pub(crate) fn to_serde_value(val: JsValue, ctx: &mut Context) -> JsResult<serde_json::Value> {
     if let Ok(json) = json_stringify(val.clone(), ctx) {
         let json = json.to_std_string().map_err(|err| {
@@ -41,6 +42,9 @@ pub(crate) fn to_serde_value(val: JsValue, ctx: &mut Context) -> JsResult<serde_
 }
 
 /// Attempts to use the global `JSON` object to stringify the given value.
+///
+/// `Uint8Array` and `ArrayBuffer` values are serialized as `0x` prefixed hex strings instead of
+/// objects of indexed bytes.
 pub(crate) fn json_stringify(val: JsValue, ctx: &mut Context) -> JsResult<JsString> {
     let json = ctx.global_object().get(js_string!("JSON"), ctx)?;
     let json_obj = json.as_object().ok_or_else(|| {
@@ -52,10 +56,24 @@ pub(crate) fn json_stringify(val: JsValue, ctx: &mut Context) -> JsResult<JsStri
     let stringify = stringify.as_callable().ok_or_else(|| {
         JsError::from_native(JsNativeError::typ().with_message("JSON.stringify is not callable"))
     })?;
-    let res = stringify.call(&json, &[val], ctx)?;
+    let replacer = NativeFunction::from_fn_ptr(bytes_to_hex_replacer).to_js_function(ctx.realm());
+    let res = stringify.call(&json, &[val, replacer.into()], ctx)?;
     res.to_string(ctx)
 }
 
+/// A `JSON.stringify` replacer that converts byte arrays to `0x` prefixed hex strings.
+fn bytes_to_hex_replacer(_: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
+    let val = args.get_or_undefined(1).clone();
+    let is_bytes = val.as_object().is_some_and(|obj| {
+        JsUint8Array::from_object(obj.clone()).is_ok() || obj.is::<ArrayBuffer>()
+    });
+    if !is_bytes {
+        return Ok(val);
+    }
+    let buf = from_buf_value(val, ctx)?;
+    Ok(js_string!(hex::encode_prefixed(buf)).into())
+}
+
 /// Registers all the builtin functions and global bigint property
 ///
 /// Note: this does not register the `isPrecompiled` builtin, as this requires the precompile
@@ -213,28 +231,32 @@ pub(crate) fn to_bigint(value: U256, ctx: &mut Context) -> JsResult<JsValue> {
 /// sender with the given salt and code hash, then converts the resulting address back into a byte
 /// buffer for output.
 pub(crate) fn to_contract2(_: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
-    // Extract the sender's address, salt and initcode from the arguments
-    let from = args.get_or_undefined(0).clone();
-    let salt = match args.get_or_undefined(1).to_string(ctx) {
-        Ok(js_string) => {
-            let buf = hex_decode_js_string(&js_string)?;
-            bytes_to_hash(buf)
-        }
-        Err(_) => {
-            return Err(JsError::from_native(
-                JsNativeError::typ().with_message("invalid salt type"),
-            ))
-        }
-    };
+    const SIGNATURE: &str = "toContract2 expects (from, salt, initCode)";
+    expect_args(SIGNATURE, args, 3)?;
 
-    let initcode = args.get_or_undefined(2).clone();
+    // Extract the sender's address, salt and initcode from the arguments
+    let addr = address_arg(SIGNATURE, args[0].clone(), ctx)?;
 
-    // Convert the sender's address to a byte buffer and then to an Address
-    let buf = from_buf_value(from, ctx)?;
-    let addr = bytes_to_address(buf);
+    let salt = &args[1];
+    let salt = if let Some(js_string) = salt.as_string() {
+        hex_decode_js_string(js_string)
+    } else if salt.is_object() {
+        from_buf_value(salt.clone(), ctx)
+    } else {
+        Err(JsNativeError::typ().with_message("invalid salt type").into())
+    }
+    .map_err(|err| arg_error(SIGNATURE, "salt", err))?;
+    if salt.len() != 32 {
+        return Err(JsError::from_native(JsNativeError::typ().with_message(format!(
+            "{SIGNATURE}: salt must be 32 bytes, got {} bytes",
+            salt.len()
+        ))));
+    }
+    let salt = B256::from_slice(&salt);
 
     // Convert the initcode to a byte buffer
-    let code_buf = from_buf_value(initcode, ctx)?;
+    let code_buf = from_buf_value(args[2].clone(), ctx)
+        .map_err(|err| arg_error(SIGNATURE, "initCode", err))?;
 
     // Compute the contract address
     let contract_addr = addr.create2_from_code(salt, code_buf);
@@ -245,21 +267,49 @@ pub(crate) fn to_contract2(_: &JsValue, args: &[JsValue], ctx: &mut Context) ->
 
 ///  Converts the sender's address to a byte buffer
 pub(crate) fn to_contract(_: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
-    // Extract the sender's address and nonce from the arguments
-    let from = args.get_or_undefined(0).clone();
-    let nonce = args.get_or_undefined(1).to_number(ctx)? as u64;
+    const SIGNATURE: &str = "toContract expects (from, nonce)";
+    expect_args(SIGNATURE, args, 2)?;
 
-    // Convert the sender's address to a byte buffer and then to an Address
-    let buf = from_buf_value(from, ctx)?;
-    let addr = bytes_to_address(buf);
+    // Extract the sender's address and nonce from the arguments
+    let addr = address_arg(SIGNATURE, args[0].clone(), ctx)?;
+    let nonce = args[1].to_number(ctx).map_err(|err| arg_error(SIGNATURE, "nonce", err))?;
+    if !nonce.is_finite() || nonce < 0.0 {
+        return Err(JsError::from_native(
+            JsNativeError::typ().with_message(format!("{SIGNATURE}: invalid nonce {nonce}")),
+        ));
+    }
 
     // Compute the contract address
-    let contract_addr = addr.create(nonce);
+    let contract_addr = addr.create(nonce as u64);
 
     // Convert the contract address to a byte buffer and return it as an ArrayBuffer
     address_to_byte_array_value(contract_addr, ctx)
 }
 
+/// Returns an error naming the expected arguments if fewer than `expected` arguments were passed.
+fn expect_args(signature: &str, args: &[JsValue], expected: usize) -> JsResult<()> {
+    if args.len() < expected {
+        return Err(JsError::from_native(
+            JsNativeError::typ().with_message(format!("{signature}, got {} arguments", args.len())),
+        ));
+    }
+    Ok(())
+}
+
+/// Converts the `from` argument to an address.
+fn address_arg(signature: &str, val: JsValue, ctx: &mut Context) -> JsResult<Address> {
+    let buf = from_buf_value(val, ctx).map_err(|err| arg_error(signature, "from", err))?;
+    Ok(bytes_to_address(buf))
+}
+
+/// Wraps the error of an invalid argument with the name of the argument and the expected
+/// arguments.
+fn arg_error(signature: &str, arg: &str, err: JsError) -> JsError {
+    JsError::from_native(
+        JsNativeError::typ().with_message(format!("{signature}: invalid {arg}: {err}")),
+    )
+}
+
 /// Converts a buffer type to an address
 pub(crate) fn to_address(_: &JsValue, args: &[JsValue], ctx: &mut Context) -> JsResult<JsValue> {
     let val = args.get_or_undefined(0).clone();
@@ -342,4 +392,39 @@ mod tests {
             big_int.as_callable().unwrap().call(&JsValue::undefined(), &[value], &mut ctx).unwrap();
         assert_eq!(result.to_string(&mut ctx).unwrap().to_std_string().unwrap(), "100");
     }
+
+    #[test]
+    fn test_to_contract_errors() {
+        let mut ctx = Context::default();
+        register_builtins(&mut ctx).unwrap();
+
+        let eval_err = |ctx: &mut Context, code: &str| {
+            let err = ctx.eval(Source::from_bytes(code)).unwrap_err();
+            err.to_opaque(ctx).to_string(ctx).unwrap().to_std_string().unwrap()
+        };
+
+        let err = eval_err(&mut ctx, "toContract2('0x01', '0x02')");
+        assert!(err.contains("toContract2 expects (from, salt, initCode)"), "{err}");
+
+        let err = eval_err(&mut ctx, "toContract2(new Uint8Array(20), '0x0102', [])");
+        assert!(err.contains("salt must be 32 bytes, got 2 bytes"), "{err}");
+
+        let err = eval_err(&mut ctx, "toContract(new Uint8Array(20))");
+        assert!(err.contains("toContract expects (from, nonce)"), "{err}");
+
+        let err = eval_err(&mut ctx, "toContract(new Uint8Array(20), -1)");
+        assert!(err.contains("invalid nonce"), "{err}");
+
+        let salt = format!("0x{}", "00".repeat(32));
+        let addr = ctx
+            .eval(Source::from_bytes(&format!(
+                "toHex(toContract2(new Uint8Array(20), '{salt}', []))"
+            )))
+            .unwrap();
+        let expected = Address::ZERO.create2_from_code(B256::ZERO, []);
+        assert_eq!(
+            addr.to_string(&mut ctx).unwrap().to_std_string().unwrap(),
+            hex::encode(expected)
+        );
+    }
 }
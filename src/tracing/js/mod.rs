@@ -10,16 +10,19 @@ use crate::tracing::{
     types::CallKind,
 };
 use alloy_primitives::{Address, Bytes, Log, B256, U256};
+use alloy_rpc_types::trace::geth::{GethDebugTracerType, GethDebugTracingOptions};
 pub use boa_engine::vm::RuntimeLimits;
-use boa_engine::{js_string, Context, JsError, JsObject, JsResult, JsValue, Source};
+use boa_engine::{js_string, Context, JsError, JsNativeError, JsObject, JsResult, JsValue, Source};
 use revm::{
     interpreter::{
-        return_revert, CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome, Gas,
-        InstructionResult, Interpreter, InterpreterResult,
+        gas::validate_initial_tx_gas, return_error, return_revert, CallInputs, CallOutcome,
+        CallScheme, CreateInputs, CreateOutcome, Gas, InstructionResult, Interpreter,
+        InterpreterResult,
     },
-    primitives::{Env, ExecutionResult, Output, ResultAndState, TransactTo},
+    primitives::{Env, ExecutionResult, Output, ResultAndState, SpecId, TransactTo},
     ContextPrecompiles, Database, DatabaseRef, EvmContext, Inspector,
 };
+use std::time::{Duration, Instant};
 
 pub(crate) mod bindings;
 pub(crate) mod builtins;
@@ -71,6 +74,17 @@ pub struct JsInspector {
     call_stack: Vec<CallStackItem>,
     /// Marker to track whether the precompiles have been registered.
     precompiles_registered: bool,
+    /// The intrinsic gas of the transaction, recorded when the root call is entered.
+    intrinsic_gas: u64,
+    /// The point in time after which no more tracer functions are invoked.
+    deadline: Option<Instant>,
+    /// Whether the deadline was exceeded during inspection.
+    timed_out: bool,
+    /// The program counter and opcode of the step that is currently being executed.
+    ///
+    /// Recorded in [Inspector::step] so that a fault is reported for the failing instruction,
+    /// since the interpreter already advanced the program counter in [Inspector::step_end].
+    current_step: (usize, u8),
 }
 
 impl JsInspector {
@@ -93,6 +107,18 @@ impl JsInspector {
         Self::with_transaction_context(code, config, Default::default())
     }
 
+    /// Creates a new inspector from the JS tracer code and the `tracerConfig` of the given
+    /// [GethDebugTracingOptions]. See also [Self::new].
+    ///
+    /// Returns an error if the options don't specify a JS tracer.
+    pub fn from_tracing_options(opts: GethDebugTracingOptions) -> Result<Self, JsInspectorError> {
+        let GethDebugTracingOptions { tracer, tracer_config, .. } = opts;
+        match tracer {
+            Some(GethDebugTracerType::JsTracer(code)) => Self::new(code, tracer_config.into_json()),
+            _ => Err(JsInspectorError::ExpectedJsTracer),
+        }
+    }
+
     /// Creates a new inspector from a javascript code snippet. See also [Self::new].
     ///
     /// This also accepts a [TransactionContext] that gives the JS code access to some contextual
@@ -152,16 +178,7 @@ impl JsInspector {
         let _js_config_value =
             JsValue::from_json(&config, &mut ctx).map_err(JsInspectorError::InvalidJsonConfig)?;
 
-        if let Some(setup_fn) = obj.get(js_string!("setup"), &mut ctx)?.as_object() {
-            if !setup_fn.is_callable() {
-                return Err(JsInspectorError::SetupFunctionNotCallable);
-            }
-
-            // call setup()
-            setup_fn
-                .call(&(obj.clone().into()), &[_js_config_value.clone()], &mut ctx)
-                .map_err(JsInspectorError::SetupCallFailed)?;
-        }
+        call_setup(&obj, &_js_config_value, &mut ctx)?;
 
         Ok(Self {
             ctx,
@@ -176,9 +193,31 @@ impl JsInspector {
             step_fn,
             call_stack: Default::default(),
             precompiles_registered: false,
+            intrinsic_gas: 0,
+            deadline: None,
+            timed_out: false,
+            current_step: (0, 0),
         })
     }
 
+    /// Resets the inspector so it can be reused for the next transaction, e.g. of the same block.
+    ///
+    /// This keeps the JS context and the evaluated tracer object, so the code is not evaluated
+    /// again, but clears the call stack and the deadline and invokes the `setup` function with the
+    /// original config again.
+    ///
+    /// Note: the tracer object, `this` in the tracer functions, is _not_ reset. Tracers that
+    /// accumulate state in `this` must initialize it in `setup` to start from a clean state.
+    pub fn reset(&mut self) -> Result<(), JsInspectorError> {
+        self.call_stack.clear();
+        self.precompiles_registered = false;
+        self.intrinsic_gas = 0;
+        self.deadline = None;
+        self.timed_out = false;
+        self.current_step = (0, 0);
+        call_setup(&self.obj, &self._js_config_value, &mut self.ctx)
+    }
+
     /// Returns the config object.
     pub const fn config(&self) -> &serde_json::Value {
         &self.config
@@ -201,6 +240,20 @@ impl JsInspector {
         self.ctx.set_runtime_limits(limits);
     }
 
+    /// Sets a deadline for the inspection.
+    ///
+    /// Once exceeded, the `step`, `enter` and `exit` functions are no longer invoked, instead the
+    /// current call is reverted and [Self::result] returns [JsInspectorError::Timeout]. Unlike the
+    /// [RuntimeLimits], this bounds the total time spent across all invocations.
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
+    /// Sets the deadline of the inspection to `timeout` from now, see [Self::set_deadline].
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.set_deadline(Instant::now() + timeout);
+    }
+
     /// Calls the result function and returns the result as [serde_json::Value].
     ///
     /// Note: This is supposed to be called after the inspection has finished.
@@ -229,8 +282,12 @@ impl JsInspector {
         DB: DatabaseRef,
         <DB as DatabaseRef>::Error: std::fmt::Display,
     {
+        if self.timed_out {
+            return Err(JsInspectorError::Timeout);
+        }
+
         let ResultAndState { result, state } = res;
-        let (db, _db_guard) = EvmDbRef::new(&state, db);
+        let (db, _db_guard) = EvmDbRef::new(&state, db, env.block.number);
 
         let gas_used = result.gas_used();
         let mut to = None;
@@ -263,14 +320,17 @@ impl JsInspector {
             from: env.tx.caller,
             to,
             input: env.tx.data.clone(),
-            gas: env.tx.gas_limit,
+            gas: env.tx.gas_limit.saturating_sub(self.intrinsic_gas),
+            tx_gas_limit: env.tx.gas_limit,
             gas_used,
-            gas_price: env.tx.gas_price.try_into().unwrap_or(u64::MAX),
+            gas_price: env.effective_gas_price().try_into().unwrap_or(u64::MAX),
             value: env.tx.value,
             block: env.block.number.try_into().unwrap_or(u64::MAX),
+            chain_id: env.cfg.chain_id,
+            base_fee: env.block.basefee,
             output: output_bytes.unwrap_or_default(),
             time: env.block.timestamp.to_string(),
-            intrinsic_gas: 0,
+            intrinsic_gas: self.intrinsic_gas,
             transaction_ctx: self.transaction_context,
         };
         let ctx = ctx.into_js_object(&mut self.ctx)?;
@@ -289,7 +349,19 @@ impl JsInspector {
         Ok(())
     }
 
+    /// Returns an error if the deadline is exceeded.
+    fn check_deadline(&mut self) -> JsResult<()> {
+        if self.timed_out || self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.timed_out = true;
+            return Err(JsError::from_native(
+                JsNativeError::error().with_message("execution timeout"),
+            ));
+        }
+        Ok(())
+    }
+
     fn try_step(&mut self, step: StepLog, db: EvmDbRef) -> JsResult<()> {
+        self.check_deadline()?;
         if let Some(step_fn) = &self.step_fn {
             let step = step.into_js_object(&mut self.ctx)?;
             let db = db.into_js_object(&mut self.ctx)?;
@@ -299,6 +371,7 @@ impl JsInspector {
     }
 
     fn try_enter(&mut self, frame: CallFrame) -> JsResult<()> {
+        self.check_deadline()?;
         if let Some(enter_fn) = &self.enter_fn {
             let frame = frame.into_js_object(&mut self.ctx)?;
             enter_fn.call(&(self.obj.clone().into()), &[frame.into()], &mut self.ctx)?;
@@ -307,6 +380,7 @@ impl JsInspector {
     }
 
     fn try_exit(&mut self, frame: FrameResult) -> JsResult<()> {
+        self.check_deadline()?;
         if let Some(exit_fn) = &self.exit_fn {
             let frame = frame.into_js_object(&mut self.ctx)?;
             exit_fn.call(&(self.obj.clone().into()), &[frame.into()], &mut self.ctx)?;
@@ -364,9 +438,25 @@ impl JsInspector {
         self.active_call()
     }
 
+    /// Records the intrinsic gas of the transaction if no call has been entered yet.
+    fn record_intrinsic_gas<DB: Database>(&mut self, context: &EvmContext<DB>) {
+        if !self.call_stack.is_empty() {
+            return;
+        }
+        let tx = &context.env.tx;
+        let spec_id = context.spec_id();
+        let intrinsic_gas =
+            validate_initial_tx_gas(spec_id, &tx.data, tx.transact_to.is_create(), &tx.access_list);
+        self.intrinsic_gas = if spec_id.is_enabled_in(SpecId::PRAGUE) {
+            intrinsic_gas.max(calldata_floor_gas(&tx.data))
+        } else {
+            intrinsic_gas
+        };
+    }
+
     /// Registers the precompiles in the JS context
     fn register_precompiles<DB: Database>(&mut self, precompiles: &ContextPrecompiles<DB>) {
-        if !self.precompiles_registered {
+        if self.precompiles_registered {
             return;
         }
         let precompiles = PrecompileList(precompiles.addresses().copied().collect());
@@ -383,11 +473,15 @@ where
     <DB as DatabaseRef>::Error: std::fmt::Display,
 {
     fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        // recorded for `fault`, which is also invoked if there's no `step` function
+        self.current_step = (interp.program_counter(), interp.current_opcode());
+
         if self.step_fn.is_none() {
             return;
         }
 
-        let (db, _db_guard) = EvmDbRef::new(&context.journaled_state.state, &context.db);
+        let (db, _db_guard) =
+            EvmDbRef::new(&context.journaled_state.state, &context.db, context.env.block.number);
 
         let (stack, _stack_guard) = StackRef::new(&interp.stack);
         let (memory, _memory_guard) = MemoryRef::new(&interp.shared_memory);
@@ -402,6 +496,7 @@ where
             refund: interp.gas.refunded() as u64,
             error: None,
             contract: self.active_call().contract.clone(),
+            returndata: interp.return_data_buffer.clone(),
         };
 
         if self.try_step(step, db).is_err() {
@@ -410,30 +505,33 @@ where
     }
 
     fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
-        if self.step_fn.is_none() {
+        // like geth, `fault` is invoked for reverts and for exceptional halts, e.g. running out of
+        // gas or an invalid opcode, regardless of whether the tracer has a `step` function
+        if !matches!(interp.instruction_result, return_revert!() | return_error!()) {
             return;
         }
 
-        if matches!(interp.instruction_result, return_revert!()) {
-            let (db, _db_guard) = EvmDbRef::new(&context.journaled_state.state, &context.db);
-
-            let (stack, _stack_guard) = StackRef::new(&interp.stack);
-            let (memory, _memory_guard) = MemoryRef::new(&interp.shared_memory);
-            let step = StepLog {
-                stack,
-                op: interp.current_opcode().into(),
-                memory,
-                pc: interp.program_counter() as u64,
-                gas_remaining: interp.gas.remaining(),
-                cost: interp.gas.spent(),
-                depth: context.journaled_state.depth(),
-                refund: interp.gas.refunded() as u64,
-                error: Some(format!("{:?}", interp.instruction_result)),
-                contract: self.active_call().contract.clone(),
-            };
+        let (db, _db_guard) =
+            EvmDbRef::new(&context.journaled_state.state, &context.db, context.env.block.number);
 
-            let _ = self.try_fault(step, db);
-        }
+        let (pc, op) = self.current_step;
+        let (stack, _stack_guard) = StackRef::new(&interp.stack);
+        let (memory, _memory_guard) = MemoryRef::new(&interp.shared_memory);
+        let step = StepLog {
+            stack,
+            op: op.into(),
+            memory,
+            pc: pc as u64,
+            gas_remaining: interp.gas.remaining(),
+            cost: interp.gas.spent(),
+            depth: context.journaled_state.depth(),
+            refund: interp.gas.refunded() as u64,
+            error: Some(format!("{:?}", interp.instruction_result)),
+            contract: self.active_call().contract.clone(),
+            returndata: interp.return_data_buffer.clone(),
+        };
+
+        let _ = self.try_fault(step, db);
     }
 
     fn log(&mut self, _context: &mut EvmContext<DB>, _log: &Log) {}
@@ -444,6 +542,7 @@ where
         inputs: &mut CallInputs,
     ) -> Option<CallOutcome> {
         self.register_precompiles(&context.precompiles);
+        self.record_intrinsic_gas(context);
 
         // determine correct `from` and `to` based on the call scheme
         let (from, to) = match inputs.scheme {
@@ -470,9 +569,11 @@ where
                 kind: call.kind,
                 gas: inputs.gas_limit,
             };
-            if let Err(_err) = self.try_enter(frame) {
-                todo!("return revert")
-                // return (InstructionResult::Revert, Gas::new(0), err.to_string().into());
+            if let Err(err) = self.try_enter(frame) {
+                return Some(CallOutcome::new(
+                    js_error_to_revert(err),
+                    inputs.return_memory_offset.clone(),
+                ));
             }
         }
 
@@ -507,6 +608,7 @@ where
         inputs: &mut CreateInputs,
     ) -> Option<CreateOutcome> {
         self.register_precompiles(&context.precompiles);
+        self.record_intrinsic_gas(context);
 
         let _ = context.load_account(inputs.caller);
         let nonce = context.journaled_state.account(inputs.caller).info.nonce;
@@ -652,6 +754,29 @@ pub enum JsInspectorError {
     /// Invalid JSON configuration encountered.
     #[error("invalid JSON config: {0}")]
     InvalidJsonConfig(JsError),
+
+    /// The tracing options do not specify a JS tracer.
+    #[error("tracing options do not specify a JS tracer")]
+    ExpectedJsTracer,
+
+    /// The deadline of the inspection was exceeded.
+    #[error("execution timeout")]
+    Timeout,
+}
+
+/// Invokes the optional `setup` function of the tracer object with the given config.
+fn call_setup(obj: &JsObject, config: &JsValue, ctx: &mut Context) -> Result<(), JsInspectorError> {
+    if let Some(setup_fn) = obj.get(js_string!("setup"), ctx)?.as_object() {
+        if !setup_fn.is_callable() {
+            return Err(JsInspectorError::SetupFunctionNotCallable);
+        }
+
+        // call setup()
+        setup_fn
+            .call(&(obj.clone().into()), &[config.clone()], ctx)
+            .map_err(JsInspectorError::SetupCallFailed)?;
+    }
+    Ok(())
 }
 
 /// Converts a JavaScript error into a [InstructionResult::Revert] [InterpreterResult].
@@ -664,9 +789,542 @@ fn js_error_to_revert(err: JsError) -> InterpreterResult {
     }
 }
 
+/// Returns the minimum gas a transaction with the given calldata is charged since Prague.
+///
+/// See [EIP-7623](https://eips.ethereum.org/EIPS/eip-7623)
+fn calldata_floor_gas(data: &[u8]) -> u64 {
+    const TOTAL_COST_FLOOR_PER_TOKEN: u64 = 10;
+    const NON_ZERO_BYTE_TOKENS: u64 = 4;
+
+    let zero_bytes = data.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = data.len() as u64 - zero_bytes;
+    let tokens = zero_bytes + non_zero_bytes * NON_ZERO_BYTE_TOKENS;
+    21_000 + tokens * TOTAL_COST_FLOOR_PER_TOKEN
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy_primitives::{bytes, hex};
+    use alloy_rpc_types::trace::geth::GethDebugTracerConfig;
+    use revm::{
+        db::{CacheDB, EmptyDB},
+        inspector_handle_register,
+        primitives::{AccountInfo, Bytecode, SpecId, TransactTo, TxEnv},
+    };
+
+    fn run_intrinsic_gas(spec_id: SpecId, tx: TxEnv) -> u64 {
+        let code = "{ result: function(ctx) { return ctx.intrinsicGas; }, fault: function() {} }";
+        let mut insp = JsInspector::new(code.to_string(), serde_json::Value::Null).unwrap();
+        let mut evm = revm::Evm::builder()
+            .with_db(CacheDB::new(EmptyDB::default()))
+            .with_external_context(&mut insp)
+            .modify_block_env(|block| block.gas_limit = U256::MAX)
+            .with_tx_env(tx)
+            .with_spec_id(spec_id)
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let res = evm.transact().unwrap();
+        let (db, env) = evm.into_db_and_env_with_handler_cfg();
+        let result = insp.json_result(res, &env, &db).unwrap();
+        result.as_u64().unwrap()
+    }
+
+    fn stop_result() -> ResultAndState {
+        ResultAndState {
+            result: ExecutionResult::Success {
+                reason: revm::primitives::SuccessReason::Stop,
+                gas_used: 0,
+                gas_refunded: 0,
+                logs: vec![],
+                output: Output::Call(Bytes::new()),
+            },
+            state: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_effective_gas_price() {
+        let gas_price = |env: &Env| {
+            let code = "{ result: function(ctx) { return ctx.gasPrice; }, fault: function() {} }";
+            let mut insp = JsInspector::new(code.to_string(), serde_json::Value::Null).unwrap();
+            let result =
+                insp.json_result(stop_result(), env, &CacheDB::new(EmptyDB::default())).unwrap();
+            result.as_u64().unwrap()
+        };
+
+        let mut env = Env::default();
+        env.block.basefee = U256::from(10);
+
+        // legacy
+        env.tx.gas_price = U256::from(100);
+        assert_eq!(gas_price(&env), 100);
+
+        // 1559: basefee + priority fee
+        env.tx.gas_priority_fee = Some(U256::from(5));
+        assert_eq!(gas_price(&env), 15);
+
+        // 1559: capped by max fee
+        env.tx.gas_priority_fee = Some(U256::from(95));
+        assert_eq!(gas_price(&env), 100);
+
+        // 1559: basefee exceeds max fee
+        env.block.basefee = U256::from(200);
+        assert_eq!(gas_price(&env), 100);
+    }
+
+    #[test]
+    fn test_tracer_config_from_tracing_options() {
+        let code = "{ setup: function(config) { this.config = config; }, result: function() { return this.config; }, fault: function() {} }";
+        let opts = GethDebugTracingOptions::default()
+            .with_tracer(GethDebugTracerType::JsTracer(code.to_string()))
+            .with_config(GethDebugTracerConfig(serde_json::json!({ "foo": "bar", "depth": 2 })));
+        let mut insp = JsInspector::from_tracing_options(opts).unwrap();
+        assert_eq!(insp.config(), &serde_json::json!({ "foo": "bar", "depth": 2 }));
+
+        let result = insp
+            .json_result(stop_result(), &Env::default(), &CacheDB::new(EmptyDB::default()))
+            .unwrap();
+        assert_eq!(result, serde_json::json!({ "foo": "bar", "depth": 2 }));
+
+        let err = JsInspector::from_tracing_options(GethDebugTracingOptions::default());
+        assert!(matches!(err, Err(JsInspectorError::ExpectedJsTracer)));
+    }
+
+    #[test]
+    fn test_byte_array_result() {
+        let code = r#"{
+            result: function() {
+                return {
+                    word: toWord('0xffaa'),
+                    buffer: new Uint8Array([1, 2]).buffer,
+                    array: [1, 2],
+                };
+            },
+            fault: function() {}
+        }"#;
+        let mut insp = JsInspector::new(code.to_string(), serde_json::Value::Null).unwrap();
+        let result = insp
+            .json_result(stop_result(), &Env::default(), &CacheDB::new(EmptyDB::default()))
+            .unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!({
+                "word": "0x000000000000000000000000000000000000000000000000000000000000ffaa",
+                "buffer": "0x0102",
+                "array": [1, 2],
+            })
+        );
+    }
+
+    #[test]
+    fn test_db_get_block_hash() {
+        let code = r#"{
+            result: function(ctx, db) {
+                return [
+                    db.getBlockHash(ctx.block - 1).length,
+                    toHex(db.getBlockHash(ctx.block - 1)),
+                    toHex(db.getBlockHash(ctx.block - 256)),
+                    toHex(db.getBlockHash(ctx.block - 257)),
+                    toHex(db.getBlockHash(ctx.block)),
+                ];
+            },
+            fault: function() {}
+        }"#;
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.block_hashes.insert(U256::from(999), B256::repeat_byte(0xaa));
+        db.block_hashes.insert(U256::from(744), B256::repeat_byte(0xbb));
+        db.block_hashes.insert(U256::from(743), B256::repeat_byte(0xcc));
+        let mut env = Env::default();
+        env.block.number = U256::from(1000);
+
+        let mut insp = JsInspector::new(code.to_string(), serde_json::Value::Null).unwrap();
+        let result = insp.json_result(stop_result(), &env, &db).unwrap();
+        assert_eq!(
+            result,
+            serde_json::json!([
+                32,
+                hex::encode(B256::repeat_byte(0xaa)),
+                hex::encode(B256::repeat_byte(0xbb)),
+                hex::encode(B256::ZERO),
+                hex::encode(B256::ZERO),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_ctx_chain_id_and_base_fee() {
+        let code = "{ result: function(ctx) { return ctx.chainId + ':' + ctx.baseFee; }, fault: function() {} }";
+
+        let mut env = Env::default();
+        env.cfg.chain_id = 10;
+        env.block.basefee = U256::from(7_000_000_000u64);
+        let mut insp = JsInspector::new(code.to_string(), serde_json::Value::Null).unwrap();
+        let result =
+            insp.json_result(stop_result(), &env, &CacheDB::new(EmptyDB::default())).unwrap();
+        assert_eq!(result, serde_json::json!("10:7000000000"));
+
+        // blocks before London have no base fee
+        env.block.basefee = U256::ZERO;
+        let mut insp = JsInspector::new(code.to_string(), serde_json::Value::Null).unwrap();
+        let result =
+            insp.json_result(stop_result(), &env, &CacheDB::new(EmptyDB::default())).unwrap();
+        assert_eq!(result, serde_json::json!("10:0"));
+    }
+
+    #[test]
+    fn test_ctx_gas_excludes_intrinsic_gas() {
+        let tracer = r#"{
+            result: function(ctx) { return [ctx.txGasLimit, ctx.gas, ctx.intrinsicGas]; },
+            fault: function() {}
+        }"#;
+        let result = run_js_tracer(tracer, &[(Address::with_last_byte(0xaa), bytes!("00"))]);
+        assert_eq!(result, serde_json::json!([1_000_000, 1_000_000 - 21000, 21000]));
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        let call = TxEnv {
+            gas_limit: 1_000_000,
+            transact_to: TransactTo::Call(Address::with_last_byte(0xaa)),
+            data: bytes!("0001"),
+            ..Default::default()
+        };
+        // 21000 + 4 (zero byte) + 16 (non-zero byte)
+        assert_eq!(run_intrinsic_gas(SpecId::CANCUN, call.clone()), 21020);
+        // pre EIP-2028: non-zero bytes cost 68
+        assert_eq!(run_intrinsic_gas(SpecId::BYZANTIUM, call.clone()), 21072);
+
+        let call_with_access_list =
+            TxEnv { access_list: vec![(Address::with_last_byte(0xbb), vec![U256::ZERO])], ..call };
+        // + 2400 (address) + 1900 (storage key)
+        assert_eq!(run_intrinsic_gas(SpecId::CANCUN, call_with_access_list.clone()), 25320);
+
+        let create = TxEnv {
+            gas_limit: 1_000_000,
+            transact_to: TransactTo::Create,
+            data: hex!("00").into(),
+            ..Default::default()
+        };
+        // 53000 + 4 (zero byte) + 2 (initcode word)
+        assert_eq!(run_intrinsic_gas(SpecId::CANCUN, create), 53006);
+
+        let calldata_heavy = TxEnv {
+            gas_limit: 1_000_000,
+            transact_to: TransactTo::Call(Address::with_last_byte(0xaa)),
+            data: vec![0xff; 1000].into(),
+            ..Default::default()
+        };
+        // 21000 + 1000 * 16 (non-zero byte)
+        assert_eq!(run_intrinsic_gas(SpecId::CANCUN, calldata_heavy.clone()), 37000);
+        // EIP-7623 floor: 21000 + 1000 * 4 (tokens per non-zero byte) * 10
+        assert_eq!(run_intrinsic_gas(SpecId::PRAGUE, calldata_heavy), 61000);
+        // the floor doesn't apply if the standard cost is higher
+        assert_eq!(run_intrinsic_gas(SpecId::PRAGUE, call_with_access_list), 25320);
+    }
+
+    /// Runs the JS tracer for a call to the first of the given contracts.
+    fn run_js_tracer(tracer: &str, contracts: &[(Address, Bytes)]) -> serde_json::Value {
+        let mut db = CacheDB::new(EmptyDB::default());
+        for (address, code) in contracts {
+            db.insert_account_info(
+                *address,
+                AccountInfo { code: Some(Bytecode::new_raw(code.clone())), ..Default::default() },
+            );
+        }
+
+        let mut insp = JsInspector::new(tracer.to_string(), serde_json::Value::Null).unwrap();
+        let mut evm = revm::Evm::builder()
+            .with_db(db)
+            .with_external_context(&mut insp)
+            .modify_block_env(|block| block.gas_limit = U256::MAX)
+            .with_tx_env(TxEnv {
+                gas_limit: 1_000_000,
+                transact_to: TransactTo::Call(contracts[0].0),
+                ..Default::default()
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let res = evm.transact().unwrap();
+        assert!(res.result.is_success());
+        let (db, env) = evm.into_db_and_env_with_handler_cfg();
+        insp.json_result(res, &env, &db).unwrap()
+    }
+
+    /// Runs the JS tracer for a call to a contract that calls another contract that returns 32
+    /// bytes, then emits a log.
+    fn run_call_with_subcall(tracer: &str) -> serde_json::Value {
+        let callee = Address::with_last_byte(0xbb);
+        let address = Address::with_last_byte(0xaa);
+        // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL POP PUSH1 0x00 PUSH1 0x00 LOG0 STOP
+        let code =
+            [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af15060006000a000")]
+                .concat();
+        // PUSH1 0x20 PUSH1 0x00 RETURN
+        let callee_code = bytes!("60206000f3");
+
+        run_js_tracer(tracer, &[(address, code.into()), (callee, callee_code)])
+    }
+
+    #[test]
+    fn test_step_log_return_data() {
+        let code = r#"{
+            data: [],
+            step: function(log) {
+                var op = log.op.toString();
+                if (log.getDepth() == 1 && (op == "CALL" || op == "POP")) {
+                    this.data.push(log.getReturnData().length);
+                }
+            },
+            result: function() { return this.data; },
+            fault: function() {}
+        }"#;
+        let result = run_call_with_subcall(code);
+
+        // no return data before the call, and the callee's output after it
+        assert_eq!(result, serde_json::json!([0, 32]));
+    }
+
+    #[test]
+    fn test_op_helpers() {
+        let code = r#"{
+            calls: 0, creates: 0, logs: 0,
+            step: function(log) {
+                if (log.op.isCall()) { this.calls++; }
+                if (log.op.isCreate()) { this.creates++; }
+                if (log.op.isLog()) { this.logs++; }
+            },
+            result: function() { return [this.calls, this.creates, this.logs]; },
+            fault: function() {}
+        }"#;
+        let result = run_call_with_subcall(code);
+
+        assert_eq!(result, serde_json::json!([1, 0, 1]));
+    }
+
+    #[test]
+    fn test_memory_slice() {
+        // PUSH1 0x01 PUSH1 0x00 MSTORE PUSH1 0x02 PUSH1 0x20 MSTORE STOP
+        let code = bytes!("600160005260026020520000");
+        let tracer = r#"{
+            data: [],
+            step: function(log) {
+                if (log.op.toString() == "STOP") {
+                    this.data.push(toHex(log.memory.slice(0, 32)));
+                    this.data.push(toHex(log.memory.slice(32, 64)));
+                    try {
+                        log.memory.slice(32, 96);
+                    } catch (err) {
+                        this.data.push("out of bounds");
+                    }
+                }
+            },
+            result: function() { return this.data; },
+            fault: function() {}
+        }"#;
+        let result = run_js_tracer(tracer, &[(Address::with_last_byte(0xaa), code)]);
+
+        assert_eq!(
+            result,
+            serde_json::json!([
+                "0000000000000000000000000000000000000000000000000000000000000001",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "out of bounds",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_reset() {
+        let first = Address::with_last_byte(0xaa);
+        let second = Address::with_last_byte(0xbb);
+        let mut db = CacheDB::new(EmptyDB::default());
+        // PUSH1 0x01 PUSH1 0x02 ADD STOP
+        db.insert_account_info(
+            first,
+            AccountInfo {
+                code: Some(Bytecode::new_raw(bytes!("600160020100"))),
+                ..Default::default()
+            },
+        );
+        // STOP
+        db.insert_account_info(
+            second,
+            AccountInfo { code: Some(Bytecode::new_raw(bytes!("00"))), ..Default::default() },
+        );
+
+        let tracer = r#"{
+            setup: function() { this.ops = []; this.calls = 0; },
+            enter: function() { this.calls++; },
+            step: function(log) { this.ops.push(log.op.toString()); },
+            result: function() { return { ops: this.ops, calls: this.calls }; },
+            fault: function() {}
+        }"#;
+        let mut insp = JsInspector::new(tracer.to_string(), serde_json::Value::Null).unwrap();
+
+        let trace = |insp: &mut JsInspector, db: CacheDB<EmptyDB>, to: Address| {
+            let mut evm = revm::Evm::builder()
+                .with_db(db)
+                .with_external_context(&mut *insp)
+                .modify_block_env(|block| block.gas_limit = U256::MAX)
+                .with_tx_env(TxEnv {
+                    gas_limit: 1_000_000,
+                    transact_to: TransactTo::Call(to),
+                    ..Default::default()
+                })
+                .append_handler_register(inspector_handle_register)
+                .build();
+            let res = evm.transact().unwrap();
+            assert!(res.result.is_success());
+            let (db, env) = evm.into_db_and_env_with_handler_cfg();
+            let result = insp.json_result(res, &env, &db).unwrap();
+            (result, db)
+        };
+
+        let (result, db) = trace(&mut insp, db, first);
+        assert_eq!(
+            result,
+            serde_json::json!({ "ops": ["PUSH1", "PUSH1", "ADD", "STOP"], "calls": 0 })
+        );
+
+        insp.reset().unwrap();
+        let (result, _) = trace(&mut insp, db, second);
+        assert_eq!(result, serde_json::json!({ "ops": ["STOP"], "calls": 0 }));
+    }
+
+    #[test]
+    fn test_is_precompiled() {
+        let address = Address::with_last_byte(0xaa);
+        let mut db = CacheDB::new(EmptyDB::default());
+        db.insert_account_info(
+            address,
+            AccountInfo { code: Some(Bytecode::new_raw(bytes!("00"))), ..Default::default() },
+        );
+
+        let tracer = r#"{
+            checked: null,
+            step: function() {
+                this.checked = {
+                    ecrecover: isPrecompiled(toAddress('0x0000000000000000000000000000000000000001')),
+                    contract: isPrecompiled(toAddress('0x00000000000000000000000000000000000000aa'))
+                };
+            },
+            result: function() { return this.checked; },
+            fault: function() {}
+        }"#;
+        let mut insp = JsInspector::new(tracer.to_string(), serde_json::Value::Null).unwrap();
+        let mut evm = revm::Evm::builder()
+            .with_db(db)
+            .with_external_context(&mut insp)
+            .modify_block_env(|block| block.gas_limit = U256::MAX)
+            .with_tx_env(TxEnv {
+                gas_limit: 1_000_000,
+                transact_to: TransactTo::Call(address),
+                ..Default::default()
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let res = evm.transact().unwrap();
+        assert!(res.result.is_success());
+        let (db, env) = evm.into_db_and_env_with_handler_cfg();
+        let result = insp.json_result(res, &env, &db).unwrap();
+        assert_eq!(result, serde_json::json!({ "ecrecover": true, "contract": false }));
+    }
+
+    #[test]
+    fn test_fault_on_halt() {
+        // `step` is optional, `fault` must be invoked without it
+        let tracer = r#"{
+            faults: [],
+            fault: function(log) { this.faults.push({ op: log.op.toString(), error: log.getError() }); },
+            result: function() { return this.faults; }
+        }"#;
+
+        let run = |tracer: &str, code: Bytes| {
+            let address = Address::with_last_byte(0xaa);
+            let mut db = CacheDB::new(EmptyDB::default());
+            db.insert_account_info(
+                address,
+                AccountInfo { code: Some(Bytecode::new_raw(code)), ..Default::default() },
+            );
+
+            let mut insp = JsInspector::new(tracer.to_string(), serde_json::Value::Null).unwrap();
+            let mut evm = revm::Evm::builder()
+                .with_db(db)
+                .with_external_context(&mut insp)
+                .modify_block_env(|block| block.gas_limit = U256::MAX)
+                .with_tx_env(TxEnv {
+                    gas_limit: 100_000,
+                    transact_to: TransactTo::Call(address),
+                    ..Default::default()
+                })
+                .append_handler_register(inspector_handle_register)
+                .build();
+            let res = evm.transact().unwrap();
+            assert!(!res.result.is_success());
+            let (db, env) = evm.into_db_and_env_with_handler_cfg();
+            insp.json_result(res, &env, &db).unwrap()
+        };
+
+        // PUSH1 0x00 INVALID
+        let faults = run(tracer, bytes!("6000fe"));
+        assert_eq!(faults, serde_json::json!([{ "op": "INVALID", "error": "InvalidEFOpcode" }]));
+
+        // JUMPDEST PUSH1 0x00 JUMP
+        let faults = run(tracer, bytes!("5b600056"));
+        assert_eq!(faults, serde_json::json!([{ "op": "JUMP", "error": "OutOfGas" }]));
+
+        // PUSH1 0x00 PUSH1 0x00 REVERT
+        let faults = run(tracer, bytes!("60006000fd"));
+        assert_eq!(faults, serde_json::json!([{ "op": "REVERT", "error": "Revert" }]));
+
+        // the same faults are reported if the tracer also has a `step` function
+        let with_step = tracer.replacen("faults: [],", "faults: [], step: function() {},", 1);
+        let faults = run(&with_step, bytes!("6000fe"));
+        assert_eq!(faults, serde_json::json!([{ "op": "INVALID", "error": "InvalidEFOpcode" }]));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let address = Address::with_last_byte(0xaa);
+        let mut db = CacheDB::new(EmptyDB::default());
+        // JUMPDEST PUSH1 0x00 JUMP
+        db.insert_account_info(
+            address,
+            AccountInfo { code: Some(Bytecode::new_raw(bytes!("5b600056"))), ..Default::default() },
+        );
+
+        let tracer = r#"{
+            step: function() { for (var i = 0; i < 10000; i++) {} },
+            result: function() { return null; },
+            fault: function() {}
+        }"#;
+        let mut insp = JsInspector::new(tracer.to_string(), serde_json::Value::Null).unwrap();
+        let timeout = Duration::from_millis(50);
+        let start = Instant::now();
+        insp.set_timeout(timeout);
+
+        let mut evm = revm::Evm::builder()
+            .with_db(db)
+            .with_external_context(&mut insp)
+            .modify_block_env(|block| block.gas_limit = U256::MAX)
+            .with_tx_env(TxEnv {
+                gas_limit: 30_000_000,
+                transact_to: TransactTo::Call(address),
+                ..Default::default()
+            })
+            .append_handler_register(inspector_handle_register)
+            .build();
+        let res = evm.transact().unwrap();
+        assert!(!res.result.is_success());
+        // aborted right after the deadline, not after running out of gas
+        assert!(start.elapsed() < timeout * 20);
+
+        let (db, env) = evm.into_db_and_env_with_handler_cfg();
+        let err = insp.json_result(res, &env, &db).unwrap_err();
+        assert!(matches!(err, JsInspectorError::Timeout));
+    }
 
     #[test]
     fn test_loop_iteration_limit() {
@@ -4,25 +4,40 @@ use crate::{
     tracing::{
         arena::PushTraceKind,
         types::{
-            CallKind, CallTraceNode, RecordedMemory, StorageChange, StorageChangeReason,
-            TraceMemberOrder,
+            CallKind, CallTraceNode, CalldataRead, RecordedMemory, StorageChange,
+            StorageChangeReason, TraceMemberOrder,
         },
-        utils::gas_used,
+        utils::{delegation_designator_target, gas_used, rlp_create_address},
     },
 };
-use alloy_primitives::{Address, Bytes, Log, U256};
+use alloy_primitives::{keccak256, Address, Bytes, Log, U256};
 use revm::{
     interpreter::{
-        opcode, CallInputs, CallOutcome, CallScheme, CreateInputs, CreateOutcome,
-        InstructionResult, Interpreter, InterpreterResult, OpCode,
+        gas::memory_gas_for_len, opcode, CallInputs, CallOutcome, CallScheme, CreateInputs,
+        CreateOutcome, InstructionResult, Interpreter, InterpreterResult, OpCode,
     },
-    primitives::SpecId,
+    primitives::{CreateScheme, EVMError, SpecId},
     Database, EvmContext, Inspector, JournalEntry,
 };
+use std::time::Instant;
 
 mod arena;
 pub use arena::CallTraceArena;
 
+#[cfg(feature = "binary")]
+mod binary;
+#[cfg(feature = "binary")]
+pub use binary::BinaryDecodeError;
+
+mod bigram;
+pub use bigram::BigramInspector;
+
+mod block;
+pub use block::BlockTracer;
+
+mod callback;
+pub use callback::FrameCallbackInspector;
+
 mod builder;
 pub use builder::{
     geth::{self, GethTraceBuilder},
@@ -44,13 +59,13 @@ use types::{CallLog, CallTrace, CallTraceStep};
 mod utils;
 
 mod writer;
-pub use writer::TraceWriter;
+pub use writer::{MarkdownTraceWriter, StreamingTraceWriter, TraceWriter};
 
 #[cfg(feature = "js-tracer")]
 pub mod js;
 
 mod mux;
-pub use mux::{Error as MuxError, MuxInspector};
+pub use mux::{Error as MuxError, MuxFailures, MuxInspector};
 
 /// An inspector that collects call traces.
 ///
@@ -72,10 +87,14 @@ pub struct TracingInspector {
     step_stack: Vec<StackStep>,
     /// Tracks the return value of the last call
     last_call_return_data: Option<Bytes>,
-    /// The spec id of the EVM.
-    ///
-    /// This is filled during execution.
-    spec_id: Option<SpecId>,
+    /// The number of recorded steps across all calls.
+    recorded_steps: usize,
+    /// Whether steps were dropped because [TracingInspectorConfig::max_steps] was reached.
+    steps_truncated: bool,
+    /// The refund counter before a pre-London `SELFDESTRUCT` is executed.
+    selfdestruct_refunded: Option<i64>,
+    /// The gas left in the caller before the call instruction that is being executed.
+    caller_gas_remaining: Option<u64>,
 }
 
 // === impl TracingInspector ===
@@ -97,7 +116,10 @@ impl TracingInspector {
             trace_stack,
             step_stack,
             last_call_return_data,
-            spec_id,
+            recorded_steps,
+            steps_truncated,
+            selfdestruct_refunded,
+            caller_gas_remaining,
             // kept
             config: _,
         } = self;
@@ -105,7 +127,10 @@ impl TracingInspector {
         trace_stack.clear();
         step_stack.clear();
         last_call_return_data.take();
-        spec_id.take();
+        *recorded_steps = 0;
+        *steps_truncated = false;
+        selfdestruct_refunded.take();
+        caller_gas_remaining.take();
     }
 
     /// Resets the inspector to it's initial state of [Self::new].
@@ -130,7 +155,13 @@ impl TracingInspector {
         &mut self,
         f: impl FnOnce(TracingInspectorConfig) -> TracingInspectorConfig,
     ) {
-        self.config = f(self.config);
+        self.config = f(std::mem::take(&mut self.config));
+    }
+
+    /// Returns true if steps were not recorded because [TracingInspectorConfig::max_steps] was
+    /// reached.
+    pub const fn steps_truncated(&self) -> bool {
+        self.steps_truncated
     }
 
     /// Gets a reference to the recorded call traces.
@@ -166,31 +197,38 @@ impl TracingInspector {
     /// transaction.
     ///
     /// This allows setting it manually by consuming the execution result's gas for example.
+    ///
+    /// This expects the receipt gas used, i.e. [ExecutionResult::gas_used], which includes the
+    /// intrinsic gas of the transaction. All other traces keep the gas used by their execution.
+    ///
+    /// [ExecutionResult::gas_used]: revm::primitives::ExecutionResult::gas_used
     #[inline]
-    pub fn set_transaction_gas_used(&mut self, gas_used: u64) {
+    pub fn set_transaction_gas_used(&mut self, receipt_gas_used: u64) {
         if let Some(node) = self.traces.arena.first_mut() {
-            node.trace.gas_used = gas_used;
+            node.trace.gas_used = receipt_gas_used;
         }
     }
 
     /// Convenience function for [ParityTraceBuilder::set_transaction_gas_used] that consumes the
     /// type.
     #[inline]
-    pub fn with_transaction_gas_used(mut self, gas_used: u64) -> Self {
-        self.set_transaction_gas_used(gas_used);
+    pub fn with_transaction_gas_used(mut self, receipt_gas_used: u64) -> Self {
+        self.set_transaction_gas_used(receipt_gas_used);
         self
     }
 
     /// Consumes the Inspector and returns a [ParityTraceBuilder].
     #[inline]
     pub fn into_parity_builder(self) -> ParityTraceBuilder {
-        ParityTraceBuilder::new(self.traces.arena, self.spec_id, self.config)
+        ParityTraceBuilder::new(self.traces.arena, self.traces.spec_id, self.config)
     }
 
     /// Consumes the Inspector and returns a [GethTraceBuilder].
     #[inline]
     pub fn into_geth_builder(self) -> GethTraceBuilder {
         GethTraceBuilder::new(self.traces.arena, self.config)
+            .with_spec_id(self.traces.spec_id)
+            .with_steps_truncated(self.steps_truncated)
     }
 
     /// Returns true if we're no longer in the context of the root call.
@@ -216,6 +254,43 @@ impl TracingInspector {
         false
     }
 
+    /// Returns the address the code of the account delegates to, if it's an EIP-7702 delegation
+    /// designator.
+    ///
+    /// This reads the account from the journaled state if it's loaded and from the database
+    /// otherwise, so the account's warm/cold status is not affected. A database error is stored in
+    /// the context, like the interpreter does for failed account loads, which aborts the
+    /// transaction.
+    fn delegation_target<DB: Database>(
+        context: &mut EvmContext<DB>,
+        address: Address,
+    ) -> Option<Address> {
+        let info = match context.journaled_state.state.get(&address) {
+            Some(account) => account.info.clone(),
+            None => match context.db.basic(address) {
+                Ok(info) => info?,
+                Err(err) => {
+                    context.error = Err(EVMError::Database(err));
+                    return None;
+                }
+            },
+        };
+        if info.is_empty_code_hash() {
+            return None;
+        }
+        let code = match info.code {
+            Some(code) => code,
+            None => match context.db.code_by_hash(info.code_hash) {
+                Ok(code) => code,
+                Err(err) => {
+                    context.error = Err(EVMError::Database(err));
+                    return None;
+                }
+            },
+        };
+        delegation_designator_target(&code.original_bytes())
+    }
+
     /// Returns the currently active call trace.
     ///
     /// This will be the last call trace pushed to the stack: the call we entered most recently.
@@ -271,15 +346,23 @@ impl TracingInspector {
         mut gas_limit: u64,
         maybe_precompile: Option<bool>,
     ) {
+        // the address whose context the call is executed in, see
+        // [CallTraceNode::execution_address]
+        let execution_address = if kind.is_delegate() { caller } else { address };
+
         // This will only be true if the inspector is configured to exclude precompiles and the call
         // is to a precompile
         let push_kind = if maybe_precompile.unwrap_or(false) {
             // We don't want to track precompiles
             PushTraceKind::PushOnly
+        } else if !self.config.should_record_address(&execution_address) {
+            // We don't want to track calls to addresses that are not configured
+            PushTraceKind::PushOnly
         } else {
             PushTraceKind::PushAndAttachToParent
         };
 
+        let interpreter_gas_limit = gas_limit;
         if self.trace_stack.is_empty() {
             // this is the root call which should get the original gas limit of the transaction,
             // because initialization costs are already subtracted from gas_limit
@@ -289,25 +372,55 @@ impl TracingInspector {
 
             // we set the spec id here because we only need to do this once and this condition is
             // hit exactly once
-            self.spec_id = Some(context.spec_id());
+            self.traces.spec_id = Some(context.spec_id());
         }
 
-        self.trace_stack.push(self.traces.push_trace(
-            0,
-            push_kind,
-            CallTrace {
-                depth: context.journaled_state.depth() as usize,
-                address,
-                kind,
-                data: input_data,
-                value,
-                status: InstructionResult::Continue,
-                caller,
-                maybe_precompile,
-                gas_limit,
-                ..Default::default()
-            },
-        ));
+        // preallocate the steps of the call if configured
+        let steps = match self.config.step_capacity_hint {
+            Some(capacity) if self.config.record_steps => Vec::with_capacity(capacity),
+            _ => Vec::new(),
+        };
+
+        let trace = CallTrace {
+            depth: context.journaled_state.depth() as usize,
+            address,
+            kind,
+            data: input_data,
+            value,
+            status: InstructionResult::Continue,
+            caller,
+            origin: context.env.tx.caller,
+            maybe_precompile,
+            gas_limit,
+            interpreter_gas_limit,
+            caller_gas_remaining: self.caller_gas_remaining.take(),
+            steps,
+            ..Default::default()
+        };
+
+        let idx = if self.config.only_addresses.is_some() && self.is_deep() {
+            // calls of a detached call are attached to the closest attached call instead
+            let parent = self.closest_attached_trace_idx();
+            self.traces.push_child_trace(parent, push_kind, trace)
+        } else {
+            self.traces.push_trace(0, push_kind, trace)
+        };
+        self.trace_stack.push(idx);
+    }
+
+    /// Returns the index of the most recently entered active call that is attached to the call
+    /// graph.
+    fn closest_attached_trace_idx(&self) -> usize {
+        self.trace_stack
+            .iter()
+            .rev()
+            .copied()
+            .find(|idx| match self.traces.arena[*idx].parent {
+                // an active call is attached if it's the last child of its parent
+                Some(parent) => self.traces.arena[parent].children.last() == Some(idx),
+                None => true,
+            })
+            .unwrap_or_default()
     }
 
     /// Fills the current trace with the outcome of a call.
@@ -340,11 +453,21 @@ impl TracingInspector {
         trace.success = trace.status.is_ok();
         trace.output = output.clone();
 
+        if !trace.success {
+            // mark the step that caused the call to fail
+            if let Some(step) = trace.steps.last_mut().filter(|step| step.is_error()) {
+                step.reverts_call = true;
+            }
+        }
+
         self.last_call_return_data = Some(output.clone());
 
         if let Some(address) = created_address {
             // A new contract was created via CREATE
             trace.address = address;
+            if trace.success {
+                trace.init_code_hash = Some(keccak256(output));
+            }
         }
     }
 
@@ -360,15 +483,36 @@ impl TracingInspector {
     fn start_step<DB: Database>(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
         let trace_idx = self.last_trace_idx();
         let trace = &mut self.traces.arena[trace_idx];
+        if self.config.record_op_counts {
+            trace.trace.op_count += 1;
+        }
 
         let step_idx = trace.trace.steps.len();
         // We always want an OpCode, even it is unknown because it could be an additional opcode
         // that not a known constant.
         let op = unsafe { OpCode::new_unchecked(interp.current_opcode()) };
 
-        let record = self.config.should_record_opcode(op);
+        let mut record = self.config.should_record_opcode(op);
+        if record {
+            if self.config.max_steps.is_some_and(|max_steps| self.recorded_steps >= max_steps) {
+                record = false;
+                self.steps_truncated = true;
+            } else {
+                self.recorded_steps += 1;
+            }
+        }
 
-        self.step_stack.push(StackStep { trace_idx, step_idx, record });
+        let memory_len = interp.shared_memory.len();
+        let started_at = (record && self.config.record_step_durations).then(Instant::now);
+        let journal_len = context.journaled_state.journal.last().map_or(0, Vec::len);
+        self.step_stack.push(StackStep {
+            trace_idx,
+            step_idx,
+            record,
+            memory_len,
+            started_at,
+            journal_len,
+        });
 
         if !record {
             return;
@@ -390,10 +534,13 @@ impl TracingInspector {
             RecordedMemory::new(interp.shared_memory.context_memory())
         });
 
-        let stack = if self.config.record_stack_snapshots.is_full() {
-            Some(interp.stack.data().clone())
-        } else {
-            None
+        let stack = match self.config.record_stack_snapshots {
+            StackSnapshotType::Full => Some(interp.stack.data().clone()),
+            StackSnapshotType::Top(n) => {
+                let stack = interp.stack.data();
+                Some(stack[stack.len().saturating_sub(n)..].to_vec())
+            }
+            StackSnapshotType::None | StackSnapshotType::Pushes => None,
         };
         let returndata = self
             .config
@@ -401,6 +548,31 @@ impl TracingInspector {
             .then(|| interp.return_data_buffer.clone())
             .unwrap_or_default();
 
+        let calldata_read = if self.config.record_calldata_reads {
+            match op.get() {
+                opcode::CALLDATALOAD => interp
+                    .stack
+                    .peek(0)
+                    .ok()
+                    .map(|offset| CalldataRead { offset, len: U256::from(32) }),
+                opcode::CALLDATACOPY => match (interp.stack.peek(1), interp.stack.peek(2)) {
+                    (Ok(offset), Ok(len)) => Some(CalldataRead { offset, len }),
+                    _ => None,
+                },
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        // reads of the transient storage are not journaled, so the accessed slot is recorded before
+        // the step is executed
+        let storage_change = if self.config.record_state_diff {
+            transient_storage_change(op.get(), interp, context)
+        } else {
+            None
+        };
+
         let gas_used =
             gas_used(context.spec_id(), interp.gas.spent(), interp.gas.refunded() as u64);
 
@@ -413,14 +585,18 @@ impl TracingInspector {
             push_stack: None,
             memory,
             returndata,
+            calldata_read,
             gas_remaining: interp.gas.remaining(),
             gas_refund_counter: interp.gas.refunded() as u64,
             gas_used,
 
             // fields will be populated end of call
             gas_cost: 0,
-            storage_change: None,
+            refund_delta: 0,
+            storage_change,
             status: InstructionResult::Continue,
+            reverts_call: false,
+            duration: None,
         });
 
         trace.ordering.push(TraceMemberOrder::Step(step_idx));
@@ -435,14 +611,24 @@ impl TracingInspector {
         interp: &mut Interpreter,
         context: &mut EvmContext<DB>,
     ) {
-        let StackStep { trace_idx, step_idx, record } =
+        let StackStep { trace_idx, step_idx, record, memory_len, started_at, journal_len } =
             self.step_stack.pop().expect("can't fill step without starting a step first");
+        let duration = started_at.map(|started_at| started_at.elapsed());
+
+        let trace = &mut self.traces.arena[trace_idx].trace;
+
+        // memory is only ever expanded, charging the difference of the memory cost of both sizes
+        let new_memory_len = interp.shared_memory.len();
+        if new_memory_len > memory_len {
+            trace.memory_gas += memory_gas_for_len(new_memory_len) - memory_gas_for_len(memory_len);
+        }
 
         if !record {
             return;
         }
 
-        let step = &mut self.traces.arena[trace_idx].trace.steps[step_idx];
+        let step = &mut trace.steps[step_idx];
+        step.duration = duration;
 
         if self.config.record_stack_snapshots.is_pushes() {
             let num_pushed = stack_push_count(step.op);
@@ -453,14 +639,15 @@ impl TracingInspector {
         if self.config.record_state_diff {
             let op = step.op.get();
 
-            let journal_entry = context
+            let journal = context
                 .journaled_state
                 .journal
                 .last()
                 // This should always work because revm initializes it as `vec![vec![]]`
                 // See [JournaledState::new](revm::JournaledState)
-                .expect("exists; initialized with vec")
-                .last();
+                .expect("exists; initialized with vec");
+            // only an entry added by this step is relevant, e.g. loading a warm slot adds none
+            let journal_entry = journal.get(journal_len..).and_then(|entries| entries.last());
 
             step.storage_change = match (op, journal_entry) {
                 (
@@ -478,10 +665,31 @@ impl TracingInspector {
                         StorageChange { key: *key, value, had_value: Some(*had_value), reason };
                     Some(change)
                 }
+                (opcode::SLOAD, Some(JournalEntry::StorageWarmed { address, key })) => {
+                    // cold load of the slot
+                    let value = context.journaled_state.state[address].storage[key].present_value();
+                    let change = StorageChange {
+                        key: *key,
+                        value,
+                        had_value: None,
+                        reason: StorageChangeReason::SLOAD,
+                    };
+                    Some(change)
+                }
+                // recorded when the step was started, only kept if it was successful
+                (opcode::TLOAD | opcode::TSTORE, _) if interp.instruction_result.is_ok() => {
+                    step.storage_change
+                }
                 _ => None,
             };
         }
 
+        if self.config.record_gas_refunds || self.config.record_state_diff {
+            // the refund counter can be negative within a call, which wraps around in the recorded
+            // `gas_refund_counter`
+            step.refund_delta = interp.gas.refunded().wrapping_sub(step.gas_refund_counter as i64);
+        }
+
         // The gas cost is the difference between the recorded gas remaining at the start of the
         // step the remaining gas here, at the end of the step.
         // TODO: Figure out why this can overflow. https://github.com/paradigmxyz/evm-inspectors/pull/38
@@ -498,21 +706,67 @@ where
 {
     #[inline]
     fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if self.config.record_selfdestruct_refunds
+            && interp.current_opcode() == opcode::SELFDESTRUCT
+            && !context.spec_id().is_enabled_in(SpecId::LONDON)
+        {
+            self.selfdestruct_refunded = Some(interp.gas.refunded());
+        }
+        if self.config.record_caller_gas
+            && matches!(
+                interp.current_opcode(),
+                opcode::CALL
+                    | opcode::CALLCODE
+                    | opcode::DELEGATECALL
+                    | opcode::STATICCALL
+                    | opcode::CREATE
+                    | opcode::CREATE2
+                    | opcode::EXTCALL
+                    | opcode::EXTDELEGATECALL
+                    | opcode::EXTSTATICCALL
+            )
+        {
+            self.caller_gas_remaining = Some(interp.gas.remaining());
+        }
         if self.config.record_steps {
             self.start_step(interp, context);
+        } else if self.config.record_op_counts {
+            self.last_trace().trace.op_count += 1;
         }
     }
 
     #[inline]
     fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        if self.caller_gas_remaining.is_some() && interp.next_action.is_none() {
+            // the call instruction failed before a new frame was requested
+            self.caller_gas_remaining = None;
+        }
+
+        if let Some(refunded) = self.selfdestruct_refunded.take() {
+            // the refund is only granted if the selfdestruct succeeded
+            if interp.instruction_result == InstructionResult::SelfDestruct {
+                self.last_trace().trace.selfdestruct_gas_refund =
+                    interp.gas.refunded().saturating_sub(refunded) as u64;
+            }
+        }
+
+        if self.config.record_max_memory {
+            let memory_len = interp.shared_memory.len() as u64;
+            let trace = &mut self.last_trace().trace;
+            trace.max_memory = trace.max_memory.max(memory_len);
+        }
+
         if self.config.record_steps {
             self.fill_step_on_step_end(interp, context);
         }
     }
 
     fn log(&mut self, _context: &mut EvmContext<DB>, log: &Log) {
-        if self.config.record_logs {
-            let trace = self.last_trace();
+        let record_logs = self.config.record_logs;
+        let trace = self.last_trace();
+        trace.trace.log_count += 1;
+        trace.trace.log_data_size += log.data.data.len() as u64;
+        if record_logs {
             trace.ordering.push(TraceMemberOrder::Log(trace.logs.len()));
             trace.logs.push(CallLog::from(log.clone()));
         }
@@ -558,6 +812,15 @@ where
             inputs.gas_limit,
             maybe_precompile,
         );
+        let delegated_to =
+            if self.config.record_delegations && context.spec_id().is_enabled_in(SpecId::PRAGUE) {
+                Self::delegation_target(context, inputs.bytecode_address)
+            } else {
+                None
+            };
+        let trace = &mut self.last_trace().trace;
+        trace.is_eof = inputs.is_eof;
+        trace.delegated_to = delegated_to;
 
         None
     }
@@ -579,9 +842,18 @@ where
     ) -> Option<CreateOutcome> {
         let _ = context.load_account(inputs.caller);
         let nonce = context.journaled_state.account(inputs.caller).info.nonce;
+        let created_address = inputs.created_address(nonce);
+        if self.config.verify_create_addresses && matches!(inputs.scheme, CreateScheme::Create) {
+            assert_eq!(
+                created_address,
+                rlp_create_address(inputs.caller, nonce),
+                "CREATE address mismatch for sender {} with nonce {nonce}",
+                inputs.caller
+            );
+        }
         self.start_trace_on_call(
             context,
-            inputs.created_address(nonce),
+            created_address,
             inputs.init_code.clone(),
             inputs.value,
             inputs.scheme.into(),
@@ -589,6 +861,9 @@ where
             inputs.gas_limit,
             Some(false),
         );
+        if let CreateScheme::Create2 { salt } = inputs.scheme {
+            self.last_trace().trace.create2_salt = Some(salt);
+        }
 
         None
     }
@@ -599,6 +874,12 @@ where
         _inputs: &CreateInputs,
         outcome: CreateOutcome,
     ) -> CreateOutcome {
+        if self.config.verify_create_addresses {
+            if let Some(address) = outcome.address {
+                let traced = self.last_trace().trace.address;
+                assert_eq!(address, traced, "created address differs from the traced address");
+            }
+        }
         self.fill_trace_on_call_end(context, &outcome.result, outcome.address);
         outcome
     }
@@ -626,4 +907,44 @@ struct StackStep {
     /// Please note that if `record` is `false`, this will still contain a value, but the step will
     /// not appear in the steps list.
     step_idx: usize,
+    /// The size of the memory at the start of the step, in bytes.
+    memory_len: usize,
+    /// When the step started, if step durations are measured.
+    started_at: Option<Instant>,
+    /// The number of entries in the current journal checkpoint at the start of the step.
+    ///
+    /// Only entries added after this were caused by the step.
+    journal_len: usize,
+}
+
+/// Returns the access of the transient storage slot by a `TLOAD` or `TSTORE` step that is about to
+/// be executed.
+fn transient_storage_change<DB: Database>(
+    op: u8,
+    interp: &Interpreter,
+    context: &EvmContext<DB>,
+) -> Option<StorageChange> {
+    let key = interp.stack.peek(0).ok()?;
+    let current = context
+        .journaled_state
+        .transient_storage
+        .get(&(interp.contract.target_address, key))
+        .copied()
+        .unwrap_or_default();
+    let change = match op {
+        opcode::TLOAD => StorageChange {
+            key,
+            value: current,
+            had_value: None,
+            reason: StorageChangeReason::TLOAD,
+        },
+        opcode::TSTORE => StorageChange {
+            key,
+            value: interp.stack.peek(1).ok()?,
+            had_value: Some(current),
+            reason: StorageChangeReason::TSTORE,
+        },
+        _ => return None,
+    };
+    Some(change)
 }
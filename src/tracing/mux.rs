@@ -10,17 +10,32 @@ use revm::{
     primitives::ResultAndState,
     Database, DatabaseRef, EvmContext, Inspector,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 
+/// The errors of the tracers of a [MuxInspector] that could not be created, keyed by tracer type.
+///
+/// The errors are shared so that the [MuxInspector] remains cloneable.
+pub type MuxFailures = HashMap<GethDebugBuiltInTracerType, Arc<Error>>;
+
 /// Mux tracing inspector that runs and collects results of multiple inspectors at once.
 ///
-/// Contains a list of tracer types with its inspectors.
-#[derive(Clone, Debug)]
-pub struct MuxInspector(Vec<(GethDebugBuiltInTracerType, DelegatingInspector)>);
+/// Contains a list of tracer types with its inspectors, and the tracers that could not be created.
+#[derive(Clone, Debug, Default)]
+pub struct MuxInspector {
+    /// The inspectors of all tracers that were created successfully.
+    inspectors: Vec<(GethDebugBuiltInTracerType, DelegatingInspector)>,
+    /// The errors of all tracers that could not be created.
+    failed: MuxFailures,
+    /// The depth of the call or create that is currently being inspected, 0 if none.
+    depth: usize,
+}
 
 impl MuxInspector {
     /// Try creating a new instance of [MuxInspector] from the given [MuxConfig].
+    ///
+    /// Fails if any of the configured tracers can't be created, see [Self::from_config] for a
+    /// variant that keeps the remaining tracers instead.
     pub fn try_from_config(config: MuxConfig) -> Result<MuxInspector, Error> {
         let inspectors = config
             .0
@@ -30,17 +45,107 @@ impl MuxInspector {
             })
             .collect::<Result<Vec<_>, _>>()?;
 
-        Ok(MuxInspector(inspectors))
+        Ok(MuxInspector { inspectors, failed: HashMap::new(), depth: 0 })
+    }
+
+    /// Creates a new instance of [MuxInspector] from the given [MuxConfig].
+    ///
+    /// Tracers that can't be created, for example because of an invalid config, are skipped and
+    /// their errors are recorded, see [Self::failed_tracers]. All other tracers are run as usual.
+    pub fn from_config(config: MuxConfig) -> MuxInspector {
+        let mut inspectors = Vec::with_capacity(config.0.len());
+        let mut failed = HashMap::new();
+        for (tracer_type, tracer_config) in config.0 {
+            match DelegatingInspector::try_from_config(tracer_type, tracer_config) {
+                Ok(inspector) => inspectors.push(inspector),
+                Err(err) => {
+                    failed.insert(tracer_type, Arc::new(err));
+                }
+            }
+        }
+
+        MuxInspector { inspectors, failed, depth: 0 }
+    }
+
+    /// Adds a tracer of the given type to the inspector, replacing any existing tracer of that
+    /// type.
+    ///
+    /// Returns an error if the tracer can't be created, or if a transaction is currently being
+    /// inspected.
+    pub fn insert_tracer(
+        &mut self,
+        tracer_type: GethDebugBuiltInTracerType,
+        tracer_config: Option<GethDebugTracerConfig>,
+    ) -> Result<(), Error> {
+        if self.depth != 0 {
+            return Err(Error::InspectionInProgress(tracer_type));
+        }
+
+        let inspector = DelegatingInspector::try_from_config(tracer_type, tracer_config)?;
+        self.remove_tracer(tracer_type);
+        self.inspectors.push(inspector);
+        Ok(())
+    }
+
+    /// Removes the tracer of the given type, including its recorded error if it could not be
+    /// created.
+    ///
+    /// Returns true if the inspector had a tracer of that type.
+    pub fn remove_tracer(&mut self, tracer_type: GethDebugBuiltInTracerType) -> bool {
+        let len = self.inspectors.len();
+        self.inspectors.retain(|(ty, _)| *ty != tracer_type);
+        let failed = self.failed.remove(&tracer_type).is_some();
+        len != self.inspectors.len() || failed
+    }
+
+    /// Resets the state of all tracers, so that the inspector can be reused for another
+    /// transaction.
+    ///
+    /// The configured tracers are kept. See also [TracingInspector::fuse].
+    pub fn fuse(&mut self) {
+        for (_, inspector) in &mut self.inspectors {
+            inspector.fuse();
+        }
+        self.depth = 0;
+    }
+
+    /// Resets the state of all tracers, see [Self::fuse].
+    #[inline]
+    pub fn fused(mut self) -> Self {
+        self.fuse();
+        self
+    }
+
+    /// Returns the errors of all tracers that could not be created, keyed by tracer type.
+    ///
+    /// This is always empty for inspectors created with [Self::try_from_config].
+    pub const fn failed_tracers(&self) -> &MuxFailures {
+        &self.failed
     }
 
     /// Try converting this [MuxInspector] into a [MuxFrame].
+    ///
+    /// The frame only contains the results of the tracers that were created successfully, see
+    /// [Self::try_into_mux_frame_with_failures] to also obtain the errors of the failed tracers.
     pub fn try_into_mux_frame<DB: DatabaseRef>(
         self,
         result: &ResultAndState,
         db: &DB,
     ) -> Result<MuxFrame, DB::Error> {
-        let mut frame = HashMap::with_capacity(self.0.len());
-        for (tracer_type, inspector) in self.0 {
+        self.try_into_mux_frame_with_failures(result, db).map(|(frame, _)| frame)
+    }
+
+    /// Try converting this [MuxInspector] into a [MuxFrame] with the results of all tracers that
+    /// were created successfully, alongside the errors of all tracers that could not be created.
+    ///
+    /// Database errors are not specific to a tracer and fail the whole conversion.
+    pub fn try_into_mux_frame_with_failures<DB: DatabaseRef>(
+        self,
+        result: &ResultAndState,
+        db: &DB,
+    ) -> Result<(MuxFrame, MuxFailures), DB::Error> {
+        let mut frame = HashMap::with_capacity(self.inspectors.len());
+        for (tracer_type, inspector) in self.inspectors {
             let trace = match inspector {
                 DelegatingInspector::FourByte(inspector) => FourByteFrame::from(inspector).into(),
                 DelegatingInspector::Call(config, inspector) => inspector
@@ -59,7 +164,7 @@ impl MuxInspector {
             frame.insert(tracer_type, trace);
         }
 
-        Ok(MuxFrame(frame))
+        Ok((MuxFrame(frame), self.failed))
     }
 }
 
@@ -69,28 +174,28 @@ where
 {
     #[inline]
     fn initialize_interp(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
-        for (_, inspector) in &mut self.0 {
+        for (_, inspector) in &mut self.inspectors {
             inspector.initialize_interp(interp, context);
         }
     }
 
     #[inline]
     fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
-        for (_, inspector) in &mut self.0 {
+        for (_, inspector) in &mut self.inspectors {
             inspector.step(interp, context);
         }
     }
 
     #[inline]
     fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
-        for (_, inspector) in &mut self.0 {
+        for (_, inspector) in &mut self.inspectors {
             inspector.step_end(interp, context);
         }
     }
 
     #[inline]
     fn log(&mut self, context: &mut EvmContext<DB>, log: &Log) {
-        for (_, inspector) in &mut self.0 {
+        for (_, inspector) in &mut self.inspectors {
             inspector.log(context, log);
         }
     }
@@ -101,7 +206,8 @@ where
         context: &mut EvmContext<DB>,
         inputs: &mut CallInputs,
     ) -> Option<CallOutcome> {
-        for (_, inspector) in &mut self.0 {
+        self.depth += 1;
+        for (_, inspector) in &mut self.inspectors {
             if let Some(outcome) = inspector.call(context, inputs) {
                 return Some(outcome);
             }
@@ -117,8 +223,9 @@ where
         inputs: &CallInputs,
         outcome: CallOutcome,
     ) -> CallOutcome {
+        self.depth = self.depth.saturating_sub(1);
         let mut outcome = outcome;
-        for (_, inspector) in &mut self.0 {
+        for (_, inspector) in &mut self.inspectors {
             outcome = inspector.call_end(context, inputs, outcome);
         }
 
@@ -131,7 +238,8 @@ where
         context: &mut EvmContext<DB>,
         inputs: &mut CreateInputs,
     ) -> Option<CreateOutcome> {
-        for (_, inspector) in &mut self.0 {
+        self.depth += 1;
+        for (_, inspector) in &mut self.inspectors {
             if let Some(outcome) = inspector.create(context, inputs) {
                 return Some(outcome);
             }
@@ -147,8 +255,9 @@ where
         inputs: &CreateInputs,
         outcome: CreateOutcome,
     ) -> CreateOutcome {
+        self.depth = self.depth.saturating_sub(1);
         let mut outcome = outcome;
-        for (_, inspector) in &mut self.0 {
+        for (_, inspector) in &mut self.inspectors {
             outcome = inspector.create_end(context, inputs, outcome);
         }
 
@@ -157,7 +266,7 @@ where
 
     #[inline]
     fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
-        for (_, inspector) in &mut self.0 {
+        for (_, inspector) in &mut self.inspectors {
             inspector.selfdestruct::<DB>(contract, target, value);
         }
     }
@@ -226,6 +335,17 @@ impl DelegatingInspector {
         inspector.map(|inspector| (tracer_type, inspector))
     }
 
+    /// Resets the state of the inspector, keeping its config.
+    fn fuse(&mut self) {
+        match self {
+            DelegatingInspector::FourByte(inspector) => *inspector = FourByteInspector::default(),
+            DelegatingInspector::Call(_, inspector) => inspector.fuse(),
+            DelegatingInspector::Prestate(_, inspector) => inspector.fuse(),
+            DelegatingInspector::Noop => {}
+            DelegatingInspector::Mux(inspector) => inspector.fuse(),
+        }
+    }
+
     #[inline]
     fn initialize_interp<DB: Database>(
         &mut self,
@@ -389,6 +509,9 @@ pub enum Error {
     /// Expected config is missing
     #[error("expected config is missing for tracer '{0:?}'")]
     MissingConfig(GethDebugBuiltInTracerType),
+    /// A tracer was added while a transaction is being inspected
+    #[error("can't add tracer '{0:?}' during inspection")]
+    InspectionInProgress(GethDebugBuiltInTracerType),
     /// Error when deserializing the config
     #[error("error deserializing config: {0}")]
     InvalidConfig(#[from] serde_json::Error),
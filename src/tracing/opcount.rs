@@ -2,28 +2,58 @@
 //!
 //! See also <https://geth.ethereum.org/docs/developers/evm-tracing/built-in-tracers>
 
-use revm::{interpreter::Interpreter, Database, EvmContext, Inspector};
+use revm::{
+    interpreter::{Interpreter, OpCode},
+    Database, EvmContext, Inspector,
+};
+use std::collections::{BTreeMap, HashMap};
 
 /// An inspector that counts all opcodes.
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct OpcodeCountInspector {
     /// opcode counter
     count: usize,
+    /// opcode counters per call depth, if enabled
+    counts_by_depth: Option<BTreeMap<u64, HashMap<OpCode, u64>>>,
 }
 
 impl OpcodeCountInspector {
+    /// Returns a new inspector that also counts every opcode per call depth.
+    ///
+    /// See [Self::counts_by_depth].
+    pub fn with_depth_buckets() -> Self {
+        Self { count: 0, counts_by_depth: Some(BTreeMap::new()) }
+    }
+
     /// Returns the opcode counter
     #[inline]
     pub const fn count(&self) -> usize {
         self.count
     }
+
+    /// Returns the number of executions of each opcode, keyed by the call depth they were executed
+    /// at.
+    ///
+    /// This is only recorded if the inspector was created with [Self::with_depth_buckets].
+    #[inline]
+    pub const fn counts_by_depth(&self) -> Option<&BTreeMap<u64, HashMap<OpCode, u64>>> {
+        self.counts_by_depth.as_ref()
+    }
 }
 
 impl<DB> Inspector<DB> for OpcodeCountInspector
 where
     DB: Database,
 {
-    fn step(&mut self, _interp: &mut Interpreter, _context: &mut EvmContext<DB>) {
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
         self.count += 1;
+
+        if let Some(counts_by_depth) = &mut self.counts_by_depth {
+            // We always want an OpCode, even it is unknown because it could be an additional
+            // opcode that not a known constant.
+            let op = unsafe { OpCode::new_unchecked(interp.current_opcode()) };
+            let depth = context.journaled_state.depth();
+            *counts_by_depth.entry(depth).or_default().entry(op).or_default() += 1;
+        }
     }
 }
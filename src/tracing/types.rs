@@ -2,7 +2,7 @@
 
 use crate::tracing::{config::TraceStyle, utils, utils::convert_memory};
 pub use alloy_primitives::Log;
-use alloy_primitives::{Address, Bytes, LogData, U256, U64};
+use alloy_primitives::{Address, Bytes, LogData, B256, U256, U64};
 use alloy_rpc_types::trace::{
     geth::{CallFrame, CallLogFrame, GethDefaultTracingOptions, StructLog},
     parity::{
@@ -10,12 +10,15 @@ use alloy_rpc_types::trace::{
         SelfdestructAction, TraceOutput, TransactionTrace,
     },
 };
-use revm::interpreter::{opcode, CallScheme, CreateScheme, InstructionResult, OpCode};
-use std::collections::VecDeque;
+use revm::interpreter::{
+    gas::CALL_STIPEND, opcode, CallScheme, CreateScheme, InstructionResult, OpCode,
+};
+use std::{collections::VecDeque, time::Duration};
 
 /// Decoded call data.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct DecodedCallData {
     /// The function signature.
     pub signature: String,
@@ -26,18 +29,25 @@ pub struct DecodedCallData {
 /// Additional decoded data enhancing the [CallTrace].
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct DecodedCallTrace {
     /// Optional decoded label for the call.
     pub label: Option<String>,
     /// Optional decoded return data.
+    #[cfg_attr(feature = "serde", serde(alias = "return_data"))]
     pub return_data: Option<String>,
     /// Optional decoded call data.
+    #[cfg_attr(feature = "serde", serde(alias = "call_data"))]
     pub call_data: Option<DecodedCallData>,
 }
 
 /// A trace of a call with optional decoded data.
+///
+/// Like the other trace types, this is serialized with camelCase field names, but the snake_case
+/// names of earlier versions are accepted when deserializing.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct CallTrace {
     /// The depth of the call.
     pub depth: usize,
@@ -45,6 +55,10 @@ pub struct CallTrace {
     pub success: bool,
     /// The caller address.
     pub caller: Address,
+    /// The origin of the transaction, `tx.origin`.
+    ///
+    /// This is the same for all calls of a transaction.
+    pub origin: Address,
     /// The target address of this call.
     ///
     /// This is:
@@ -55,6 +69,7 @@ pub struct CallTrace {
     /// Whether this is a call to a precompile.
     ///
     /// Note: This is optional because not all tracers make use of this.
+    #[cfg_attr(feature = "serde", serde(alias = "maybe_precompile"))]
     pub maybe_precompile: Option<bool>,
     /// Holds the target for the selfdestruct refund target.
     ///
@@ -62,6 +77,7 @@ pub struct CallTrace {
     /// Cancun hardfork.
     ///
     /// See [`is_selfdestruct`](Self::is_selfdestruct) for more information.
+    #[cfg_attr(feature = "serde", serde(alias = "selfdestruct_refund_target"))]
     pub selfdestruct_refund_target: Option<Address>,
     /// The value transferred on a selfdestruct.
     ///
@@ -69,21 +85,122 @@ pub struct CallTrace {
     /// Cancun hardfork.
     ///
     /// See [`is_selfdestruct`](Self::is_selfdestruct) for more information.
+    #[cfg_attr(feature = "serde", serde(alias = "selfdestruct_transferred_value"))]
     pub selfdestruct_transferred_value: Option<U256>,
+    /// The gas refunded for a selfdestruct.
+    ///
+    /// This is only non-zero if a selfdestruct was executed before the London hardfork, which
+    /// removed the selfdestruct refund (EIP-3529), and the contract was not already selfdestructed
+    /// in the same transaction.
+    ///
+    /// This is only recorded if [TracingInspectorConfig::record_selfdestruct_refunds] is enabled.
+    ///
+    /// [TracingInspectorConfig::record_selfdestruct_refunds]: crate::tracing::TracingInspectorConfig::record_selfdestruct_refunds
+    #[cfg_attr(feature = "serde", serde(alias = "selfdestruct_gas_refund"))]
+    pub selfdestruct_gas_refund: u64,
     /// The kind of call.
     pub kind: CallKind,
+    /// The salt of a [CallKind::Create2], `None` for all other kinds.
+    ///
+    /// Together with the caller and the init code this determines the created address.
+    #[cfg_attr(feature = "serde", serde(alias = "create2_salt"))]
+    pub create2_salt: Option<U256>,
     /// The value transferred in the call.
     pub value: U256,
     /// The calldata/input, or the init code for contract creations.
     pub data: Bytes,
     /// The return data, or the runtime bytecode of the created contract.
     pub output: Bytes,
+    /// The hash of the runtime bytecode of the created contract, i.e. the hash of the
+    /// [`output`](Self::output) of a successful contract creation.
+    ///
+    /// This is `None` for calls and failed contract creations.
+    #[cfg_attr(feature = "serde", serde(alias = "init_code_hash"))]
+    pub init_code_hash: Option<B256>,
     /// The total gas cost of the call.
+    #[cfg_attr(feature = "serde", serde(alias = "gas_used"))]
     pub gas_used: u64,
     /// The gas limit of the call.
+    ///
+    /// For the root call this is the gas limit of the transaction. For calls that transfer value
+    /// this includes the call stipend, i.e. this is the gas the callee starts with.
+    #[cfg_attr(feature = "serde", serde(alias = "gas_limit"))]
     pub gas_limit: u64,
+    /// The raw gas limit of the call's interpreter at frame entry.
+    ///
+    /// Unlike [`gas_limit`](Self::gas_limit), this excludes the intrinsic gas of the transaction
+    /// for the root call.
+    ///
+    /// For calls that transfer value this includes the call stipend, see
+    /// [`forwarded_gas_limit`](Self::forwarded_gas_limit) for the gas forwarded by the caller.
+    #[cfg_attr(feature = "serde", serde(alias = "interpreter_gas_limit"))]
+    pub interpreter_gas_limit: u64,
+    /// The gas the caller had left when it executed the call instruction, before the costs of the
+    /// call itself were charged.
+    ///
+    /// The gas forwarded to the call is capped at 63/64 of the gas left after these costs, see
+    /// EIP-150, so comparing this with [`forwarded_gas_limit`](Self::forwarded_gas_limit) shows
+    /// how much gas the caller kept.
+    ///
+    /// This is only recorded if [TracingInspectorConfig::record_caller_gas] is enabled, and is
+    /// always `None` for the root call.
+    ///
+    /// [TracingInspectorConfig::record_caller_gas]: crate::tracing::TracingInspectorConfig::record_caller_gas
+    #[cfg_attr(feature = "serde", serde(alias = "caller_gas_remaining"))]
+    pub caller_gas_remaining: Option<u64>,
+    /// The address of the [EIP-7702](https://eips.ethereum.org/EIPS/eip-7702) delegation
+    /// designator found in the code of the called account, if any.
+    ///
+    /// This only reports the designator, the pinned revm version does not execute delegated code,
+    /// so the called account's own code is what is executed and traced. Like the EVM, delegations
+    /// are not followed, so this is the designator's address even if that account delegates as
+    /// well. For `DELEGATECALL` and `CALLCODE` this is read from the code address.
+    ///
+    /// This is only recorded if
+    /// [TracingInspectorConfig::record_delegations](crate::tracing::TracingInspectorConfig::record_delegations)
+    /// is enabled and the spec is Prague or later. It is always `None` for creates. `AUTHCALL`s
+    /// are not executed by revm, so there is no interplay with EIP-3074 authorizations.
+    #[cfg_attr(feature = "serde", serde(alias = "delegated_to"))]
+    pub delegated_to: Option<Address>,
     /// The final status of the call.
     pub status: InstructionResult,
+    /// Whether this call was made from EOF code, i.e. by `EXTCALL`, `EXTDELEGATECALL` or
+    /// `EXTSTATICCALL`.
+    ///
+    /// See [Self::ext_call_status] for the status the caller observes.
+    #[cfg_attr(feature = "serde", serde(alias = "is_eof"))]
+    pub is_eof: bool,
+    /// The number of opcodes executed by the call itself, excluding its subcalls.
+    ///
+    /// This is only counted if [TracingInspectorConfig::record_op_counts] is enabled.
+    ///
+    /// [TracingInspectorConfig::record_op_counts]: crate::tracing::TracingInspectorConfig::record_op_counts
+    #[cfg_attr(feature = "serde", serde(alias = "op_count"))]
+    pub op_count: u64,
+    /// The gas spent on memory expansion by the call itself, excluding its subcalls.
+    ///
+    /// This is only recorded if steps are recorded.
+    #[cfg_attr(feature = "serde", serde(alias = "memory_gas"))]
+    pub memory_gas: u64,
+    /// The peak memory size of the call in bytes.
+    ///
+    /// This is only recorded if [TracingInspectorConfig::record_max_memory] is enabled, which
+    /// does not require steps or memory snapshots to be recorded.
+    ///
+    /// [TracingInspectorConfig::record_max_memory]: crate::tracing::TracingInspectorConfig::record_max_memory
+    #[cfg_attr(feature = "serde", serde(alias = "max_memory"))]
+    pub max_memory: u64,
+    /// The number of logs emitted by the call itself, excluding its subcalls.
+    ///
+    /// This is counted regardless of whether logs are recorded.
+    #[cfg_attr(feature = "serde", serde(alias = "log_count"))]
+    pub log_count: u64,
+    /// The total size of the data of the logs emitted by the call itself in bytes, excluding
+    /// topics and subcalls.
+    ///
+    /// This is counted regardless of whether logs are recorded.
+    #[cfg_attr(feature = "serde", serde(alias = "log_data_size"))]
+    pub log_data_size: u64,
     /// Opcode-level execution steps.
     pub steps: Vec<CallTraceStep>,
     /// Optional complementary decoded call data.
@@ -91,6 +208,38 @@ pub struct CallTrace {
 }
 
 impl CallTrace {
+    /// Returns the gas forwarded by the caller.
+    ///
+    /// This is the [`interpreter_gas_limit`](Self::interpreter_gas_limit) without the call stipend
+    /// that is added to calls that transfer value, see EIP-150.
+    #[inline]
+    pub fn forwarded_gas_limit(&self) -> u64 {
+        if self.has_call_stipend() {
+            self.interpreter_gas_limit.saturating_sub(CALL_STIPEND)
+        } else {
+            self.interpreter_gas_limit
+        }
+    }
+
+    /// Returns true if the call received the call stipend in addition to the forwarded gas.
+    ///
+    /// This is the case for `CALL` and `CALLCODE` subcalls that transfer value, e.g. a solidity
+    /// `transfer` that forwards no gas and only gets the stipend.
+    #[inline]
+    pub fn has_call_stipend(&self) -> bool {
+        self.depth > 0
+            && matches!(self.kind, CallKind::Call | CallKind::CallCode)
+            && !self.value.is_zero()
+    }
+
+    /// Returns the approximate wall time spent executing the call itself, excluding its subcalls.
+    ///
+    /// This is the sum of the [durations](CallTraceStep::duration) of the recorded steps, or
+    /// `None` if no step durations were recorded.
+    pub fn steps_duration(&self) -> Option<Duration> {
+        self.steps.iter().filter_map(|step| step.duration).reduce(|acc, duration| acc + duration)
+    }
+
     /// Returns true if the status code is an error or revert, See [InstructionResult::Revert]
     #[inline]
     pub const fn is_error(&self) -> bool {
@@ -103,6 +252,50 @@ impl CallTrace {
         self.status == InstructionResult::Revert
     }
 
+    /// Returns true if this is an EOF external call that failed without executing the callee,
+    /// because the call depth limit was reached or the caller's balance was insufficient.
+    ///
+    /// These "light failures" consume no gas and are reported to the caller like a revert.
+    #[inline]
+    pub const fn is_light_failure(&self) -> bool {
+        self.is_eof
+            && matches!(self.status, InstructionResult::CallTooDeep | InstructionResult::OutOfFunds)
+    }
+
+    /// Returns the status code an EOF external call pushes onto the caller's stack, or `None` if
+    /// this is not an EOF external call.
+    ///
+    /// The status code is `0` for success, `1` for reverts and light failures, see
+    /// [Self::is_light_failure], and `2` for all other failures.
+    #[inline]
+    pub const fn ext_call_status(&self) -> Option<u8> {
+        if !self.is_eof {
+            return None;
+        }
+        Some(if self.status.is_ok() {
+            0
+        } else if self.status.is_revert() {
+            1
+        } else {
+            2
+        })
+    }
+
+    /// Returns true if the call itself ran out of gas.
+    ///
+    /// This is not the case for calls that only failed because a subcall ran out of gas.
+    #[inline]
+    pub const fn is_out_of_gas(&self) -> bool {
+        matches!(
+            self.status,
+            InstructionResult::OutOfGas
+                | InstructionResult::MemoryOOG
+                | InstructionResult::MemoryLimitOOG
+                | InstructionResult::PrecompileOOG
+                | InstructionResult::InvalidOperandOOG
+        )
+    }
+
     /// Returns `true` if this trace was a selfdestruct.
     ///
     /// See also `TracingInspector::selfdestruct`.
@@ -148,6 +341,7 @@ impl CallTrace {
 /// Additional decoded data enhancing the [CallLog].
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct DecodedCallLog {
     /// The decoded event name.
     pub name: Option<String>,
@@ -159,8 +353,10 @@ pub struct DecodedCallLog {
 /// A log with optional decoded data.
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct CallLog {
     /// The raw log data.
+    #[cfg_attr(feature = "serde", serde(alias = "raw_log"))]
     pub raw_log: LogData,
     /// Optional complementary decoded log data.
     pub decoded: DecodedCallLog,
@@ -176,6 +372,7 @@ impl From<Log> for CallLog {
 /// A node in the arena
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct CallTraceNode {
     /// Parent node index in the arena
     pub parent: Option<usize>,
@@ -247,6 +444,26 @@ impl CallTraceNode {
         self.trace.kind
     }
 
+    /// Returns the length of the call's input data
+    #[inline]
+    pub fn input_len(&self) -> usize {
+        self.trace.data.len()
+    }
+
+    /// Returns the length of the call's output data
+    #[inline]
+    pub fn output_len(&self) -> usize {
+        self.trace.output.len()
+    }
+
+    /// Returns the salt of the call if it's a `CREATE2`
+    ///
+    /// Parity's [CreateAction] has no field for the salt, so it's only available from the trace.
+    #[inline]
+    pub const fn create2_salt(&self) -> Option<U256> {
+        self.trace.create2_salt
+    }
+
     /// Returns the status of the call
     #[inline]
     pub const fn status(&self) -> InstructionResult {
@@ -407,7 +624,7 @@ impl CallTraceNode {
 }
 
 /// A unified representation of a call.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "UPPERCASE"))]
 pub enum CallKind {
@@ -544,6 +761,7 @@ pub enum TraceMemberOrder {
 /// Represents a tracked call step during execution
 #[derive(Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct CallTraceStep {
     // Fields filled in `step`
     /// Call depth
@@ -556,8 +774,12 @@ pub struct CallTraceStep {
     /// Current contract address
     pub contract: Address,
     /// Stack before step execution
+    ///
+    /// With [StackSnapshotType::Top](crate::tracing::StackSnapshotType::Top) this only contains
+    /// the top items of the stack, the last item is always the top of the stack.
     pub stack: Option<Vec<U256>>,
     /// The new stack items placed by this step if any
+    #[cfg_attr(feature = "serde", serde(alias = "push_stack"))]
     pub push_stack: Option<Vec<U256>>,
     /// Memory before step execution.
     ///
@@ -565,21 +787,50 @@ pub struct CallTraceStep {
     pub memory: Option<RecordedMemory>,
     /// Returndata before step execution
     pub returndata: Bytes,
+    /// The calldata region read by this step, if this is a `CALLDATALOAD` or `CALLDATACOPY`.
+    ///
+    /// This will be `None` if calldata read recording is disabled.
+    #[cfg_attr(feature = "serde", serde(alias = "calldata_read"))]
+    pub calldata_read: Option<CalldataRead>,
     /// Remaining gas before step execution
+    #[cfg_attr(feature = "serde", serde(alias = "gas_remaining"))]
     pub gas_remaining: u64,
     /// Gas refund counter before step execution
+    #[cfg_attr(feature = "serde", serde(alias = "gas_refund_counter"))]
     pub gas_refund_counter: u64,
     /// Total gas used before step execution
+    #[cfg_attr(feature = "serde", serde(alias = "gas_used"))]
     pub gas_used: u64,
     // Fields filled in `step_end`
     /// Gas cost of step execution
+    #[cfg_attr(feature = "serde", serde(alias = "gas_cost"))]
     pub gas_cost: u64,
+    /// Change of the gas refund counter by the step execution, e.g. a refund for clearing a
+    /// storage slot with `SSTORE`.
+    ///
+    /// This is negative if a refund was reverted. This is only recorded if
+    /// [TracingInspectorConfig::record_gas_refunds](crate::tracing::TracingInspectorConfig::record_gas_refunds)
+    /// or state diff recording is enabled, otherwise it's zero.
+    #[cfg_attr(feature = "serde", serde(alias = "refund_delta"))]
+    pub refund_delta: i64,
     /// Change of the contract state after step execution (effect of the SLOAD/SSTORE instructions)
+    #[cfg_attr(feature = "serde", serde(alias = "storage_change"))]
     pub storage_change: Option<StorageChange>,
     /// Final status of the step
     ///
     /// This is set after the step was executed.
     pub status: InstructionResult,
+    /// Whether this step caused the call to fail, e.g. a `REVERT` or an erroneous step.
+    ///
+    /// This is only set on the last step of a failed call, after the call ended.
+    #[cfg_attr(feature = "serde", serde(alias = "reverts_call"))]
+    pub reverts_call: bool,
+    /// Wall time spent executing the step.
+    ///
+    /// This is only measured if
+    /// [TracingInspectorConfig::record_step_durations](crate::tracing::TracingInspectorConfig::record_step_durations)
+    /// is enabled. The duration of a step that enters a subcall excludes the subcall's execution.
+    pub duration: Option<Duration>,
 }
 
 // === impl CallTraceStep ===
@@ -594,7 +845,7 @@ impl CallTraceStep {
             error: self.as_error(),
             gas: self.gas_remaining,
             gas_cost: self.gas_cost,
-            op: self.op.to_string(),
+            op: utils::geth_opcode_name(self.op),
             pc: self.pc as u64,
             refund_counter: (self.gas_refund_counter > 0).then_some(self.gas_refund_counter),
             // Filled, if not disabled manually
@@ -605,11 +856,13 @@ impl CallTraceStep {
             storage: None,
             // Only enabled if `opts.enable_memory` is true
             memory: None,
-            // This is None in the rpc response
+            // This is None in the rpc response, unless enabled in the `GethTraceBuilder`
             memory_size: None,
         };
 
         if opts.is_stack_enabled() {
+            // this is only the top of the stack if the stack was recorded with
+            // `StackSnapshotType::Top`
             log.stack.clone_from(&self.stack);
         }
 
@@ -647,6 +900,33 @@ impl CallTraceStep {
         self.status as u8 >= InstructionResult::Revert as u8
     }
 
+    /// Returns true if the step attempted a state change in a static context and failed because
+    /// of it.
+    ///
+    /// This is the case for `SSTORE`, `TSTORE`, `LOG*`, `CREATE`, `CREATE2` and `SELFDESTRUCT`
+    /// steps, and `CALL` steps that transfer value, executed in a `STATICCALL` frame or any of its
+    /// subcalls.
+    #[inline]
+    pub const fn is_static_violation(&self) -> bool {
+        matches!(
+            self.status,
+            InstructionResult::StateChangeDuringStaticCall
+                | InstructionResult::CallNotAllowedInsideStatic
+        )
+    }
+
+    /// Returns true if the step is an `SLOAD` of a cold storage slot.
+    ///
+    /// Slots in the transaction's access list are warm from the start of the transaction, like
+    /// slots that were accessed before. This requires
+    /// [TracingInspectorConfig::record_state_diff](crate::tracing::TracingInspectorConfig::record_state_diff),
+    /// since only cold loads record a [StorageChange] for `SLOAD` steps.
+    #[inline]
+    pub fn is_cold_sload(&self) -> bool {
+        self.op.get() == opcode::SLOAD
+            && self.storage_change.is_some_and(|change| change.reason == StorageChangeReason::SLOAD)
+    }
+
     /// Returns the error message if it is an erroneous result.
     #[inline]
     pub(crate) fn as_error(&self) -> Option<String> {
@@ -654,8 +934,19 @@ impl CallTraceStep {
     }
 }
 
+/// A region of the calldata read by a `CALLDATALOAD` or `CALLDATACOPY` step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct CalldataRead {
+    /// Offset into the calldata
+    pub offset: U256,
+    /// Number of bytes read, this is always 32 for `CALLDATALOAD`
+    pub len: U256,
+}
+
 /// Represents the source of a storage change - e.g., whether it came
-/// from an SSTORE or SLOAD instruction.
+/// from an SSTORE, SLOAD, TSTORE or TLOAD instruction.
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -664,6 +955,18 @@ pub enum StorageChangeReason {
     SLOAD,
     /// SSTORE opcode
     SSTORE,
+    /// TLOAD opcode
+    TLOAD,
+    /// TSTORE opcode
+    TSTORE,
+}
+
+impl StorageChangeReason {
+    /// Returns true if this is an access of the transient storage, see EIP-1153.
+    #[inline]
+    pub const fn is_transient(self) -> bool {
+        matches!(self, Self::TLOAD | Self::TSTORE)
+    }
 }
 
 /// Represents a storage change during execution.
@@ -675,12 +978,14 @@ pub enum StorageChangeReason {
 /// to EIP-2929 AccessList had_value will be None.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
 pub struct StorageChange {
     /// key of the storage slot
     pub key: U256,
     /// Current value of the storage slot
     pub value: U256,
     /// The previous value of the storage slot, if any
+    #[cfg_attr(feature = "serde", serde(alias = "had_value"))]
     pub had_value: Option<U256>,
     /// How this storage was accessed
     pub reason: StorageChangeReason,
@@ -1,11 +1,14 @@
 //! Util functions for revm related ops
 
-use alloy_primitives::{hex, Bytes};
+use crate::tracing::types::CallTraceNode;
+use alloy_primitives::{hex, keccak256, Address, Bytes};
 use alloy_sol_types::{ContractError, GenericRevertReason};
 use revm::{
+    interpreter::OpCode,
     primitives::{SpecId, KECCAK_EMPTY},
     DatabaseRef,
 };
+use std::collections::HashMap;
 
 /// Formats memory data into a list of 32-byte hex-encoded chunks.
 ///
@@ -25,6 +28,62 @@ pub(crate) fn convert_memory(data: &[u8]) -> Vec<String> {
     memory
 }
 
+/// Returns the name of the opcode as rendered by geth.
+///
+/// Geth renders undefined opcodes as `opcode 0x..`, whereas [OpCode]'s `Display` impl renders
+/// them as `UNKNOWN(0x..)`.
+///
+/// See: <https://github.com/ethereum/go-ethereum/blob/366d2169fbc0e0f803b68c042b77b6b480836dbc/core/vm/opcodes.go#L412-L418>
+pub(crate) fn geth_opcode_name(op: OpCode) -> String {
+    match OpCode::info_by_op(op.get()) {
+        Some(info) => info.name().to_string(),
+        None => format!("opcode {:#x}", op.get()),
+    }
+}
+
+/// The prefix of an [EIP-7702](https://eips.ethereum.org/EIPS/eip-7702) delegation designator.
+const DELEGATION_DESIGNATOR_PREFIX: [u8; 3] = [0xef, 0x01, 0x00];
+
+/// Returns the address the code delegates to if it's an
+/// [EIP-7702](https://eips.ethereum.org/EIPS/eip-7702) delegation designator, `0xef0100 ||
+/// address`.
+pub(crate) fn delegation_designator_target(code: &[u8]) -> Option<Address> {
+    match code.strip_prefix(&DELEGATION_DESIGNATOR_PREFIX) {
+        Some(address) if address.len() == Address::len_bytes() => {
+            Some(Address::from_slice(address))
+        }
+        _ => None,
+    }
+}
+
+/// Computes the address of a contract created with `CREATE` by `sender` at `nonce`,
+/// `keccak256(rlp([sender, nonce]))[12:]`.
+///
+/// This encodes the RLP list by hand so it can be used to verify the address computed by the
+/// interpreter.
+pub(crate) fn rlp_create_address(sender: Address, nonce: u64) -> Address {
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+
+    // the address is a 20 byte string, the nonce an integer without leading zeros
+    let mut payload = Vec::with_capacity(30);
+    payload.push(0x80 + 20);
+    payload.extend_from_slice(sender.as_slice());
+    match nonce_bytes {
+        [byte] if *byte < 0x80 => payload.push(*byte),
+        bytes => {
+            payload.push(0x80 + bytes.len() as u8);
+            payload.extend_from_slice(bytes);
+        }
+    }
+
+    // the payload is always shorter than 56 bytes
+    let mut list = Vec::with_capacity(payload.len() + 1);
+    list.push(0xc0 + payload.len() as u8);
+    list.extend_from_slice(&payload);
+    Address::from_slice(&keccak256(list)[12..])
+}
+
 /// Get the gas used, accounting for refunds
 #[inline]
 pub(crate) fn gas_used(spec: SpecId, spent: u64, refunded: u64) -> u64 {
@@ -54,6 +113,18 @@ pub(crate) fn load_account_code<DB: DatabaseRef>(
         .map(Into::into)
 }
 
+/// Sets the decoded label of every node whose address has a label in the given map.
+///
+/// Labels that were previously set are overwritten, nodes without a label in the map are left
+/// unchanged.
+pub(crate) fn apply_labels(nodes: &mut [CallTraceNode], labels: &HashMap<Address, String>) {
+    for node in nodes {
+        if let Some(label) = labels.get(&node.trace.address) {
+            node.trace.decoded.label = Some(label.clone());
+        }
+    }
+}
+
 /// Returns a non empty revert reason if the output is a revert/error.
 #[inline]
 pub(crate) fn maybe_revert_reason(output: &[u8]) -> Option<String> {
@@ -77,8 +148,39 @@ pub(crate) fn maybe_revert_reason(output: &[u8]) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy_primitives::address;
     use alloy_sol_types::{GenericContractError, SolInterface};
 
+    #[test]
+    fn geth_unknown_opcode_name() {
+        assert_eq!(geth_opcode_name(OpCode::ADD), "ADD");
+        // SAFETY: only used for formatting
+        let unknown = unsafe { OpCode::new_unchecked(0x0c) };
+        assert_eq!(geth_opcode_name(unknown), "opcode 0xc");
+        let unknown = unsafe { OpCode::new_unchecked(0xef) };
+        assert_eq!(geth_opcode_name(unknown), "opcode 0xef");
+    }
+
+    #[test]
+    fn rlp_create_addresses() {
+        let sender = address!("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        assert_eq!(
+            rlp_create_address(sender, 0),
+            address!("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d")
+        );
+        assert_eq!(
+            rlp_create_address(sender, 1),
+            address!("343c43a37d37dff08ae8c4a11544c718abb4fcf8")
+        );
+        assert_eq!(
+            rlp_create_address(sender, 2),
+            address!("f778b86fa74e846c4f0a1fbd1335fe81c00a0c91")
+        );
+        for nonce in [0x7f, 0x80, 0xff, 0x100, u32::MAX as u64, u64::MAX] {
+            assert_eq!(rlp_create_address(sender, nonce), sender.create(nonce));
+        }
+    }
+
     #[test]
     fn decode_revert_reason() {
         let err = GenericContractError::Revert("my revert".into());
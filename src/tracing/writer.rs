@@ -1,10 +1,18 @@
 use super::{
-    types::{CallKind, CallLog, CallTrace, CallTraceNode, DecodedCallData, TraceMemberOrder},
-    CallTraceArena,
+    types::{
+        CallKind, CallLog, CallTrace, CallTraceNode, CallTraceStep, DecodedCallData,
+        TraceMemberOrder,
+    },
+    CallTraceArena, TracingInspector,
 };
-use alloy_primitives::{address, hex, Address};
+use alloy_primitives::{address, hex, Address, Log, U256};
 use anstyle::{AnsiColor, Color, Style};
 use colorchoice::ColorChoice;
+use revm::{
+    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Interpreter},
+    Database, EvmContext, Inspector,
+};
+use serde_json::{json, Value};
 use std::io::{self, Write};
 
 const CHEATCODE_ADDRESS: Address = address!("7109709ECfa91a80626fF3989D68f67F5b1DD12D");
@@ -15,8 +23,12 @@ const BRANCH: &str = "  ├─ ";
 const CALL: &str = "→ ";
 const RETURN: &str = "← ";
 
+/// The number of stack items written for each step, starting at the top of the stack.
+const STEP_STACK_ITEMS: usize = 3;
+
 const TRACE_KIND_STYLE: Style = AnsiColor::Yellow.on_default();
 const LOG_STYLE: Style = AnsiColor::Cyan.on_default();
+const TRUNCATION_STYLE: Style = Style::new().dimmed();
 
 /// Formats [call traces](CallTraceArena) to an [`Write`] writer.
 ///
@@ -26,6 +38,8 @@ pub struct TraceWriter<W> {
     writer: W,
     use_colors: bool,
     color_cheatcodes: bool,
+    write_steps: bool,
+    max_bytes_len: Option<usize>,
     indentation_level: u16,
 }
 
@@ -37,6 +51,8 @@ impl<W: Write> TraceWriter<W> {
             writer,
             use_colors: use_colors(ColorChoice::global()),
             color_cheatcodes: false,
+            write_steps: false,
+            max_bytes_len: None,
             indentation_level: 0,
         }
     }
@@ -55,6 +71,27 @@ impl<W: Write> TraceWriter<W> {
         self
     }
 
+    /// Sets whether to write the recorded steps of each call, interleaved with its logs and
+    /// subcalls.
+    ///
+    /// Each step is written as `pc  OP  gas_cost  [stack top]`, the stack is omitted if it was not
+    /// recorded.
+    #[inline]
+    pub fn write_steps(mut self, yes: bool) -> Self {
+        self.write_steps = yes;
+        self
+    }
+
+    /// Sets the maximum number of bytes of call inputs and outputs to write.
+    ///
+    /// Longer inputs and outputs are truncated with an ellipsis and their total size, e.g.
+    /// `0xdeadbeef…(1.2KiB)`.
+    #[inline]
+    pub fn max_bytes_len(mut self, max_bytes_len: Option<usize>) -> Self {
+        self.max_bytes_len = max_bytes_len;
+        self
+    }
+
     /// Sets the starting indentation level.
     #[inline]
     pub fn with_indentation_level(mut self, level: u16) -> Self {
@@ -86,6 +123,18 @@ impl<W: Write> TraceWriter<W> {
         self.writer.flush()
     }
 
+    /// Writes a call trace arena to the writer as JSON.
+    ///
+    /// Each call is written as an object with its `kind`, `from`, `to`, `value`, `gas_used`,
+    /// `status`, `input` and `output`, the decoded `label`, `function`, `args` and `return_data`
+    /// if any, its `logs`, and its subcalls as `calls`.
+    ///
+    /// Colors and indentation are not applied to the JSON output.
+    pub fn write_arena_json(&mut self, arena: &CallTraceArena) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, &json_node(arena.nodes(), 0))?;
+        self.writer.flush()
+    }
+
     /// Writes a single node and its children to the writer.
     fn write_node(&mut self, nodes: &[CallTraceNode], idx: usize) -> io::Result<()> {
         let node = &nodes[idx];
@@ -101,6 +150,9 @@ impl<W: Write> TraceWriter<W> {
             match *child {
                 TraceMemberOrder::Log(index) => self.write_log(&node.logs[index]),
                 TraceMemberOrder::Call(index) => self.write_node(nodes, node.children[index]),
+                TraceMemberOrder::Step(index) if self.write_steps => {
+                    self.write_step(&node.trace.steps[index])
+                }
                 TraceMemberOrder::Step(_) => Ok(()),
             }?;
         }
@@ -115,6 +167,22 @@ impl<W: Write> TraceWriter<W> {
         Ok(())
     }
 
+    /// Writes the header and footer of a single call trace on one line, indented by its depth.
+    ///
+    /// Logs and subcalls are not written.
+    fn write_frame(&mut self, trace: &CallTrace) -> io::Result<()> {
+        let indentation_level = self.indentation_level;
+        self.indentation_level = indentation_level.saturating_add(trace.depth as u16);
+        self.write_branch()?;
+        self.indentation_level = indentation_level;
+
+        self.write_trace_header(trace)?;
+        self.writer.write_all(b" ")?;
+        self.write_trace_footer(trace)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
     /// Writes the header of a call trace.
     fn write_trace_header(&mut self, trace: &CallTrace) -> io::Result<()> {
         write!(self.writer, "[{}] ", trace.gas_used)?;
@@ -129,20 +197,7 @@ impl<W: Write> TraceWriter<W> {
                 label = trace.decoded.label.as_deref().unwrap_or("<unknown>")
             )?;
         } else {
-            let (func_name, inputs) = match &trace.decoded.call_data {
-                Some(DecodedCallData { signature, args }) => {
-                    let name = signature.split('(').next().unwrap();
-                    (name.to_string(), args.join(", "))
-                }
-                None => {
-                    if trace.data.len() < 4 {
-                        ("fallback".to_string(), hex::encode(&trace.data))
-                    } else {
-                        let (selector, data) = trace.data.split_at(4);
-                        (hex::encode(selector), hex::encode(data))
-                    }
-                }
-            };
+            let (func_name, inputs) = func_name_and_inputs(trace, |data| self.format_bytes(data));
 
             write!(
                 self.writer,
@@ -173,6 +228,24 @@ impl<W: Write> TraceWriter<W> {
         Ok(())
     }
 
+    fn write_step(&mut self, step: &CallTraceStep) -> io::Result<()> {
+        self.write_branch()?;
+        write!(self.writer, "{}  {}  {}", step.pc, step.op, step.gas_cost)?;
+
+        if let Some(stack) = &step.stack {
+            self.writer.write_all(b"  [")?;
+            for (i, value) in stack.iter().rev().take(STEP_STACK_ITEMS).enumerate() {
+                if i > 0 {
+                    self.writer.write_all(b", ")?;
+                }
+                write!(self.writer, "{value:#x}")?;
+            }
+            self.writer.write_all(b"]")?;
+        }
+
+        self.writer.write_all(b"\n")
+    }
+
     fn write_log(&mut self, log: &CallLog) -> io::Result<()> {
         let log_style = self.log_style();
         self.write_branch()?;
@@ -228,12 +301,28 @@ impl<W: Write> TraceWriter<W> {
         if trace.kind.is_any_create() {
             write!(self.writer, "{} bytes of code", trace.output.len())?;
         } else if !trace.output.is_empty() {
-            write!(self.writer, "{}", trace.output)?;
+            let output = self.format_bytes(&trace.output);
+            write!(self.writer, "0x{output}")?;
         }
 
         Ok(())
     }
 
+    /// Returns the hex encoded bytes, truncated to [Self::max_bytes_len] if configured.
+    fn format_bytes(&self, bytes: &[u8]) -> String {
+        match self.max_bytes_len {
+            Some(max) if bytes.len() > max => {
+                let style = self.truncation_style();
+                format!(
+                    "{}{style}…({}){style:#}",
+                    hex::encode(&bytes[..max]),
+                    format_byte_size(bytes.len())
+                )
+            }
+            _ => hex::encode(bytes),
+        }
+    }
+
     fn write_indentation(&mut self) -> io::Result<()> {
         self.writer.write_all(b"  ")?;
         for _ in 1..self.indentation_level {
@@ -283,6 +372,13 @@ impl<W: Write> TraceWriter<W> {
         TRACE_KIND_STYLE
     }
 
+    fn truncation_style(&self) -> Style {
+        if !self.use_colors {
+            return Style::default();
+        }
+        TRUNCATION_STYLE
+    }
+
     fn log_style(&self) -> Style {
         if !self.use_colors {
             return Style::default();
@@ -291,6 +387,279 @@ impl<W: Write> TraceWriter<W> {
     }
 }
 
+/// Formats [call traces](CallTraceArena) as a Markdown nested list to an [`Write`] writer.
+///
+/// Each call frame is rendered as a list item with its kind, address, function, gas used and
+/// status, e.g. for pasting a trace into an issue report.
+#[derive(Clone, Debug)]
+pub struct MarkdownTraceWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> MarkdownTraceWriter<W> {
+    /// Create a new `MarkdownTraceWriter` with the given writer.
+    #[inline]
+    pub const fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Returns a reference to the inner writer.
+    #[inline]
+    pub const fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Returns a mutable reference to the inner writer.
+    #[inline]
+    pub fn writer_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Consumes the `MarkdownTraceWriter` and returns the inner writer.
+    #[inline]
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+
+    /// Writes a call trace arena to the writer.
+    pub fn write_arena(&mut self, arena: &CallTraceArena) -> io::Result<()> {
+        self.write_node(arena.nodes(), 0, 0)?;
+        self.writer.flush()
+    }
+
+    /// Writes a single node and its children to the writer.
+    fn write_node(&mut self, nodes: &[CallTraceNode], idx: usize, depth: usize) -> io::Result<()> {
+        let node = &nodes[idx];
+        let trace = &node.trace;
+
+        write!(self.writer, "{:indent$}- **{}** `", "", trace.kind, indent = depth * 2)?;
+        let address = trace.address.to_checksum_buffer(None);
+        if trace.kind.is_any_create() {
+            write!(
+                self.writer,
+                "new {label}@{address}",
+                label = trace.decoded.label.as_deref().unwrap_or("<unknown>")
+            )?;
+        } else {
+            let (func_name, _) = func_name_and_inputs(trace, |data| hex::encode(data));
+            write!(
+                self.writer,
+                "{addr}::{func_name}",
+                addr = trace.decoded.label.as_deref().unwrap_or(address.as_str()),
+            )?;
+        }
+        writeln!(self.writer, "` gas used: {}, status: `{:?}`", trace.gas_used, trace.status)?;
+
+        for child in &node.children {
+            self.write_node(nodes, *child, depth + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An inspector that records traces with a [TracingInspector] and writes each call frame to a
+/// [TraceWriter] as soon as it completes.
+///
+/// Frames are written in the order they complete, i.e. subcalls before their parent, one line per
+/// frame indented by its depth. Logs are not written.
+///
+/// Errors of the writer can't be returned from the inspector, the first error is kept and can be
+/// retrieved with [StreamingTraceWriter::error]; no further frames are written after an error.
+#[derive(Debug)]
+pub struct StreamingTraceWriter<W> {
+    inspector: TracingInspector,
+    writer: TraceWriter<W>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> StreamingTraceWriter<W> {
+    /// Create a new `StreamingTraceWriter` that writes the frames recorded by the inspector to the
+    /// given writer.
+    #[inline]
+    pub const fn new(inspector: TracingInspector, writer: TraceWriter<W>) -> Self {
+        Self { inspector, writer, error: None }
+    }
+
+    /// Returns a reference to the tracing inspector.
+    #[inline]
+    pub const fn inspector(&self) -> &TracingInspector {
+        &self.inspector
+    }
+
+    /// Returns a reference to the trace writer.
+    #[inline]
+    pub const fn writer(&self) -> &TraceWriter<W> {
+        &self.writer
+    }
+
+    /// Returns the first error that occurred while writing, if any.
+    #[inline]
+    pub const fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Consumes the `StreamingTraceWriter` and returns the tracing inspector and the trace writer.
+    #[inline]
+    pub fn into_parts(self) -> (TracingInspector, TraceWriter<W>) {
+        (self.inspector, self.writer)
+    }
+
+    /// Writes the frame of the given trace node, if no error occurred yet.
+    fn write_completed(&mut self, idx: usize) {
+        if self.error.is_some() {
+            return;
+        }
+        let trace = &self.inspector.traces.arena[idx].trace;
+        if let Err(err) = self.writer.write_frame(trace) {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl<DB, W> Inspector<DB> for StreamingTraceWriter<W>
+where
+    DB: Database,
+    W: Write,
+{
+    #[inline]
+    fn step(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspector.step(interp, context);
+    }
+
+    #[inline]
+    fn step_end(&mut self, interp: &mut Interpreter, context: &mut EvmContext<DB>) {
+        self.inspector.step_end(interp, context);
+    }
+
+    #[inline]
+    fn log(&mut self, context: &mut EvmContext<DB>, log: &Log) {
+        self.inspector.log(context, log);
+    }
+
+    #[inline]
+    fn call(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CallInputs,
+    ) -> Option<CallOutcome> {
+        self.inspector.call(context, inputs)
+    }
+
+    fn call_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        let idx = self.inspector.last_trace_idx();
+        let outcome = self.inspector.call_end(context, inputs, outcome);
+        self.write_completed(idx);
+        outcome
+    }
+
+    #[inline]
+    fn create(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.inspector.create(context, inputs)
+    }
+
+    fn create_end(
+        &mut self,
+        context: &mut EvmContext<DB>,
+        inputs: &CreateInputs,
+        outcome: CreateOutcome,
+    ) -> CreateOutcome {
+        let idx = self.inspector.last_trace_idx();
+        let outcome = self.inspector.create_end(context, inputs, outcome);
+        self.write_completed(idx);
+        outcome
+    }
+
+    #[inline]
+    fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
+        Inspector::<DB>::selfdestruct(&mut self.inspector, contract, target, value);
+    }
+}
+
+/// Returns the JSON representation of a single node and its children, see
+/// [TraceWriter::write_arena_json].
+fn json_node(nodes: &[CallTraceNode], idx: usize) -> Value {
+    let node = &nodes[idx];
+    let trace = &node.trace;
+    let decoded = &trace.decoded;
+
+    let logs = node
+        .logs
+        .iter()
+        .map(|log| {
+            json!({
+                "topics": log.raw_log.topics(),
+                "data": log.raw_log.data,
+                "name": log.decoded.name,
+                "params": log.decoded.params,
+            })
+        })
+        .collect::<Vec<_>>();
+    let calls = node.children.iter().map(|child| json_node(nodes, *child)).collect::<Vec<_>>();
+
+    json!({
+        "kind": trace.kind.to_string(),
+        "from": trace.caller,
+        "to": trace.address,
+        "value": trace.value.to_string(),
+        "gas_used": trace.gas_used,
+        "status": format!("{:?}", trace.status),
+        "input": trace.data,
+        "output": trace.output,
+        "label": decoded.label,
+        "function": decoded.call_data.as_ref().map(|call_data| &call_data.signature),
+        "args": decoded.call_data.as_ref().map(|call_data| &call_data.args),
+        "return_data": decoded.return_data,
+        "logs": logs,
+        "calls": calls,
+    })
+}
+
+/// Returns the function name and the formatted inputs of a call.
+///
+/// Undecoded inputs are formatted with `format_bytes`.
+fn func_name_and_inputs(
+    trace: &CallTrace,
+    format_bytes: impl Fn(&[u8]) -> String,
+) -> (String, String) {
+    match &trace.decoded.call_data {
+        Some(DecodedCallData { signature, args }) => {
+            let name = signature.split('(').next().unwrap();
+            (name.to_string(), args.join(", "))
+        }
+        None => {
+            if trace.data.len() < 4 {
+                ("fallback".to_string(), format_bytes(&trace.data))
+            } else {
+                let (selector, data) = trace.data.split_at(4);
+                (hex::encode(selector), format_bytes(data))
+            }
+        }
+    }
+}
+
+/// Formats a number of bytes in binary units, e.g. `1.2KiB`.
+fn format_byte_size(len: usize) -> String {
+    const KIB: usize = 1024;
+    const MIB: usize = KIB * 1024;
+    if len < KIB {
+        format!("{len}B")
+    } else if len < MIB {
+        format!("{:.1}KiB", len as f64 / KIB as f64)
+    } else {
+        format!("{:.1}MiB", len as f64 / MIB as f64)
+    }
+}
+
 fn use_colors(choice: ColorChoice) -> bool {
     use io::IsTerminal;
     match choice {
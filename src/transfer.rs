@@ -1,16 +1,35 @@
-use alloy_primitives::{Address, U256};
+use alloy_primitives::{b256, Address, Log, B256, I256, U256};
 use revm::{
-    interpreter::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, CreateScheme},
-    Database, EvmContext, Inspector,
+    interpreter::{
+        CallInputs, CallOutcome, CreateInputs, CreateOutcome, CreateScheme, InterpreterResult,
+    },
+    primitives::{Env, ResultAndState},
+    Database, DatabaseRef, EvmContext, Inspector,
 };
+use std::collections::{HashMap, HashSet};
+
+/// The signature of the ERC-20 and ERC-721 `Transfer(address,address,uint256)` event.
+const TRANSFER_EVENT_SIGNATURE: B256 =
+    b256!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
 
 /// An [Inspector] that collects internal ETH transfers.
 ///
 /// This can be used to construct via `ots_getInternalOperations`
+///
+/// Optionally, token transfers can be collected from the emitted `Transfer` events, see
+/// [TransferInspector::with_token_transfers].
+///
+/// Like `ots_getInternalOperations`, the ETH transfers of calls that were reverted are collected as
+/// well, see [TransferInspector::is_reverted]. Token transfers of reverted calls are dropped.
 #[derive(Debug, Default)]
 pub struct TransferInspector {
     internal_only: bool,
+    token_transfers: bool,
     transfers: Vec<TransferOperation>,
+    /// The indices of the transfers that were made by calls that were reverted.
+    reverted: HashSet<usize>,
+    /// The number of collected transfers when each active call was entered.
+    checkpoints: Vec<usize>,
 }
 
 impl TransferInspector {
@@ -19,7 +38,16 @@ impl TransferInspector {
     /// If `internal_only` is set to `true`, only internal transfers are collected, in other words,
     /// the top level call is ignored.
     pub fn new(internal_only: bool) -> Self {
-        Self { internal_only, transfers: Vec::new() }
+        Self { internal_only, token_transfers: false, ..Default::default() }
+    }
+
+    /// Configures whether ERC-20 and ERC-721 token transfers are collected from the emitted
+    /// `Transfer(address,address,uint256)` events, see [TransferKind::Token].
+    ///
+    /// Events emitted by calls that were reverted are discarded, since the transfers didn't happen.
+    pub const fn with_token_transfers(mut self, token_transfers: bool) -> Self {
+        self.token_transfers = token_transfers;
+        self
     }
 
     /// Creates a new transfer inspector that only collects internal transfers.
@@ -41,6 +69,139 @@ impl TransferInspector {
     pub fn iter(&self) -> impl Iterator<Item = &TransferOperation> {
         self.transfers.iter()
     }
+
+    /// Returns true if the transfer at the given index of [Self::transfers] was made by a call
+    /// that was reverted, or one of its subcalls, and therefore did not happen.
+    pub fn is_reverted(&self, idx: usize) -> bool {
+        self.reverted.contains(&idx)
+    }
+
+    /// Returns the net balance change of every address involved in a collected ETH transfer.
+    ///
+    /// Every transfer debits the sender and credits the receiver, so transfers to self, including
+    /// a selfdestruct to self, net to zero. Token transfers and the transfers of reverted calls
+    /// are ignored, and so is the top level call if the inspector was configured with
+    /// `internal_only`.
+    pub fn net_balance_changes(&self) -> HashMap<Address, I256> {
+        let mut changes = HashMap::<Address, I256>::new();
+        for (idx, transfer) in self.transfers.iter().enumerate() {
+            if transfer.kind.is_token() || self.is_reverted(idx) {
+                continue;
+            }
+            let value = I256::from_raw(transfer.value);
+            *changes.entry(transfer.from).or_default() -= value;
+            *changes.entry(transfer.to).or_default() += value;
+        }
+        changes
+    }
+
+    /// Returns the fees paid by the sender of the transaction as separate transfers, see
+    /// [TransferKind::Fee].
+    ///
+    /// The base fee and blob fee are burned, which is represented as a transfer to the zero
+    /// address, the priority fee is transferred to the coinbase. Fees that are zero, e.g. the blob
+    /// fee of non-blob transactions, are omitted.
+    ///
+    /// `gas_used` is the gas used by the transaction as reported by its
+    /// [ExecutionResult](revm::primitives::ExecutionResult).
+    pub fn fee_transfers(env: &Env, gas_used: u64) -> Vec<TransferOperation> {
+        let gas_used = U256::from(gas_used);
+        let effective_gas_price = env.effective_gas_price();
+        // before London there's no base fee, so the entire gas price goes to the coinbase
+        let base_fee = effective_gas_price.min(env.block.basefee);
+        let fees = [
+            (FeeKind::BaseFee, Address::ZERO, base_fee * gas_used),
+            (FeeKind::PriorityFee, env.block.coinbase, (effective_gas_price - base_fee) * gas_used),
+            (FeeKind::BlobFee, Address::ZERO, env.calc_data_fee().unwrap_or_default()),
+        ];
+        fees.into_iter()
+            .filter(|(_, _, value)| !value.is_zero())
+            .map(|(kind, to, value)| TransferOperation {
+                kind: TransferKind::Fee(kind),
+                from: env.tx.caller,
+                to,
+                value,
+            })
+            .collect()
+    }
+
+    /// Marks the transfers of the call that ended as reverted if it failed.
+    ///
+    /// Token transfers of a failed call are dropped, since their events were never emitted.
+    fn end_frame(&mut self, result: &InterpreterResult) {
+        let checkpoint = self.checkpoints.pop().unwrap_or_default();
+        if result.is_ok() {
+            return;
+        }
+        let transfers = self.transfers.split_off(checkpoint);
+        self.transfers.extend(transfers.into_iter().filter(|transfer| !transfer.kind.is_token()));
+        self.reverted.retain(|idx| *idx < checkpoint);
+        self.reverted.extend(checkpoint..self.transfers.len());
+    }
+
+    /// Reconciles the collected transfers against the balance changes of the executed transaction.
+    ///
+    /// For every account this compares the net balance change between the `db`, which should
+    /// point to the state before the transaction, and the post-execution state with the sum of
+    /// all recorded transfers and the fees paid by the sender and received by the coinbase.
+    ///
+    /// Returns the accounts for which these don't match, mapped to the unexplained balance delta.
+    /// The burned base fee, the transfers of reverted calls and the balance burned by a
+    /// selfdestruct to self are accounted for, so an empty map means the recorded transfers fully
+    /// explain the state diff.
+    ///
+    /// Note: this expects that the inspector was _not_ configured with `internal_only`, since the
+    /// top level transfer would otherwise be reported as a discrepancy. Token transfers are
+    /// ignored.
+    pub fn balance_discrepancies<DB: DatabaseRef>(
+        &self,
+        res: &ResultAndState,
+        env: &Env,
+        db: DB,
+    ) -> Result<HashMap<Address, I256>, DB::Error> {
+        let mut expected = self.net_balance_changes();
+
+        // the sender pays for all used gas and blob gas, the coinbase only receives the priority
+        // fee, the base fee is burned
+        let gas_used = U256::from(res.result.gas_used());
+        let effective_gas_price = env.effective_gas_price();
+        let sender_fee = effective_gas_price * gas_used + env.calc_data_fee().unwrap_or_default();
+        let coinbase_fee = effective_gas_price.saturating_sub(env.block.basefee) * gas_used;
+        *expected.entry(env.tx.caller).or_default() -= I256::from_raw(sender_fee);
+        *expected.entry(env.block.coinbase).or_default() += I256::from_raw(coinbase_fee);
+
+        // the balance of a destroyed account that selfdestructed to itself is burned, e.g. before
+        // Cancun
+        for (idx, transfer) in self.transfers.iter().enumerate() {
+            if transfer.kind == TransferKind::SelfDestruct
+                && transfer.from == transfer.to
+                && !self.is_reverted(idx)
+                && res.state.get(&transfer.from).is_some_and(|acc| acc.is_selfdestructed())
+            {
+                *expected.entry(transfer.from).or_default() -= I256::from_raw(transfer.value);
+            }
+        }
+
+        let mut discrepancies = HashMap::new();
+        for (address, account) in &res.state {
+            let pre = db.basic_ref(*address)?.map(|acc| acc.balance).unwrap_or_default();
+            let actual = I256::from_raw(account.info.balance) - I256::from_raw(pre);
+            let delta = actual - expected.remove(address).unwrap_or_default();
+            if !delta.is_zero() {
+                discrepancies.insert(*address, delta);
+            }
+        }
+
+        // any remaining entries belong to accounts that were not touched at all
+        discrepancies.extend(
+            expected
+                .into_iter()
+                .filter(|(_, delta)| !delta.is_zero())
+                .map(|(address, delta)| (address, -delta)),
+        );
+
+        Ok(discrepancies)
+    }
 }
 
 impl<DB> Inspector<DB> for TransferInspector
@@ -52,6 +213,8 @@ where
         context: &mut EvmContext<DB>,
         inputs: &mut CallInputs,
     ) -> Option<CallOutcome> {
+        self.checkpoints.push(self.transfers.len());
+
         if self.internal_only && context.journaled_state.depth() == 0 {
             // skip top level call
             return None;
@@ -69,16 +232,33 @@ where
         None
     }
 
+    fn call_end(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &CallInputs,
+        outcome: CallOutcome,
+    ) -> CallOutcome {
+        self.end_frame(&outcome.result);
+        outcome
+    }
+
+    fn create(
+        &mut self,
+        _context: &mut EvmContext<DB>,
+        _inputs: &mut CreateInputs,
+    ) -> Option<CreateOutcome> {
+        self.checkpoints.push(self.transfers.len());
+        None
+    }
+
     fn create_end(
         &mut self,
         context: &mut EvmContext<DB>,
         inputs: &CreateInputs,
         outcome: CreateOutcome,
     ) -> CreateOutcome {
-        if self.internal_only && context.journaled_state.depth() == 0 {
-            return outcome;
-        }
-        if let Some(address) = outcome.address {
+        let skip = self.internal_only && context.journaled_state.depth() == 0;
+        if let Some(address) = outcome.address.filter(|_| !skip) {
             let kind = match inputs.scheme {
                 CreateScheme::Create => TransferKind::Create,
                 CreateScheme::Create2 { .. } => TransferKind::Create2,
@@ -90,9 +270,19 @@ where
                 value: inputs.value,
             });
         }
+        self.end_frame(&outcome.result);
         outcome
     }
 
+    fn log(&mut self, _context: &mut EvmContext<DB>, log: &Log) {
+        if !self.token_transfers {
+            return;
+        }
+        if let Some(transfer) = TransferOperation::from_token_transfer_log(log) {
+            self.transfers.push(transfer);
+        }
+    }
+
     fn selfdestruct(&mut self, contract: Address, target: Address, value: U256) {
         self.transfers.push(TransferOperation {
             kind: TransferKind::SelfDestruct,
@@ -116,6 +306,30 @@ pub struct TransferOperation {
     pub value: U256,
 }
 
+impl TransferOperation {
+    /// Decodes a token transfer from a `Transfer(address,address,uint256)` event.
+    ///
+    /// ERC-20 transfers have the amount as data, ERC-721 transfers have an indexed token id
+    /// instead. Returns `None` if the log is not a well formed transfer event of either standard.
+    fn from_token_transfer_log(log: &Log) -> Option<Self> {
+        let (from, to, standard, value) = match (log.topics(), log.data.data.as_ref()) {
+            ([TRANSFER_EVENT_SIGNATURE, from, to], amount) if amount.len() == 32 => {
+                (from, to, TokenStandard::Erc20, U256::from_be_slice(amount))
+            }
+            ([TRANSFER_EVENT_SIGNATURE, from, to, token_id], []) => {
+                (from, to, TokenStandard::Erc721, (*token_id).into())
+            }
+            _ => return None,
+        };
+        Some(Self {
+            kind: TransferKind::Token { token: log.address, standard },
+            from: Address::from_word(*from),
+            to: Address::from_word(*to),
+            value,
+        })
+    }
+}
+
 /// The kind of transfer operation.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TransferKind {
@@ -127,4 +341,43 @@ pub enum TransferKind {
     Create2,
     /// A SELFDESTRUCT operation
     SelfDestruct,
+    /// A fee paid by the transaction sender, see [TransferInspector::fee_transfers].
+    Fee(FeeKind),
+    /// A token transfer, decoded from a `Transfer` event emitted by the `token` contract.
+    ///
+    /// The value of the [TransferOperation] is the transferred amount for ERC-20 tokens, and the
+    /// token id for ERC-721 tokens.
+    Token {
+        /// The address of the token contract.
+        token: Address,
+        /// The token standard of the transfer.
+        standard: TokenStandard,
+    },
+}
+
+impl TransferKind {
+    /// Returns true if this is a token transfer.
+    pub const fn is_token(&self) -> bool {
+        matches!(self, Self::Token { .. })
+    }
+}
+
+/// The component of the transaction fee of a [TransferKind::Fee] transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeKind {
+    /// The base fee, which is burned.
+    BaseFee,
+    /// The priority fee, which is paid to the coinbase.
+    PriorityFee,
+    /// The [EIP-4844](https://eips.ethereum.org/EIPS/eip-4844) blob fee, which is burned.
+    BlobFee,
+}
+
+/// The standard of a token transfer, see [TransferKind::Token].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenStandard {
+    /// A fungible token transfer, the amount is the event data.
+    Erc20,
+    /// A non-fungible token transfer, the token id is the third indexed event topic.
+    Erc721,
 }
@@ -0,0 +1,111 @@
+//! Access list inspector tests
+
+use crate::utils::TestEvm;
+use alloy_primitives::{address, hex, Address, B256};
+use alloy_rpc_types::{AccessList, AccessListItem};
+use revm::precompile::Precompiles;
+use revm_inspectors::access_list::AccessListInspector;
+
+#[test]
+fn test_access_list_excludes_warm_addresses() {
+    let caller = address!("0000000000000000000000000000000000000011");
+    let to = address!("00000000000000000000000000000000000000aa");
+    let other = address!("00000000000000000000000000000000000000bb");
+    let sha256 = address!("0000000000000000000000000000000000000002");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    evm.env.tx.caller = caller;
+    evm.set_code(to, [call(sha256), call(other), vec![0x00]].concat().into());
+
+    let provided = AccessList(
+        [caller, to]
+            .into_iter()
+            .map(|address| AccessListItem { address, storage_keys: vec![] })
+            .collect(),
+    );
+    let new_inspector = || {
+        AccessListInspector::new(
+            provided.clone(),
+            caller,
+            to,
+            Precompiles::latest().addresses().copied(),
+        )
+    };
+
+    let mut insp = new_inspector();
+    let res = evm.clone().call(to, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+    let access_list = insp.into_access_list();
+    assert_eq!(access_list.0, vec![AccessListItem { address: other, storage_keys: vec![] }]);
+
+    // the provided warm addresses are kept
+    let mut insp = new_inspector().with_include_warm_addresses(true);
+    evm.clone().call(to, Default::default(), &mut insp).unwrap();
+    let mut addresses =
+        insp.access_list().0.into_iter().map(|item| item.address).collect::<Vec<_>>();
+    addresses.sort();
+    assert_eq!(addresses, vec![caller, to, other]);
+
+    // touched storage slots of warm addresses are not warm
+    // PUSH1 0x00 SLOAD POP STOP
+    evm.set_code(to, hex!("6000545000").into());
+    let mut insp = new_inspector();
+    evm.call(to, Default::default(), &mut insp).unwrap();
+    assert_eq!(
+        insp.into_access_list().0,
+        vec![AccessListItem { address: to, storage_keys: vec![B256::ZERO] }]
+    );
+}
+
+#[test]
+fn test_access_list_sorted_and_unique() {
+    let caller = address!("0000000000000000000000000000000000000011");
+    let to = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.env.tx.caller = caller;
+    // PUSH1 0x05 SLOAD POP PUSH1 0x01 PUSH1 0x05 SSTORE PUSH1 0x09 SLOAD POP PUSH1 0x05 SLOAD POP
+    // PUSH1 0x02 SLOAD POP
+    // PUSH20 0xcc BALANCE POP PUSH20 0xbb BALANCE POP STOP
+    let code = [
+        &hex!("600554506001600555600954506005545060025450")[..],
+        &[0x73],
+        &address!("00000000000000000000000000000000000000cc")[..],
+        &hex!("3150"),
+        &[0x73],
+        &address!("00000000000000000000000000000000000000bb")[..],
+        &hex!("315000"),
+    ]
+    .concat();
+    evm.set_code(to, code.into());
+
+    let mut access_lists = Vec::new();
+    for _ in 0..2 {
+        let mut insp = AccessListInspector::new(Default::default(), caller, to, []);
+        let res = evm.clone().call(to, Default::default(), &mut insp).unwrap();
+        assert!(res.is_success());
+        access_lists.push(insp.into_access_list());
+    }
+    assert_eq!(access_lists[0], access_lists[1]);
+
+    let slot = |slot: u8| B256::with_last_byte(slot);
+    assert_eq!(
+        access_lists[0].0,
+        vec![
+            AccessListItem { address: to, storage_keys: vec![slot(2), slot(5), slot(9)] },
+            AccessListItem {
+                address: address!("00000000000000000000000000000000000000bb"),
+                storage_keys: vec![]
+            },
+            AccessListItem {
+                address: address!("00000000000000000000000000000000000000cc"),
+                storage_keys: vec![]
+            },
+        ]
+    );
+}
@@ -1,20 +1,24 @@
 //! Geth tests
 
-use crate::utils::inspect;
-use alloy_primitives::{hex, Address, Bytes};
+use crate::utils::{inspect, TestEvm};
+use alloy_primitives::{address, hex, Address, Bytes, U256};
 use alloy_rpc_types::trace::geth::{
-    mux::MuxConfig, CallConfig, GethDebugBuiltInTracerType, GethDebugTracerConfig, GethTrace,
-    PreStateConfig,
+    mux::MuxConfig, CallConfig, CallFrame, GethDebugBuiltInTracerType, GethDebugTracerConfig,
+    GethDefaultTracingOptions, GethTrace, PreStateConfig, PreStateFrame,
 };
 use revm::{
     db::{CacheDB, EmptyDB},
+    interpreter::OpCode,
     primitives::{
-        BlockEnv, CfgEnv, CfgEnvWithHandlerCfg, EnvWithHandlerCfg, ExecutionResult, HandlerCfg,
-        Output, SpecId, TransactTo, TxEnv,
+        AccountInfo, BlockEnv, CfgEnv, CfgEnvWithHandlerCfg, EnvWithHandlerCfg, ExecutionResult,
+        HandlerCfg, Output, SpecId, TransactTo, TxEnv,
     },
     DatabaseCommit,
 };
-use revm_inspectors::tracing::{MuxInspector, TracingInspector, TracingInspectorConfig};
+use revm_inspectors::tracing::{
+    geth::SelfdestructPlacement, types::TraceMemberOrder, GethTraceBuilder, MuxError, MuxInspector,
+    OpcodeFilter, TracingInspector, TracingInspectorConfig,
+};
 use std::collections::HashMap;
 
 #[test]
@@ -102,10 +106,9 @@ fn test_geth_calltracer_logs() {
     let (res, _) = inspect(&mut db, env, &mut insp).unwrap();
     assert!(res.result.is_success());
 
-    let call_frame = insp
-        .with_transaction_gas_used(res.result.gas_used())
-        .into_geth_builder()
-        .geth_call_traces(CallConfig::default().with_log(), res.result.gas_used());
+    let builder = insp.with_transaction_gas_used(res.result.gas_used()).into_geth_builder();
+    let call_frame =
+        builder.geth_call_traces(CallConfig::default().with_log(), res.result.gas_used());
 
     // three subcalls
     assert_eq!(call_frame.calls.len(), 3);
@@ -124,6 +127,30 @@ fn test_geth_calltracer_logs() {
 
     // third call succeeded, one log
     assert_eq!(call_frame.calls[2].logs.len(), 1);
+
+    let ordered =
+        builder.geth_call_traces_ordered(CallConfig::default().with_log(), res.result.gas_used());
+
+    // emit first, then the three subcalls
+    assert_eq!(
+        ordered.ordering,
+        vec![
+            TraceMemberOrder::Log(0),
+            TraceMemberOrder::Call(0),
+            TraceMemberOrder::Call(1),
+            TraceMemberOrder::Call(2)
+        ]
+    );
+    assert_eq!(ordered.calls.len(), 3);
+    assert!(ordered.frame.calls.is_empty());
+    assert_eq!(ordered.frame.logs, call_frame.logs);
+
+    // logs of failed calls are excluded from the ordering
+    assert!(ordered.calls[0].ordering.is_empty());
+    assert_eq!(ordered.calls[1].ordering, vec![TraceMemberOrder::Call(0)]);
+    assert_eq!(ordered.calls[1].calls[0].ordering, vec![TraceMemberOrder::Call(0)]);
+    assert!(ordered.calls[1].calls[0].calls[0].ordering.is_empty());
+    assert_eq!(ordered.calls[2].ordering, vec![TraceMemberOrder::Log(0)]);
 }
 
 #[test]
@@ -283,6 +310,102 @@ fn test_geth_mux_tracer() {
     }
 }
 
+#[test]
+fn test_geth_mux_tracer_partial_failure() {
+    let address = address!("00000000000000000000000000000000000000aa");
+    let mut evm = TestEvm::new();
+    // PUSH1 0x01 STOP
+    evm.set_code(address, Bytes::from_static(&hex!("600100")));
+    evm.env.tx.transact_to = TransactTo::Call(address);
+
+    let call_config = CallConfig { only_top_call: Some(true), with_log: Some(false) };
+    let config = MuxConfig(HashMap::from([
+        (GethDebugBuiltInTracerType::FourByteTracer, None),
+        (
+            GethDebugBuiltInTracerType::CallTracer,
+            Some(GethDebugTracerConfig(serde_json::to_value(call_config).unwrap())),
+        ),
+        (
+            GethDebugBuiltInTracerType::PreStateTracer,
+            Some(GethDebugTracerConfig(serde_json::json!({ "diffMode": "yes" }))),
+        ),
+    ]));
+
+    assert!(MuxInspector::try_from_config(config.clone()).is_err());
+
+    let mut insp = MuxInspector::from_config(config);
+    assert_eq!(insp.failed_tracers().len(), 1);
+
+    let (res, _) = evm.inspect(&mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    let (frame, failed) = insp.try_into_mux_frame_with_failures(&res, &evm.db).unwrap();
+    assert_eq!(frame.0.len(), 2);
+    assert!(frame.0.contains_key(&GethDebugBuiltInTracerType::FourByteTracer));
+    match &frame.0[&GethDebugBuiltInTracerType::CallTracer] {
+        GethTrace::CallTracer(call_frame) => assert_eq!(call_frame.to, Some(address)),
+        _ => panic!("Expected CallTracer"),
+    }
+
+    assert_eq!(failed.len(), 1);
+    assert!(matches!(
+        *failed[&GethDebugBuiltInTracerType::PreStateTracer],
+        MuxError::InvalidConfig(_)
+    ));
+}
+
+#[test]
+fn test_geth_mux_tracer_insert_remove() {
+    let address = address!("00000000000000000000000000000000000000aa");
+    let mut evm = TestEvm::new();
+    // PUSH1 0x01 STOP
+    evm.set_code(address, Bytes::from_static(&hex!("600100")));
+    evm.env.tx.transact_to = TransactTo::Call(address);
+    evm.env.tx.data = Bytes::from_static(&hex!("deadbeef"));
+
+    let mut insp = MuxInspector::default();
+    let call_config = CallConfig { only_top_call: Some(true), with_log: Some(false) };
+    insp.insert_tracer(
+        GethDebugBuiltInTracerType::CallTracer,
+        Some(GethDebugTracerConfig(serde_json::to_value(call_config).unwrap())),
+    )
+    .unwrap();
+    insp.insert_tracer(GethDebugBuiltInTracerType::FourByteTracer, None).unwrap();
+    assert!(insp
+        .insert_tracer(
+            GethDebugBuiltInTracerType::PreStateTracer,
+            Some(GethDebugTracerConfig(serde_json::json!({ "diffMode": "yes" }))),
+        )
+        .is_err());
+
+    let (res, _) = evm.inspect(&mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    // the state of all tracers is reset
+    insp.fuse();
+    let (res, _) = evm.inspect(&mut insp).unwrap();
+
+    let frame = insp.clone().try_into_mux_frame(&res, &evm.db).unwrap();
+    assert_eq!(frame.0.len(), 2);
+    match &frame.0[&GethDebugBuiltInTracerType::CallTracer] {
+        GethTrace::CallTracer(call_frame) => assert_eq!(call_frame.to, Some(address)),
+        _ => panic!("Expected CallTracer"),
+    }
+    match &frame.0[&GethDebugBuiltInTracerType::FourByteTracer] {
+        GethTrace::FourByteTracer(four_byte_frame) => {
+            assert_eq!(four_byte_frame.0.len(), 1);
+            assert_eq!(four_byte_frame.0["0xdeadbeef-0"], 1);
+        }
+        _ => panic!("Expected FourByteTracer"),
+    }
+
+    assert!(insp.remove_tracer(GethDebugBuiltInTracerType::CallTracer));
+    assert!(!insp.remove_tracer(GethDebugBuiltInTracerType::CallTracer));
+    let frame = insp.try_into_mux_frame(&res, &evm.db).unwrap();
+    assert_eq!(frame.0.len(), 1);
+    assert!(frame.0.contains_key(&GethDebugBuiltInTracerType::FourByteTracer));
+}
+
 #[test]
 fn test_geth_inspector_reset() {
     let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
@@ -317,3 +440,513 @@ fn test_geth_inspector_reset() {
     assert!(res.result.is_success());
     assert_eq!(insp.traces().nodes().first().unwrap().trace.gas_limit, 1000000);
 }
+
+#[test]
+fn test_geth_calltracer_selfdestruct_placement() {
+    /*
+    contract DummySelfDestruct {
+        function close() public {
+            new Noop();
+        }
+    }
+    contract Noop {
+        constructor() {
+            selfdestruct(payable(msg.sender));
+        }
+    }
+    */
+
+    let code = hex!("6080604052348015600f57600080fd5b5060b48061001e6000396000f3fe6080604052348015600f57600080fd5b506004361060285760003560e01c806343d726d614602d575b600080fd5b60336035565b005b604051603f90605e565b604051809103906000f080158015605a573d6000803e3d6000fd5b5050565b60148061006b8339019056fe6080604052348015600f57600080fd5b5033fffea264697066735822122087fcd1ed364913e41107ea336facf7b7f5972695b3e3abcf55dbb2452e124ea964736f6c634300080d0033");
+    let deployer = Address::ZERO;
+
+    let mut db = CacheDB::new(EmptyDB::default());
+
+    let cfg = CfgEnvWithHandlerCfg::new(CfgEnv::default(), HandlerCfg::new(SpecId::LONDON));
+
+    let env = EnvWithHandlerCfg::new_with_cfg_env(
+        cfg.clone(),
+        BlockEnv::default(),
+        TxEnv {
+            caller: deployer,
+            gas_limit: 1000000,
+            transact_to: TransactTo::Create,
+            data: code.into(),
+            ..Default::default()
+        },
+    );
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let (res, _) = inspect(&mut db, env, &mut insp).unwrap();
+    let addr = match res.result {
+        ExecutionResult::Success { output, .. } => match output {
+            Output::Create(_, addr) => addr.unwrap(),
+            _ => panic!("Create failed"),
+        },
+        _ => panic!("Execution failed"),
+    };
+    db.commit(res.state);
+
+    let env = EnvWithHandlerCfg::new_with_cfg_env(
+        cfg,
+        BlockEnv::default(),
+        TxEnv {
+            caller: deployer,
+            gas_limit: 1000000,
+            transact_to: TransactTo::Call(addr),
+            data: hex!("43d726d6").into(),
+            ..Default::default()
+        },
+    );
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::from_geth_call_config(
+        &CallConfig::default(),
+    ));
+    let (res, _) = inspect(&mut db, env, &mut insp).unwrap();
+    assert!(res.result.is_success());
+    let gas_used = res.result.gas_used();
+
+    let builder = insp.into_geth_builder();
+
+    // geth default: the selfdestruct is a child of the selfdestructed CREATE frame
+    let call_frame = builder.clone().geth_call_traces(CallConfig::default(), gas_used);
+    assert_eq!(call_frame.calls.len(), 1);
+    assert_eq!(call_frame.calls[0].typ, "CREATE");
+    assert_eq!(call_frame.calls[0].calls.len(), 1);
+    assert_eq!(call_frame.calls[0].calls[0].typ, "SELFDESTRUCT");
+
+    // inline: the selfdestruct directly follows the CREATE frame
+    let call_frame = builder
+        .with_selfdestruct_placement(SelfdestructPlacement::Inline)
+        .geth_call_traces(CallConfig::default(), gas_used);
+    assert_eq!(call_frame.calls.len(), 2);
+    assert_eq!(call_frame.calls[0].typ, "CREATE");
+    assert!(call_frame.calls[0].calls.is_empty());
+    assert_eq!(call_frame.calls[1].typ, "SELFDESTRUCT");
+}
+
+#[test]
+fn test_geth_calltracer_only_addresses() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let router = address!("00000000000000000000000000000000000000bb");
+    let target = address!("00000000000000000000000000000000000000cc");
+    let other = address!("00000000000000000000000000000000000000dd");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+    // PUSH1 0x01 STOP
+    let stop = Bytes::from_static(&hex!("600100"));
+
+    let mut evm = TestEvm::new();
+    // root calls the router, which calls the target, and then calls another contract
+    evm.set_code(root, [call(router), call(other), vec![0x00]].concat().into());
+    evm.set_code(router, [call(target), vec![0x00]].concat().into());
+    evm.set_code(target, stop.clone());
+    evm.set_code(other, stop);
+
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::from_geth_call_config(&CallConfig::default())
+            .with_only_addresses([target]),
+    );
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let frame = insp.into_geth_builder().geth_call_traces(CallConfig::default(), res.gas_used());
+    assert_eq!(frame.to, Some(root));
+    assert_eq!(frame.calls.len(), 1);
+    // the target is attached to the root since the router is not recorded
+    assert_eq!(frame.calls[0].from, router);
+    assert_eq!(frame.calls[0].to, Some(target));
+    assert!(frame.calls[0].calls.is_empty());
+}
+
+#[test]
+fn test_geth_clear_steps() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let callee = address!("00000000000000000000000000000000000000bb");
+
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL STOP
+    let code = [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af100")].concat();
+    let mut evm = TestEvm::new();
+    evm.set_code(root, code.into());
+    // PUSH1 0x01 STOP
+    evm.set_code(callee, Bytes::from_static(&hex!("600100")));
+
+    let config = TracingInspectorConfig::default_geth();
+    let mut insp = TracingInspector::new(config.clone());
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    let gas_used = res.gas_used();
+
+    let builder = GethTraceBuilder::new(insp.traces().nodes().to_vec(), config);
+    let frame = builder.geth_call_traces(CallConfig::default(), gas_used);
+    let trace = builder.geth_traces(gas_used, Bytes::new(), Default::default());
+    assert!(!trace.struct_logs.is_empty());
+
+    let traces = insp.traces_mut();
+    traces.clear_steps();
+    assert!(traces.nodes().iter().all(|node| node.trace.steps.is_empty()));
+    assert!(traces
+        .nodes()
+        .iter()
+        .all(|node| node.ordering.iter().all(|o| matches!(o, TraceMemberOrder::Call(_)))));
+
+    let builder = insp.into_geth_builder();
+    assert_eq!(builder.geth_call_traces(CallConfig::default(), gas_used), frame);
+    let trace = builder.geth_traces(gas_used, Bytes::new(), Default::default());
+    assert!(trace.struct_logs.is_empty());
+}
+
+#[test]
+fn test_geth_calltracer_include_precompiles() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let sha256 = address!("0000000000000000000000000000000000000002");
+
+    // PUSH1 0x00 (x5) PUSH20 <sha256> GAS CALL STOP
+    let code = [&hex!("60006000600060006000")[..], &[0x73], &sha256[..], &hex!("5af100")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, code.into());
+
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::from_geth_call_config(&CallConfig::default())
+            .set_exclude_precompile_calls(true),
+    );
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let builder = insp.into_geth_builder();
+
+    let frame = builder.geth_call_traces(CallConfig::default(), res.gas_used());
+    assert!(frame.calls.is_empty());
+
+    let frame = builder
+        .with_include_precompiles(true)
+        .geth_call_traces(CallConfig::default(), res.gas_used());
+    assert_eq!(frame.calls.len(), 1);
+    assert_eq!(frame.calls[0].from, root);
+    assert_eq!(frame.calls[0].to, Some(sha256));
+    assert_eq!(frame.calls[0].typ, "CALL");
+}
+
+#[test]
+fn test_geth_calltracer_exclude_precompiles() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let target = address!("00000000000000000000000000000000000000bb");
+    let ecrecover = address!("0000000000000000000000000000000000000001");
+    let sha256 = address!("0000000000000000000000000000000000000002");
+
+    // PUSH1 0x00 (x4) PUSH1 <value> PUSH20 <address> GAS CALL POP
+    let call = |address: Address, value: u8| {
+        [&hex!("6000600060006000")[..], &[0x60, value, 0x73], &address[..], &hex!("5af150")]
+            .concat()
+    };
+
+    let mut evm = TestEvm::new();
+    evm.set_code(
+        root,
+        [call(ecrecover, 0), call(target, 0), call(sha256, 1), vec![0x00]].concat().into(),
+    );
+    evm.db.accounts.get_mut(&root).unwrap().info.balance = U256::from(1);
+    // PUSH1 0x01 STOP
+    evm.set_code(target, hex!("600100").into());
+
+    // precompile calls are recorded and attached
+    let mut insp = TracingInspector::new(TracingInspectorConfig::from_geth_call_config(
+        &CallConfig::default(),
+    ));
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let builder = insp.into_geth_builder();
+    let frame = builder.geth_call_traces(CallConfig::default(), res.gas_used());
+    let targets = |frame: &CallFrame| frame.calls.iter().map(|call| call.to).collect::<Vec<_>>();
+    assert_eq!(targets(&frame), [Some(ecrecover), Some(target), Some(sha256)]);
+
+    let frame = builder
+        .with_exclude_precompiles(true)
+        .geth_call_traces(CallConfig::default(), res.gas_used());
+    // the value transfer to the precompile is kept
+    assert_eq!(targets(&frame), [Some(target), Some(sha256)]);
+    assert_eq!(frame.calls[1].value, Some(U256::from(1)));
+}
+
+#[test]
+fn test_geth_calltracer_receipt_gas_used() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let child = address!("00000000000000000000000000000000000000bb");
+
+    // PUSH1 0x00 (x5) PUSH20 <child> GAS CALL STOP
+    let code = [&hex!("60006000600060006000")[..], &[0x73], &child[..], &hex!("5af100")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, code.into());
+    // PUSH1 0x01 STOP
+    evm.set_code(child, Bytes::from_static(&hex!("600100")));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let builder = insp.into_geth_builder();
+    let child_gas_used = builder.nodes()[1].trace.gas_used;
+    // the receipt gas used includes the intrinsic gas, the execution gas doesn't
+    assert_eq!(builder.execution_gas_used() + 21000, res.gas_used());
+
+    let frame = builder.geth_call_traces(CallConfig::default(), res.gas_used());
+    assert_eq!(frame.gas_used, U256::from(res.gas_used()));
+    assert_eq!(frame.calls.len(), 1);
+    assert_eq!(frame.calls[0].gas_used, U256::from(child_gas_used));
+    assert_eq!(child_gas_used, 3);
+}
+
+#[test]
+fn test_geth_execution_gas_used_refund() {
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    // PUSH1 0x00 PUSH1 0x00 SSTORE STOP
+    evm.set_code(address, Bytes::from_static(&hex!("6000600055")));
+    evm.db.insert_account_storage(address, U256::ZERO, U256::from(1)).unwrap();
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    // the execution spends 2 * 3 + 2100 + 2900 gas, the refund of 4800 is capped at a fifth of
+    // the execution gas, unlike the receipt where it's capped at a fifth of the total gas
+    assert_eq!(res.gas_used(), 21000 + 5006 - 4800);
+    assert_eq!(insp.clone().into_geth_builder().execution_gas_used(), 5006 - 5006 / 5);
+
+    // overwriting the gas used of the root trace changes the execution gas used
+    let builder = insp.with_transaction_gas_used(res.gas_used()).into_geth_builder();
+    assert_eq!(builder.execution_gas_used(), res.gas_used());
+}
+
+#[test]
+fn test_geth_prestate_exclude_precompiles() {
+    let caller = address!("00000000000000000000000000000000000000ff");
+    let root = address!("00000000000000000000000000000000000000aa");
+    let ecrecover = address!("0000000000000000000000000000000000000001");
+    let sha256 = address!("0000000000000000000000000000000000000002");
+
+    // PUSH1 0x00 (x4) PUSH1 <value> PUSH20 <address> GAS CALL POP
+    let call = |address: Address, value: u8| {
+        [&hex!("6000600060006000")[..], &[0x60, value, 0x73], &address[..], &hex!("5af150")]
+            .concat()
+    };
+
+    let mut evm = TestEvm::new();
+    // call ecrecover without value and sha256 with value
+    evm.set_code(root, [call(ecrecover, 0), call(sha256, 1), vec![0x00]].concat().into());
+    evm.db
+        .insert_account_info(caller, AccountInfo { balance: U256::from(1), ..Default::default() });
+    evm.env.tx.caller = caller;
+    evm.env.tx.value = U256::from(1);
+    evm.env.tx.transact_to = TransactTo::Call(root);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::from_geth_prestate_config(
+        &Default::default(),
+    ));
+    let (res, _) = evm.inspect(&mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    let builder = insp.into_geth_builder();
+
+    let prestate = builder.geth_prestate_traces(&res, PreStateConfig::default(), &evm.db).unwrap();
+    let PreStateFrame::Default(prestate) = prestate else { panic!("expected default mode") };
+    assert!(prestate.0.contains_key(&ecrecover));
+    assert!(prestate.0.contains_key(&sha256));
+
+    let builder = builder.with_exclude_prestate_precompiles(true);
+    let prestate = builder.geth_prestate_traces(&res, PreStateConfig::default(), &evm.db).unwrap();
+    let PreStateFrame::Default(prestate) = prestate else { panic!("expected default mode") };
+    assert!(!prestate.0.contains_key(&ecrecover));
+    // the precompile received a value transfer
+    assert!(prestate.0.contains_key(&sha256));
+    assert!(prestate.0.contains_key(&root));
+
+    let diff_config = PreStateConfig { diff_mode: Some(true) };
+    let prestate = builder.geth_prestate_traces(&res, diff_config, &evm.db).unwrap();
+    let PreStateFrame::Diff(diff) = prestate else { panic!("expected diff mode") };
+    assert!(!diff.pre.contains_key(&ecrecover));
+    assert!(!diff.post.contains_key(&ecrecover));
+    assert!(diff.post.contains_key(&sha256));
+}
+
+#[test]
+fn test_geth_prestate_exclude_precompiles_of_spec() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    // the point evaluation precompile address, which is not a precompile before Cancun
+    let account = address!("000000000000000000000000000000000000000a");
+
+    // PUSH1 0x00 (x5) PUSH20 <account> GAS CALL STOP
+    let code = [&hex!("60006000600060006000")[..], &[0x73], &account[..], &hex!("5af100")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.env.handler_cfg.spec_id = SpecId::SHANGHAI;
+    evm.set_code(root, code.into());
+    // PUSH1 0x01 STOP
+    evm.set_code(account, Bytes::from_static(&hex!("600100")));
+    evm.env.tx.transact_to = TransactTo::Call(root);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::from_geth_prestate_config(
+        &Default::default(),
+    ));
+    let (res, _) = evm.inspect(&mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    let builder = insp.into_geth_builder().with_exclude_prestate_precompiles(true);
+    let prestate = builder.geth_prestate_traces(&res, PreStateConfig::default(), &evm.db).unwrap();
+    let PreStateFrame::Default(prestate) = prestate else { panic!("expected default mode") };
+    assert!(prestate.0.contains_key(&account));
+}
+
+#[test]
+fn test_geth_struct_log_config_skips_disabled_snapshots() {
+    // PUSH1 0x01 PUSH1 0x00 MSTORE PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    let code = hex!("600160005260016000550000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code.into());
+
+    let trace = |config: TracingInspectorConfig| {
+        let mut insp = TracingInspector::new(config);
+        // storage must be unchanged for every run
+        let res = evm.clone().call(address, Bytes::new(), &mut insp).unwrap();
+        assert!(res.is_success());
+        insp.into_traces().into_nodes().remove(0).trace.steps
+    };
+
+    let opts =
+        GethDefaultTracingOptions::default().disable_memory().disable_stack().disable_storage();
+    let steps = trace(TracingInspectorConfig::from_geth_config(&opts));
+    assert!(!steps.is_empty());
+    for step in &steps {
+        assert!(step.memory.is_none());
+        assert!(step.stack.is_none());
+        assert!(step.storage_change.is_none());
+    }
+
+    // memory is included in the output if it is not disabled
+    let opts = GethDefaultTracingOptions::default().with_disable_memory(false);
+    let steps = trace(TracingInspectorConfig::from_geth_config(&opts));
+    assert!(steps.iter().all(|step| step.memory.is_some() && step.stack.is_some()));
+    assert!(steps.iter().any(|step| step.storage_change.is_some()));
+
+    // only the filtered opcodes are recorded, with their snapshots
+    let filter = OpcodeFilter::new().enabled(OpCode::MSTORE).enabled(OpCode::SSTORE);
+    let opts = GethDefaultTracingOptions::default().enable_memory();
+    let steps = trace(TracingInspectorConfig {
+        record_opcodes_filter: Some(filter),
+        ..TracingInspectorConfig::from_geth_config(&opts)
+    });
+    assert_eq!(
+        steps.iter().map(|step| step.op).collect::<Vec<_>>(),
+        [OpCode::MSTORE, OpCode::SSTORE]
+    );
+    assert!(steps.iter().all(|step| step.memory.is_some()));
+
+    let opts = GethDefaultTracingOptions::default().disable_memory();
+    let steps = trace(TracingInspectorConfig {
+        record_opcodes_filter: Some(filter),
+        ..TracingInspectorConfig::from_geth_config(&opts)
+    });
+    assert_eq!(steps.len(), 2);
+    assert!(steps.iter().all(|step| step.memory.is_none()));
+}
+
+#[test]
+fn test_geth_struct_log_limit() {
+    // PUSH1 0x03 JUMPDEST PUSH1 0x01 SWAP1 SUB DUP1 PUSH1 0x02 JUMPI STOP
+    let code = hex!("60035b600190038060025700");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code.into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    let gas_used = res.gas_used();
+
+    let builder = insp.into_geth_builder();
+
+    let opts = GethDefaultTracingOptions::default().with_limit(5);
+    let frame = builder.geth_traces(gas_used, Bytes::new(), opts);
+    assert!(!frame.failed);
+    assert_eq!(frame.gas, gas_used);
+    // the first steps in execution order
+    assert_eq!(frame.struct_logs.iter().map(|log| log.pc).collect::<Vec<_>>(), [0, 2, 3, 5, 6]);
+
+    // a limit of zero returns all steps
+    let opts = GethDefaultTracingOptions::default().with_limit(0);
+    let frame = builder.geth_traces(gas_used, Bytes::new(), opts);
+    assert_eq!(frame.struct_logs.len(), 1 + 3 * 7 + 1);
+}
+
+#[test]
+fn test_geth_struct_logs_iter() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 PUSH1 0x00 SSTORE PUSH1 0x02 PUSH1 0x01 SSTORE STOP
+    let callee_code = hex!("600160005560026001550000");
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL POP STOP
+    let caller_code =
+        [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af15000")].concat();
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code.into());
+    evm.set_code(address, caller_code.into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    let gas_used = res.gas_used();
+
+    let builder = insp.into_geth_builder();
+    let opts = GethDefaultTracingOptions::default();
+    let frame = builder.geth_traces(gas_used, Bytes::new(), opts);
+
+    assert_eq!(builder.struct_logs_iter(opts).count(), frame.struct_logs.len());
+    assert!(builder.struct_logs_iter(opts).eq(frame.struct_logs));
+
+    // the limit is applied lazily as well
+    let opts = GethDefaultTracingOptions::default().with_limit(3);
+    assert_eq!(builder.struct_logs_iter(opts).count(), 3);
+}
+
+#[test]
+fn test_geth_struct_log_memory_size() {
+    // PUSH1 0x01 PUSH1 0x20 MSTORE STOP
+    let code = hex!("600160205200");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code.into());
+
+    let opts = GethDefaultTracingOptions::default().enable_memory();
+    let mut insp = TracingInspector::new(TracingInspectorConfig::from_geth_config(&opts));
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    let gas_used = res.gas_used();
+
+    let builder = insp.into_geth_builder();
+    let frame = builder.geth_traces(gas_used, Bytes::new(), opts);
+    assert!(frame.struct_logs.iter().all(|log| log.memory_size.is_none()));
+
+    let builder = builder.with_include_memory_size(true);
+    let frame = builder.geth_traces(gas_used, Bytes::new(), opts);
+    // the step after the MSTORE
+    let stop = frame.struct_logs.last().unwrap();
+    assert_eq!(stop.op, "STOP");
+    assert_eq!(stop.memory_size, Some(64));
+    assert_eq!(stop.memory.as_ref().unwrap().len(), 2);
+    assert_eq!(frame.struct_logs[0].memory_size, Some(0));
+
+    // memory size is not included if memory is disabled
+    let frame = builder.geth_traces(gas_used, Bytes::new(), Default::default());
+    assert!(frame.struct_logs.iter().all(|log| log.memory_size.is_none()));
+}
@@ -1,6 +1,9 @@
 pub mod utils;
 
+mod access_list;
 mod geth;
+mod opcode;
 mod parity;
+mod tracing;
 mod transfer;
 mod writer;
@@ -0,0 +1,31 @@
+//! Opcode inspector tests
+
+use crate::utils::TestEvm;
+use alloy_primitives::{address, bytes, Bytes};
+use revm::interpreter::{opcode, OpCode};
+use revm_inspectors::opcode::OpcodeGasInspector;
+
+#[test]
+fn test_opcode_gas_matches_execution_gas() {
+    // PUSH1 0x01 PUSH1 0x02 ADD PUSH1 0x00 SSTORE PUSH1 0x00 SLOAD POP STOP
+    let code = bytes!("60016002016000556000545000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp = OpcodeGasInspector::new();
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let op = |op| OpCode::new(op).unwrap();
+    assert_eq!(insp.opcode_counts()[&op(opcode::PUSH1)], 4);
+    // cold SSTORE of the slot
+    assert_eq!(insp.opcode_gas()[&op(opcode::SSTORE)], 22_100);
+    // warm SLOAD of the same slot
+    assert_eq!(insp.opcode_gas()[&op(opcode::SLOAD)], 100);
+
+    // without calls and refunds, the gas of all opcodes is the execution gas of the transaction
+    let total = insp.opcode_gas().values().sum::<u64>();
+    assert_eq!(total, res.gas_used() - 21_000);
+}
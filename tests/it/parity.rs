@@ -1,23 +1,36 @@
 //! Parity tests
 
-use crate::utils::{inspect, print_traces};
-use alloy_primitives::{address, hex, Address, U256, U64};
+use crate::utils::{inspect, print_traces, TestEvm};
+use alloy_primitives::{address, bytes, hex, Address, Bytes, B256, U256, U64};
 use alloy_rpc_types::{
-    trace::parity::{Action, CallAction, CallType, SelfdestructAction, TraceType},
+    trace::{
+        geth::CallConfig,
+        parity::{
+            Action, CallAction, CallType, ChangedType, Delta, SelfdestructAction, TraceOutput,
+            TraceType, TransactionTrace, VmTrace,
+        },
+    },
     TransactionInfo,
 };
+use alloy_sol_types::{Revert, SolError};
 use revm::{
     db::{CacheDB, EmptyDB},
     primitives::{
-        AccountInfo, BlobExcessGasAndPrice, BlockEnv, CfgEnv, CfgEnvWithHandlerCfg,
-        EnvWithHandlerCfg, ExecutionResult, HandlerCfg, Output, SpecId, TransactTo, TxEnv,
+        Account, AccountInfo, AccountStatus, BlobExcessGasAndPrice, BlockEnv, Bytecode, CfgEnv,
+        CfgEnvWithHandlerCfg, EnvWithHandlerCfg, EvmStorageSlot, ExecutionResult, HandlerCfg,
+        HashMap, Output, SpecId, TransactTo, TxEnv,
     },
-    DatabaseCommit,
+    DatabaseCommit, DatabaseRef,
 };
 use revm_inspectors::tracing::{
-    parity::populate_state_diff, TracingInspector, TracingInspectorConfig,
+    parity::populate_state_diff, BlockTracer, TracingInspector, TracingInspectorConfig,
 };
-use std::collections::HashSet;
+use std::{cell::Cell, collections::HashSet, convert::Infallible};
+
+#[test]
+fn test_parity_selfdestruct_berlin() {
+    test_parity_selfdestruct(SpecId::BERLIN);
+}
 
 #[test]
 fn test_parity_selfdestruct_london() {
@@ -29,6 +42,69 @@ fn test_parity_selfdestruct_cancun() {
     test_parity_selfdestruct(SpecId::CANCUN);
 }
 
+#[test]
+fn test_parity_create_balance_only_target_homestead() {
+    test_parity_create_balance_only_target(SpecId::HOMESTEAD);
+}
+
+#[test]
+fn test_parity_create_balance_only_target_cancun() {
+    test_parity_create_balance_only_target(SpecId::CANCUN);
+}
+
+// An account that only has a balance doesn't collide with a CREATE, in any spec.
+fn test_parity_create_balance_only_target(spec_id: SpecId) {
+    let caller = address!("00000000000000000000000000000000000000ff");
+    let factory = address!("00000000000000000000000000000000000000aa");
+    let created = factory.create(0);
+    let balance = U256::from(1337);
+
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(created, AccountInfo { balance, ..Default::default() });
+    // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 CREATE POP STOP
+    db.insert_account_info(
+        factory,
+        AccountInfo {
+            code: Some(Bytecode::new_raw(bytes!("600060006000f05000"))),
+            ..Default::default()
+        },
+    );
+
+    let cfg = CfgEnvWithHandlerCfg::new(CfgEnv::default(), HandlerCfg::new(spec_id));
+    let env = EnvWithHandlerCfg::new_with_cfg_env(
+        cfg,
+        BlockEnv::default(),
+        TxEnv {
+            caller,
+            gas_limit: 1000000,
+            transact_to: TransactTo::Call(factory),
+            ..Default::default()
+        },
+    );
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let (res, _) = inspect(&mut db, env, &mut insp).unwrap();
+    assert!(res.result.is_success(), "{res:#?}");
+    // the existing balance is kept
+    assert_eq!(res.state[&created].info.balance, balance);
+
+    let node = &insp.traces().nodes()[1];
+    assert!(node.trace.kind.is_any_create());
+    assert!(node.trace.success, "{node:#?}");
+    assert_eq!(node.trace.address, created);
+
+    let traces = insp
+        .with_transaction_gas_used(res.result.gas_used())
+        .into_parity_builder()
+        .into_localized_transaction_traces(TransactionInfo::default());
+    assert_eq!(traces.len(), 2);
+    assert_eq!(traces[1].trace.error, None);
+    match traces[1].trace.result.as_ref().unwrap() {
+        TraceOutput::Create(output) => assert_eq!(output.address, created),
+        output => panic!("expected create output, got {output:?}"),
+    }
+}
+
 fn test_parity_selfdestruct(spec_id: SpecId) {
     /*
     contract DummySelfDestruct {
@@ -74,7 +150,9 @@ fn test_parity_selfdestruct(spec_id: SpecId) {
     };
     db.commit(res.state);
 
-    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::default_parity().record_selfdestruct_refunds(),
+    );
 
     let env = EnvWithHandlerCfg::new_with_cfg_env(
         cfg,
@@ -102,12 +180,19 @@ fn test_parity_selfdestruct(spec_id: SpecId) {
         assert_eq!(node.trace.address, contract_address);
         assert_eq!(node.trace.selfdestruct_refund_target, expected_target);
         assert_eq!(node.trace.selfdestruct_transferred_value, expected_value);
+        // the selfdestruct refund was removed in London
+        let expected_refund = if spec_id < SpecId::LONDON { 24_000 } else { 0 };
+        assert_eq!(node.trace.selfdestruct_gas_refund, expected_refund);
     }
 
-    let traces = insp
-        .with_transaction_gas_used(res.result.gas_used())
-        .into_parity_builder()
-        .into_localized_transaction_traces(TransactionInfo::default());
+    assert_eq!(insp.traces().spec_id(), Some(spec_id));
+    let geth_builder = insp.clone().into_geth_builder();
+    assert_eq!(geth_builder.spec_id(), Some(spec_id));
+
+    let builder = insp.with_transaction_gas_used(res.result.gas_used()).into_parity_builder();
+    // the builder knows the spec the selfdestruct was recorded with
+    assert_eq!(builder.spec_id(), Some(spec_id));
+    let traces = builder.into_localized_transaction_traces(TransactionInfo::default());
 
     assert_eq!(traces.len(), 2);
     assert_eq!(
@@ -337,3 +422,452 @@ fn test_parity_statediff_blob_commit() {
     assert!(!state_diff.contains_key(&to));
     assert!(state_diff.contains_key(&caller));
 }
+
+#[test]
+fn test_parity_statediff_code_changed() {
+    let proxy = address!("00000000000000000000000000000000000000aa");
+    let other = address!("00000000000000000000000000000000000000bb");
+    // large implementations, only their hashes are compared
+    let old_code = Bytes::from(vec![0x5b; 20_000]);
+    let new_code = Bytes::from([&[0x60, 0x01][..], &[0x5b; 20_000]].concat());
+
+    let mut db = CacheDB::new(EmptyDB::default());
+    let code_info = |code: &Bytes| {
+        let code = Bytecode::new_raw(code.clone());
+        AccountInfo::new(U256::ZERO, 1, code.hash_slow(), code)
+    };
+    let old_info = code_info(&old_code);
+    db.insert_account_info(proxy, old_info.clone());
+    db.insert_account_info(other, old_info.clone());
+
+    let upgraded = Account {
+        info: code_info(&new_code),
+        storage: Default::default(),
+        status: AccountStatus::Touched,
+    };
+    let storage_only = Account {
+        info: old_info,
+        storage: HashMap::from_iter([(
+            U256::from(1),
+            EvmStorageSlot::new_changed(U256::ZERO, U256::from(2)),
+        )]),
+        status: AccountStatus::Touched,
+    };
+    let state: HashMap<_, _> = [(proxy, upgraded), (other, storage_only)].into_iter().collect();
+
+    let mut state_diff = Default::default();
+    populate_state_diff(&mut state_diff, &db, state.iter()).unwrap();
+
+    assert_eq!(
+        state_diff[&proxy].code,
+        Delta::Changed(ChangedType { from: old_code, to: new_code })
+    );
+    assert_eq!(state_diff[&other].code, Delta::Unchanged);
+    assert_eq!(state_diff[&other].storage.len(), 1);
+}
+
+#[test]
+fn test_parity_vm_trace_idx() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 STOP
+    let callee_code = bytes!("600100");
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL STOP
+    let caller_code =
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af100");
+    let caller = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(caller, caller_code);
+
+    let mut insp =
+        TracingInspector::new(TracingInspectorConfig::from_parity_config(&HashSet::from([
+            TraceType::VmTrace,
+        ])));
+    let res = evm.call(caller, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let vm_trace = insp.into_parity_builder().vm_trace();
+    let idxs = vm_trace.ops.iter().map(|op| op.idx.as_deref().unwrap()).collect::<Vec<_>>();
+    assert_eq!(idxs, ["0", "1", "2", "3", "4", "5", "6", "7", "8"]);
+
+    let call = &vm_trace.ops[7];
+    assert_eq!(call.op.as_deref(), Some("CALL"));
+    let sub = call.sub.as_ref().unwrap();
+    let sub_idxs = sub.ops.iter().map(|op| op.idx.as_deref().unwrap()).collect::<Vec<_>>();
+    assert_eq!(sub_idxs, ["7-0", "7-1"]);
+}
+
+#[test]
+fn test_parity_only_addresses() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let router = address!("00000000000000000000000000000000000000bb");
+    let target = address!("00000000000000000000000000000000000000cc");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, [call(router), vec![0x00]].concat().into());
+    evm.set_code(router, [call(target), vec![0x00]].concat().into());
+    // PUSH1 0x01 STOP
+    evm.set_code(target, bytes!("600100"));
+
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::default_parity().with_only_addresses([target]),
+    );
+    let res = evm.call(root, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let traces = insp.into_parity_builder().into_transaction_traces();
+    assert_eq!(traces.len(), 2);
+    assert_eq!(traces[0].subtraces, 1);
+    assert_eq!(traces[1].trace_address, vec![0]);
+    match &traces[1].action {
+        Action::Call(call) => {
+            assert_eq!(call.from, router);
+            assert_eq!(call.to, target);
+        }
+        action => panic!("unexpected action: {action:?}"),
+    }
+}
+
+#[test]
+fn test_parity_include_precompiles() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let target = address!("00000000000000000000000000000000000000bb");
+    let sha256 = address!("0000000000000000000000000000000000000002");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, [call(sha256), call(target), vec![0x00]].concat().into());
+    evm.set_code(target, [call(sha256), vec![0x00]].concat().into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(root, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let builder = insp.into_parity_builder();
+
+    // precompiles are excluded by default
+    let traces = builder.clone().into_transaction_traces();
+    assert_eq!(traces.len(), 2);
+    assert_eq!(traces[0].subtraces, 1);
+    assert_eq!(traces[1].trace_address, vec![0]);
+    assert_eq!(traces[1].subtraces, 0);
+
+    let builder = builder.with_include_precompiles(true);
+    let (traces, _, _) = builder.clone().into_trace_type_traces(&HashSet::from([TraceType::Trace]));
+    let traces = traces.unwrap();
+    assert_eq!(traces, builder.into_transaction_traces());
+
+    assert_eq!(traces.len(), 4);
+    assert_eq!(traces[0].subtraces, 2);
+    assert_eq!(traces[2].subtraces, 1);
+    let expected = [(root, sha256, vec![0]), (root, target, vec![1]), (target, sha256, vec![1, 0])];
+    for (trace, (from, to, trace_address)) in traces[1..].iter().zip(expected) {
+        match &trace.action {
+            Action::Call(call) => {
+                assert_eq!(call.from, from);
+                assert_eq!(call.to, to);
+            }
+            action => panic!("unexpected action: {action:?}"),
+        }
+        assert_eq!(trace.trace_address, trace_address);
+    }
+}
+
+#[test]
+fn test_parity_filtered() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let first = address!("00000000000000000000000000000000000000bb");
+    let second = address!("00000000000000000000000000000000000000cc");
+    let target = address!("00000000000000000000000000000000000000dd");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, [call(first), call(second), vec![0x00]].concat().into());
+    evm.set_code(first, [call(target), vec![0x00]].concat().into());
+    evm.set_code(second, [call(target), vec![0x00]].concat().into());
+    // PUSH1 0x01 STOP
+    evm.set_code(target, bytes!("600100"));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(root, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let builder = insp.into_parity_builder();
+    let calls = |traces: Vec<TransactionTrace>| {
+        traces
+            .into_iter()
+            .map(|trace| match trace.action {
+                Action::Call(call) => (call.from, call.to, trace.trace_address),
+                action => panic!("unexpected action: {action:?}"),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(builder.clone().filtered(None, None), builder.clone().into_transaction_traces());
+    assert_eq!(
+        calls(builder.clone().filtered(Some(&HashSet::from([second])), None)),
+        vec![(second, target, vec![1, 0])]
+    );
+    assert_eq!(
+        calls(builder.clone().filtered(None, Some(&HashSet::from([target])))),
+        vec![(first, target, vec![0, 0]), (second, target, vec![1, 0])]
+    );
+    assert_eq!(
+        calls(builder.filtered(Some(&HashSet::from([root])), Some(&HashSet::from([second])))),
+        vec![(root, second, vec![1])]
+    );
+}
+
+#[test]
+fn test_parity_omit_empty_call_outputs() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let target = address!("00000000000000000000000000000000000000bb");
+
+    // PUSH1 0x00 (x5) PUSH20 <target> GAS CALL POP STOP
+    let code =
+        [&hex!("60006000600060006000")[..], &[0x73], &target[..], &hex!("5af15000")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, code.into());
+    // PUSH1 0x01 STOP
+    evm.set_code(target, bytes!("600100"));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(root, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let builder = insp.into_parity_builder();
+
+    let traces = builder.clone().into_transaction_traces();
+    assert_eq!(traces.len(), 2);
+    for trace in &traces {
+        match &trace.result {
+            Some(TraceOutput::Call(out)) => assert!(out.output.is_empty()),
+            result => panic!("unexpected result: {result:?}"),
+        }
+    }
+
+    let builder = builder.with_omit_empty_call_outputs(true);
+    let (traces, _, _) = builder.clone().into_trace_type_traces(&HashSet::from([TraceType::Trace]));
+    let traces = traces.unwrap();
+    assert_eq!(traces, builder.into_transaction_traces());
+    assert_eq!(traces.len(), 2);
+    assert!(traces.iter().all(|trace| trace.result.is_none()));
+}
+
+#[test]
+fn test_parity_include_revert_reasons() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let target = address!("00000000000000000000000000000000000000bb");
+
+    // PUSH1 0x00 (x5) PUSH20 <target> GAS CALL POP STOP
+    let code =
+        [&hex!("60006000600060006000")[..], &[0x73], &target[..], &hex!("5af15000")].concat();
+    // `require(false, "msg")`
+    let revert = Revert::from("msg").abi_encode();
+    assert_eq!(revert.len(), 0x64);
+    // PUSH1 0x64 PUSH1 0x0c PUSH1 0x00 CODECOPY PUSH1 0x64 PUSH1 0x00 REVERT <revert>
+    let target_code = [&hex!("6064600c60003960646000fd")[..], &revert].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, code.into());
+    evm.set_code(target, target_code.into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(root, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let builder = insp.into_parity_builder();
+
+    let traces = builder.clone().into_transaction_traces();
+    assert_eq!(traces.len(), 2);
+    assert_eq!(traces[0].error, None);
+    assert_eq!(traces[1].error.as_deref(), Some("Reverted"));
+
+    let builder = builder.with_include_revert_reasons(true);
+    let (traces, _, _) = builder.clone().into_trace_type_traces(&HashSet::from([TraceType::Trace]));
+    let traces = traces.unwrap();
+    assert_eq!(traces, builder.into_transaction_traces());
+    assert_eq!(traces[0].error, None);
+    assert_eq!(traces[1].error.as_deref(), Some("Reverted: msg"));
+}
+
+#[test]
+fn test_parity_vm_trace_code() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let a = address!("00000000000000000000000000000000000000bb");
+    let b = address!("00000000000000000000000000000000000000cc");
+    let c = address!("00000000000000000000000000000000000000dd");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    // root calls a, which calls b, and then calls c
+    let root_code = Bytes::from([call(a), call(c), vec![0x00]].concat());
+    let a_code = Bytes::from([call(b), vec![0x00]].concat());
+    // PUSH1 0x01 STOP
+    let b_code = bytes!("600100");
+    // PUSH1 0x02 STOP
+    let c_code = bytes!("600200");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, root_code.clone());
+    evm.set_code(a, a_code.clone());
+    evm.set_code(b, b_code.clone());
+    evm.set_code(c, c_code.clone());
+    evm.env.tx.transact_to = TransactTo::Call(root);
+
+    let trace_types = HashSet::from([TraceType::VmTrace]);
+    let mut insp = TracingInspector::new(TracingInspectorConfig::from_parity_config(&trace_types));
+    let (res, _) = evm.inspect(&mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    let vm_trace = insp
+        .into_parity_builder()
+        .into_trace_results_with_state(&res, &trace_types, &evm.db)
+        .unwrap()
+        .vm_trace
+        .unwrap();
+
+    let subs =
+        |trace: &VmTrace| trace.ops.iter().filter_map(|op| op.sub.clone()).collect::<Vec<_>>();
+
+    assert_eq!(vm_trace.code, root_code);
+    let root_subs = subs(&vm_trace);
+    assert_eq!(root_subs.len(), 2);
+    assert_eq!(root_subs[0].code, a_code);
+    assert_eq!(root_subs[1].code, c_code);
+
+    let a_subs = subs(&root_subs[0]);
+    assert_eq!(a_subs.len(), 1);
+    assert_eq!(a_subs[0].code, b_code);
+    assert!(subs(&a_subs[0]).is_empty());
+}
+
+#[test]
+fn test_parity_vm_trace_code_fetched_once() {
+    /// Counts the code lookups of the wrapped database.
+    struct CountingDb<'a> {
+        db: &'a CacheDB<EmptyDB>,
+        code_lookups: Cell<usize>,
+    }
+
+    impl DatabaseRef for CountingDb<'_> {
+        type Error = Infallible;
+
+        fn basic_ref(&self, address: Address) -> Result<Option<AccountInfo>, Self::Error> {
+            self.db.basic_ref(address)
+        }
+
+        fn code_by_hash_ref(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+            self.code_lookups.set(self.code_lookups.get() + 1);
+            self.db.code_by_hash_ref(code_hash)
+        }
+
+        fn storage_ref(&self, address: Address, index: U256) -> Result<U256, Self::Error> {
+            self.db.storage_ref(address, index)
+        }
+
+        fn block_hash_ref(&self, number: U256) -> Result<B256, Self::Error> {
+            self.db.block_hash_ref(number)
+        }
+    }
+
+    let root = address!("00000000000000000000000000000000000000aa");
+    let callee = address!("00000000000000000000000000000000000000bb");
+
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL POP
+    let call = [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af150")].concat();
+    // root calls the callee three times
+    let root_code = Bytes::from([&call[..], &call, &call, &[0x00]].concat());
+    // PUSH1 0x01 STOP
+    let callee_code = bytes!("600100");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, root_code.clone());
+    evm.set_code(callee, callee_code.clone());
+
+    let trace_types = HashSet::from([TraceType::VmTrace]);
+    let mut insp = TracingInspector::new(TracingInspectorConfig::from_parity_config(&trace_types));
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let db = CountingDb { db: &evm.db, code_lookups: Cell::new(0) };
+    let vm_trace = insp.into_parity_builder().vm_trace_with_code(&db).unwrap();
+    // one lookup per unique code hash
+    assert_eq!(db.code_lookups.get(), 2);
+
+    assert_eq!(vm_trace.code, root_code);
+    let subs = vm_trace.ops.iter().filter_map(|op| op.sub.as_ref()).collect::<Vec<_>>();
+    assert_eq!(subs.len(), 3);
+    assert!(subs.iter().all(|sub| sub.code == callee_code));
+}
+
+#[test]
+fn test_block_tracer() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    let address = address!("00000000000000000000000000000000000000aa");
+    // CALL <callee>, POP the result
+    let code = [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af150")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, bytes!("00"));
+    evm.set_code(address, code.into());
+
+    let txs = (0..3u64)
+        .map(|index| {
+            let tx = TxEnv {
+                caller: Address::with_last_byte(index as u8 + 1),
+                gas_limit: 1_000_000,
+                transact_to: TransactTo::Call(address),
+                ..Default::default()
+            };
+            let info = TransactionInfo {
+                hash: Some(B256::with_last_byte(index as u8 + 1)),
+                index: Some(index),
+                ..Default::default()
+            };
+            (tx, info)
+        })
+        .collect::<Vec<_>>();
+
+    let mut tracer = BlockTracer::new(TracingInspectorConfig::default_parity());
+    let traces =
+        tracer.trace_block_parity(&mut evm.db.clone(), evm.env.clone(), txs.clone()).unwrap();
+
+    // every tx has the root call and the call to the callee
+    assert_eq!(traces.len(), 6);
+    for (i, trace) in traces.iter().enumerate() {
+        let index = i as u64 / 2;
+        assert_eq!(trace.transaction_position, Some(index));
+        assert_eq!(trace.transaction_hash, Some(B256::with_last_byte(index as u8 + 1)));
+        assert_eq!(trace.trace.trace_address.len(), i % 2);
+    }
+
+    let mut tracer = BlockTracer::new(TracingInspectorConfig::default_geth());
+    let results =
+        tracer.trace_block_geth(&mut evm.db, evm.env.clone(), txs, CallConfig::default()).unwrap();
+    assert_eq!(results.len(), 3);
+    for (i, result) in results.iter().enumerate() {
+        assert_eq!(result.tx_hash(), Some(B256::with_last_byte(i as u8 + 1)));
+        let frame = result.success().unwrap().clone().try_into_call_frame().unwrap();
+        assert_eq!(frame.from, Address::with_last_byte(i as u8 + 1));
+        assert_eq!(frame.calls.len(), 1);
+    }
+}
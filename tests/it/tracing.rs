@@ -0,0 +1,1520 @@
+//! Tracing inspector tests
+
+use crate::utils::TestEvm;
+use alloy_primitives::{address, bytes, hex, keccak256, Address, Bytes, B256, U256};
+use alloy_rpc_types::trace::geth::GethDefaultTracingOptions;
+use alloy_sol_types::{Revert, SolError};
+use revm::interpreter::{opcode, InstructionResult, OpCode};
+use revm_inspectors::tracing::{
+    types::{CallKind, CalldataRead, StorageChange, StorageChangeReason},
+    BigramInspector, FrameCallbackInspector, OpcodeCountInspector, StackSnapshotType,
+    TracingInspector, TracingInspectorConfig,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+#[test]
+fn test_record_calldata_reads() {
+    // PUSH1 0x04 PUSH1 0x02 PUSH1 0x00 CALLDATACOPY PUSH1 0x00 CALLDATALOAD STOP
+    let code = bytes!("600460026000376000350000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp =
+        TracingInspector::new(TracingInspectorConfig::default_geth().record_calldata_reads());
+    let res = evm.call(address, bytes!("deadbeefcafe"), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let steps = &insp.traces().nodes()[0].trace.steps;
+    let copy = steps.iter().find(|step| step.op.get() == opcode::CALLDATACOPY).unwrap();
+    assert_eq!(
+        copy.calldata_read,
+        Some(CalldataRead { offset: U256::from(2), len: U256::from(4) })
+    );
+    let load = steps.iter().find(|step| step.op.get() == opcode::CALLDATALOAD).unwrap();
+    assert_eq!(load.calldata_read, Some(CalldataRead { offset: U256::ZERO, len: U256::from(32) }));
+    assert!(steps
+        .iter()
+        .filter(|step| !matches!(step.op.get(), opcode::CALLDATACOPY | opcode::CALLDATALOAD))
+        .all(|step| step.calldata_read.is_none()));
+}
+
+#[test]
+fn test_storage_reads_before_writes() {
+    // PUSH1 0x00 SLOAD POP
+    // PUSH1 0x01 PUSH1 0x01 SSTORE
+    // PUSH1 0x01 SLOAD POP
+    // PUSH1 0x01 PUSH1 0x00 SSTORE
+    // STOP
+    let code = bytes!("6000545060016001556001545060016000550000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp =
+        TracingInspector::new(TracingInspectorConfig::default_geth().set_state_diffs(true));
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    // slot 0 is read and then written, slot 1 is written and then read
+    assert_eq!(
+        insp.traces().storage_reads_before_writes(),
+        HashMap::from([(address, HashSet::from([U256::ZERO]))])
+    );
+}
+
+#[test]
+fn test_storage_reads_before_writes_access_list() {
+    // PUSH1 0x00 SLOAD POP PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    let code = bytes!("60005450600160005500");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+    evm.env.tx.access_list = vec![(address, vec![U256::ZERO])];
+
+    // the warm slot is read from the stack
+    let mut insp =
+        TracingInspector::new(TracingInspectorConfig::default_geth().set_state_diffs(true));
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    assert_eq!(
+        insp.traces().storage_reads_before_writes(),
+        HashMap::from([(address, HashSet::from([U256::ZERO]))])
+    );
+
+    // without stack snapshots the read of the warm slot is not recorded
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::default_geth()
+            .set_state_diffs(true)
+            .set_stack_snapshots(StackSnapshotType::None),
+    );
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    assert!(insp.traces().storage_reads_before_writes().is_empty());
+}
+
+#[test]
+fn test_call_origin() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 STOP
+    let callee_code = bytes!("600100");
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL STOP
+    let caller_code =
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af100");
+    let address = address!("00000000000000000000000000000000000000aa");
+    let origin = address!("00000000000000000000000000000000000000cc");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+    evm.env.tx.caller = origin;
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(nodes[1].trace.caller, address);
+    assert!(nodes.iter().all(|node| node.trace.origin == origin));
+}
+
+#[test]
+fn test_max_steps() {
+    // JUMPDEST PUSH1 0x00 JUMP
+    let code = bytes!("5b600056");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+    evm.env.tx.gas_limit = 1_000_000;
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth().max_steps(10_000));
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    // runs out of gas
+    assert!(!res.is_success());
+
+    assert!(insp.steps_truncated());
+    assert_eq!(insp.traces().nodes()[0].trace.steps.len(), 10_000);
+    assert_eq!(insp.traces().nodes()[0].trace.gas_used, 1_000_000 - 21_000);
+
+    let builder = insp.into_geth_builder();
+    assert!(builder.steps_truncated());
+    let frame = builder.geth_traces(1_000_000, Bytes::new(), Default::default());
+    assert_eq!(frame.struct_logs.len(), 10_000);
+}
+
+#[test]
+fn test_interpreter_gas_limit() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 STOP
+    let callee_code = bytes!("600100");
+    // PUSH1 0x00 (x4) PUSH1 0x01 PUSH20 <callee> PUSH2 0x2710 CALL STOP
+    let caller_code =
+        bytes!("600060006000600060017300000000000000000000000000000000000000bb612710f100");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+    evm.db.accounts.get_mut(&address).unwrap().info.balance = U256::from(1);
+    evm.env.tx.gas_limit = 1_000_000;
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+
+    // the root call's interpreter doesn't get the intrinsic gas
+    assert_eq!(nodes[0].trace.gas_limit, 1_000_000);
+    assert_eq!(nodes[0].trace.interpreter_gas_limit, 1_000_000 - 21_000);
+    assert_eq!(nodes[0].trace.forwarded_gas_limit(), 1_000_000 - 21_000);
+
+    // the value call's interpreter gets the stipend on top of the forwarded gas
+    assert_eq!(nodes[1].trace.interpreter_gas_limit, 10_000 + 2_300);
+    assert_eq!(nodes[1].trace.forwarded_gas_limit(), 10_000);
+}
+
+#[test]
+fn test_call_stipend() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 STOP
+    let callee_code = bytes!("600100");
+    // like solidity's `transfer`: PUSH1 0x00 (x4) PUSH1 0x01 PUSH20 <callee> PUSH1 0x00 CALL STOP
+    let caller_code =
+        bytes!("600060006000600060017300000000000000000000000000000000000000bb6000f100");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+    evm.db.accounts.get_mut(&address).unwrap().info.balance = U256::from(1);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+
+    assert!(!nodes[0].trace.has_call_stipend());
+
+    // no gas was forwarded, the callee only runs on the stipend
+    assert!(nodes[1].trace.has_call_stipend());
+    assert_eq!(nodes[1].trace.interpreter_gas_limit, 2_300);
+    assert_eq!(nodes[1].trace.forwarded_gas_limit(), 0);
+}
+
+#[test]
+fn test_init_code_hash() {
+    let factory = address!("00000000000000000000000000000000000000aa");
+    // CREATE2 with init code that deploys `PUSH1 0x01 STOP`, then with init code that reverts:
+    // PUSH12 <init code> PUSH1 0x00 MSTORE PUSH1 0x00 PUSH1 0x0c PUSH1 0x14 PUSH1 0x00 CREATE2 POP
+    // PUSH5 <init code> PUSH1 0x00 MSTORE PUSH1 0x01 PUSH1 0x05 PUSH1 0x1b PUSH1 0x00 CREATE2 POP
+    // STOP
+    let code = bytes!(
+        "6b626001006000526003601df36000526000600c60146000f550"
+        "6460006000fd60005260016005601b6000f550"
+        "00"
+    );
+
+    let mut evm = TestEvm::new();
+    evm.set_code(factory, code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(factory, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[0].trace.init_code_hash, None);
+
+    let created = &nodes[1].trace;
+    assert!(created.success);
+    assert_eq!(created.output, bytes!("600100"));
+    assert_eq!(created.init_code_hash, Some(keccak256(&created.output)));
+
+    // failed creates don't deploy any code
+    assert!(!nodes[2].trace.success);
+    assert_eq!(nodes[2].trace.init_code_hash, None);
+}
+
+#[test]
+fn test_create2_salt() {
+    let factory = address!("00000000000000000000000000000000000000aa");
+    // CREATE2 with salt 0x2a and CREATE with init code that deploys `PUSH1 0x01 STOP`:
+    // PUSH12 <init code> PUSH1 0x00 MSTORE
+    // PUSH1 0x2a PUSH1 0x0c PUSH1 0x14 PUSH1 0x00 CREATE2 POP
+    // PUSH1 0x0c PUSH1 0x14 PUSH1 0x00 CREATE POP STOP
+    let code = bytes!(
+        "6b626001006000526003601df3600052"
+        "602a600c60146000f550"
+        "600c60146000f05000"
+    );
+
+    let mut evm = TestEvm::new();
+    evm.set_code(factory, code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(factory, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[0].create2_salt(), None);
+
+    let create2 = &nodes[1];
+    assert_eq!(create2.kind(), CallKind::Create2);
+    assert_eq!(create2.create2_salt(), Some(U256::from(0x2a)));
+    // the created address can be recomputed from the salt
+    let salt = create2.create2_salt().unwrap().to_be_bytes::<32>();
+    assert_eq!(create2.trace.address, factory.create2_from_code(salt, &create2.trace.data));
+
+    // plain creates have no salt
+    assert_eq!(nodes[2].kind(), CallKind::Create);
+    assert_eq!(nodes[2].create2_salt(), None);
+}
+
+#[test]
+fn test_call_stipend_forwarded_gas() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 STOP
+    let callee_code = bytes!("600100");
+    // PUSH1 0x00 (x4) PUSH1 0x01 PUSH20 <callee> PUSH2 0x2710 CALL STOP
+    let caller_code =
+        bytes!("600060006000600060017300000000000000000000000000000000000000bb612710f100");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+    evm.db.accounts.get_mut(&address).unwrap().info.balance = U256::from(1);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let trace = &insp.traces().nodes()[1].trace;
+    assert!(trace.has_call_stipend());
+    assert_eq!(trace.forwarded_gas_limit(), 10_000);
+    // the callee starts with the forwarded gas and the stipend
+    assert_eq!(trace.interpreter_gas_limit, 12_300);
+    assert_eq!(trace.gas_limit, 12_300);
+    assert_eq!(trace.steps[0].gas_remaining, 12_300);
+}
+
+#[test]
+fn test_caller_gas_remaining() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x00 (x5) PUSH20 <callee> PUSH4 0xffffffff CALL STOP
+    let caller_code =
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb63fffffffff100");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, bytes!("600100"));
+    evm.set_code(address, caller_code.clone());
+
+    evm.env.tx.gas_limit = 100_000;
+
+    let config = TracingInspectorConfig::default_geth().record_caller_gas();
+    let mut insp = TracingInspector::new(config);
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes[0].trace.caller_gas_remaining, None);
+
+    // the gas left before the CALL, the callee is cold
+    let remaining = nodes[0].trace.steps[7].gas_remaining;
+    assert_eq!(nodes[1].trace.caller_gas_remaining, Some(remaining));
+    // the requested gas is capped at 63/64 of the gas left after the call costs
+    let available = remaining - 2_600;
+    assert_eq!(nodes[1].trace.forwarded_gas_limit(), available - available / 64);
+
+    // not recorded by default
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, bytes!("600100"));
+    evm.set_code(address, caller_code);
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert_eq!(insp.traces().nodes()[1].trace.caller_gas_remaining, None);
+}
+
+#[cfg(feature = "msgpack")]
+#[test]
+fn test_msgpack_roundtrip() {
+    use revm_inspectors::tracing::CallTraceArena;
+
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x00 SLOAD POP PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    let callee_code = bytes!("600054506001600055");
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL STOP
+    let caller_code =
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af100");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::all().with_state_diffs());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let arena = insp.into_traces();
+    let encoded = arena.to_msgpack().unwrap();
+    assert!(encoded.len() < serde_json::to_vec(&arena).unwrap().len());
+    assert_eq!(CallTraceArena::from_msgpack(&encoded).unwrap(), arena);
+}
+
+#[cfg(all(feature = "binary", feature = "serde"))]
+#[test]
+fn test_binary_roundtrip() {
+    use revm_inspectors::tracing::{BinaryDecodeError, CallTraceArena};
+
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x00 SLOAD POP PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    let callee_code = bytes!("600054506001600055");
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL STOP
+    let caller_code =
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af100");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::all().with_state_diffs());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let arena = insp.into_traces();
+    let encoded = arena.to_binary();
+    assert!(encoded.len() < serde_json::to_vec(&arena).unwrap().len());
+    assert_eq!(CallTraceArena::from_binary(&encoded).unwrap(), arena);
+
+    let mut bad_version = encoded.clone();
+    bad_version[4] = 0xee;
+    assert_eq!(
+        CallTraceArena::from_binary(&bad_version),
+        Err(BinaryDecodeError::UnsupportedVersion(0xee))
+    );
+    assert_eq!(
+        CallTraceArena::from_binary(&encoded[..encoded.len() - 1]),
+        Err(BinaryDecodeError::UnexpectedEof)
+    );
+    assert_eq!(CallTraceArena::from_binary(b"JSON"), Err(BinaryDecodeError::InvalidMagic));
+}
+
+#[test]
+fn test_subtree_opcode_counts() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 STOP
+    let callee_code = bytes!("600100");
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL STOP
+    let caller_code =
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af100");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let traces = insp.traces();
+    let global = traces.subtree_opcode_counts(0);
+    assert_eq!(
+        global,
+        HashMap::from([
+            (OpCode::PUSH1, 6),
+            (OpCode::PUSH20, 1),
+            (OpCode::GAS, 1),
+            (OpCode::CALL, 1),
+            (OpCode::STOP, 2),
+        ])
+    );
+
+    let subtree = traces.subtree_opcode_counts(1);
+    assert_eq!(subtree, HashMap::from([(OpCode::PUSH1, 1), (OpCode::STOP, 1)]));
+}
+
+#[test]
+fn test_descendant_count() {
+    let call = |addr: Address| {
+        // PUSH1 0x00 (x5) PUSH20 <addr> GAS CALL POP
+        [&hex!("60006000600060006000")[..], &[0x73], &addr[..], &hex!("5af150")].concat()
+    };
+    let root = address!("00000000000000000000000000000000000000aa");
+    let first = address!("00000000000000000000000000000000000000bb");
+    let nested = address!("00000000000000000000000000000000000000cc");
+    let second = address!("00000000000000000000000000000000000000dd");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, [call(first), call(second), vec![0x00]].concat().into());
+    evm.set_code(first, [call(nested), vec![0x00]].concat().into());
+    // PUSH1 0x01 PUSH1 0x00 MSTORE PUSH1 0x20 PUSH1 0x00 RETURN
+    evm.set_code(nested, bytes!("600160005260206000f3"));
+    evm.set_code(second, bytes!("00"));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(root, bytes!("deadbeef"), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let traces = insp.traces();
+    assert_eq!(traces.nodes().len(), 4);
+    assert_eq!(traces.descendant_count(0), 3);
+    assert_eq!(traces.descendant_count(1), 1);
+    assert_eq!(traces.descendant_count(2), 0);
+    assert_eq!(traces.descendant_count(3), 0);
+
+    let nodes = traces.nodes();
+    assert_eq!(nodes[0].input_len(), 4);
+    assert_eq!(nodes[0].output_len(), 0);
+    assert_eq!(nodes[2].trace.address, nested);
+    assert_eq!(nodes[2].input_len(), 0);
+    assert_eq!(nodes[2].output_len(), 32);
+}
+
+#[test]
+fn test_stack_snapshot_top() {
+    let address = address!("00000000000000000000000000000000000000aa");
+    // PUSH1 0x01 PUSH1 0x02 PUSH1 0x03 PUSH1 0x04 PUSH1 0x05 STOP
+    let mut evm = TestEvm::new();
+    evm.set_code(address, bytes!("6001600260036004600500"));
+
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::default_geth().set_stack_snapshots(StackSnapshotType::Top(3)),
+    );
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let steps = &insp.traces().nodes()[0].trace.steps;
+    assert_eq!(steps.len(), 6);
+    assert!(steps.iter().all(|step| step.stack.as_ref().unwrap().len() <= 3));
+    assert_eq!(steps[2].stack, Some(vec![U256::from(1), U256::from(2)]));
+    // the top of the stack is the last item
+    assert_eq!(steps[5].stack, Some(vec![U256::from(3), U256::from(4), U256::from(5)]));
+}
+
+#[test]
+fn test_transient_storage_changes() {
+    let address = address!("00000000000000000000000000000000000000aa");
+    // PUSH1 0x2a PUSH1 0x01 TSTORE PUSH1 0x01 TLOAD POP
+    // PUSH1 0x01 SLOAD POP PUSH1 0x01 PUSH1 0x01 SSTORE STOP
+    let mut evm = TestEvm::new();
+    evm.set_code(address, bytes!("602a60015d60015c5060015450600160015500"));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth().with_state_diffs());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let steps = &insp.traces().nodes()[0].trace.steps;
+    assert_eq!(steps[2].op, OpCode::TSTORE);
+    assert_eq!(
+        steps[2].storage_change,
+        Some(StorageChange {
+            key: U256::from(1),
+            value: U256::from(0x2a),
+            had_value: Some(U256::ZERO),
+            reason: StorageChangeReason::TSTORE,
+        })
+    );
+    assert_eq!(steps[4].op, OpCode::TLOAD);
+    assert_eq!(
+        steps[4].storage_change,
+        Some(StorageChange {
+            key: U256::from(1),
+            value: U256::from(0x2a),
+            had_value: None,
+            reason: StorageChangeReason::TLOAD,
+        })
+    );
+
+    // a transient slot does not shadow the contract storage slot with the same key
+    assert_eq!(
+        insp.traces().storage_reads_before_writes(),
+        HashMap::from([(address, HashSet::from([U256::from(1)]))])
+    );
+
+    // the transient storage is reported for the transient storage steps only, separate from the
+    // contract storage
+    let trace = insp.into_geth_builder().geth_traces(
+        res.gas_used(),
+        Bytes::new(),
+        GethDefaultTracingOptions::default(),
+    );
+    let slot = |value: u8| BTreeMap::from([(B256::with_last_byte(1), B256::with_last_byte(value))]);
+    let storage: Vec<_> = trace.struct_logs.iter().map(|log| log.storage.clone()).collect();
+    assert_eq!(storage[2], Some(slot(0x2a)));
+    assert_eq!(storage[4], Some(slot(0x2a)));
+    assert_eq!(storage[7], Some(slot(0)));
+    assert_eq!(storage[11], Some(slot(1)));
+    assert_eq!(storage.iter().flatten().count(), 4);
+}
+
+#[test]
+fn test_step_reverts_call() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x00 PUSH1 0x00 REVERT
+    let callee_code = bytes!("60006000fd");
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL POP STOP
+    let caller_code =
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af15000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+
+    // the caller succeeded
+    assert!(nodes[0].trace.steps.iter().all(|step| !step.reverts_call));
+
+    // only the REVERT step of the callee is marked
+    let steps = &nodes[1].trace.steps;
+    let (last, rest) = steps.split_last().unwrap();
+    assert_eq!(last.op, OpCode::new(opcode::REVERT).unwrap());
+    assert!(last.reverts_call);
+    assert!(rest.iter().all(|step| !step.reverts_call));
+}
+
+#[test]
+fn test_record_gas_refunds() {
+    // PUSH1 0x00 PUSH1 0x00 SSTORE STOP
+    let code = bytes!("600060005500");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+    evm.db.insert_account_storage(address, U256::ZERO, U256::from(1)).unwrap();
+
+    let mut insp =
+        TracingInspector::new(TracingInspectorConfig::none().steps().record_gas_refunds());
+    let res = evm.clone().call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let steps = &insp.traces().nodes()[0].trace.steps;
+    assert_eq!(steps.len(), 4);
+    assert_eq!(steps[2].op, OpCode::new(opcode::SSTORE).unwrap());
+    // clearing the slot refunds gas
+    assert_eq!(steps[2].refund_delta, 4800);
+    assert!(steps.iter().filter(|step| step.op != steps[2].op).all(|step| step.refund_delta == 0));
+
+    // not recorded if disabled
+    let mut insp = TracingInspector::new(TracingInspectorConfig::none().steps());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    assert!(insp.traces().nodes()[0].trace.steps.iter().all(|step| step.refund_delta == 0));
+}
+
+#[test]
+fn test_opcode_counts_by_depth() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x03 JUMPDEST PUSH1 0x01 SWAP1 SUB DUP1 PUSH1 0x02 JUMPI STOP
+    let callee_code = bytes!("60035b600190038060025700");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL POP STOP
+    evm.set_code(
+        address,
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af15000"),
+    );
+
+    let mut insp = OpcodeCountInspector::with_depth_buckets();
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let counts = insp.counts_by_depth().unwrap();
+    assert_eq!(counts.len(), 2);
+    let (root, inner) = (&counts[&1], &counts[&2]);
+
+    let op = |op| OpCode::new(op).unwrap();
+    assert_eq!(root[&op(opcode::CALL)], 1);
+    assert_eq!(root[&op(opcode::PUSH1)], 5);
+    assert!(!root.contains_key(&op(opcode::SUB)));
+
+    // the loop body is executed three times
+    assert_eq!(inner[&op(opcode::JUMPDEST)], 3);
+    assert_eq!(inner[&op(opcode::SUB)], 3);
+    assert_eq!(inner[&op(opcode::JUMPI)], 3);
+    assert!(!inner.contains_key(&op(opcode::CALL)));
+
+    // the total is the sum of all buckets
+    let total = counts.values().flat_map(|counts| counts.values()).sum::<u64>();
+    assert_eq!(total as usize, insp.count());
+}
+
+#[test]
+fn test_keccak_preimages() {
+    // PUSH4 0xdeadbeef PUSH1 0x00 MSTORE PUSH1 0x04 PUSH1 0x1c KECCAK256 POP
+    // PUSH1 0x00 PUSH1 0x00 KECCAK256 POP STOP
+    let code = bytes!("63deadbeef6000526004601c205060006000205000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth().memory_snapshots());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let preimages = insp.traces().keccak_preimages();
+    assert_eq!(
+        preimages,
+        vec![
+            (keccak256(hex!("deadbeef")), Bytes::from_static(&hex!("deadbeef"))),
+            (keccak256([]), Bytes::new()),
+        ]
+    );
+}
+
+#[test]
+fn test_step_capacity_hint() {
+    // JUMPDEST PUSH1 0x00 JUMP
+    let code = bytes!("5b600056");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+    evm.env.tx.gas_limit = 100_000;
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    evm.clone().call(address, Bytes::new(), &mut insp).unwrap();
+    let steps = insp.traces().nodes()[0].trace.steps.len();
+
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::default_geth().with_step_capacity_hint(steps),
+    );
+    evm.call(address, Bytes::new(), &mut insp).unwrap();
+    let trace = &insp.traces().nodes()[0].trace;
+    assert_eq!(trace.steps.len(), steps);
+    // no reallocation happened
+    assert_eq!(trace.steps.capacity(), steps);
+}
+
+#[test]
+fn test_out_of_gas_nodes() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // JUMPDEST PUSH1 0x00 JUMP
+    let callee_code = bytes!("5b600056");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    // PUSH1 0x00 (x5) PUSH20 <callee> PUSH1 0x64 CALL POP STOP
+    evm.set_code(
+        address,
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb6064f15000"),
+    );
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    // the caller handles the failed call
+    assert!(res.is_success());
+
+    let nodes = insp.traces().out_of_gas_nodes().collect::<Vec<_>>();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].idx, 1);
+    assert_eq!(nodes[0].trace.address, callee);
+    assert_eq!(nodes[0].trace.status, InstructionResult::OutOfGas);
+}
+
+#[test]
+fn test_flatten() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let first = address!("00000000000000000000000000000000000000bb");
+    let second = address!("00000000000000000000000000000000000000cc");
+    let nested = address!("00000000000000000000000000000000000000dd");
+    let sha256 = address!("0000000000000000000000000000000000000002");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    // root calls the first contract, which calls sha256 and another contract, then the second
+    evm.set_code(root, [call(first), call(second), vec![0x00]].concat().into());
+    evm.set_code(first, [call(sha256), call(nested), vec![0x00]].concat().into());
+    // PUSH1 0x01 STOP
+    evm.set_code(second, bytes!("600100"));
+    evm.set_code(nested, bytes!("600100"));
+
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::default_parity().set_exclude_precompile_calls(true),
+    );
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let flat = insp.traces().flatten(false);
+    let calls = flat.iter().map(|(path, trace)| (path.clone(), trace.address)).collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![(vec![], root), (vec![0], first), (vec![0, 0], nested), (vec![1], second)]
+    );
+    assert!(flat.iter().all(|(path, trace)| path.len() == trace.depth));
+
+    let flat = insp.traces().flatten(true);
+    let calls = flat.iter().map(|(path, trace)| (path.clone(), trace.address)).collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        vec![
+            (vec![], root),
+            (vec![0], first),
+            (vec![0, 0], sha256),
+            (vec![0, 1], nested),
+            (vec![1], second)
+        ]
+    );
+
+    // the paths match the parity trace addresses
+    let traces = insp
+        .into_parity_builder()
+        .with_include_precompiles(true)
+        .into_localized_transaction_traces(Default::default());
+    let trace_addresses = traces.iter().map(|trace| trace.trace.trace_address.clone());
+    assert!(trace_addresses.eq(calls.into_iter().map(|(path, _)| path)));
+}
+
+#[test]
+fn test_calls_to_protected() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let other = address!("00000000000000000000000000000000000000bb");
+    let predeploy = address!("4200000000000000000000000000000000000015");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    // root calls another contract, which calls the predeploy
+    evm.set_code(root, [call(other), vec![0x00]].concat().into());
+    evm.set_code(other, [call(predeploy), vec![0x00]].concat().into());
+    // PUSH1 0x01 STOP
+    evm.set_code(predeploy, bytes!("600100"));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let traces = insp.traces();
+    let protected = HashSet::from([predeploy]);
+    let nodes = traces.calls_to_protected(&protected).collect::<Vec<_>>();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].trace.address, predeploy);
+    assert_eq!(nodes[0].trace.caller, other);
+    assert_eq!(nodes[0].parent, Some(1));
+
+    assert_eq!(traces.calls_to_protected(&HashSet::new()).count(), 0);
+}
+
+#[test]
+fn test_record_step_durations() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let callee = address!("00000000000000000000000000000000000000bb");
+
+    let mut evm = TestEvm::new();
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL POP STOP
+    evm.set_code(
+        root,
+        [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af15000")]
+            .concat()
+            .into(),
+    );
+    // PUSH1 0x01 PUSH1 0x00 MSTORE STOP
+    evm.set_code(callee, bytes!("600160005200"));
+
+    // not measured by any preset
+    for config in [TracingInspectorConfig::default_geth(), TracingInspectorConfig::all()] {
+        let mut insp = TracingInspector::new(config);
+        let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+        assert!(res.is_success());
+        for node in insp.traces().nodes() {
+            assert!(node.trace.steps.iter().all(|step| step.duration.is_none()));
+            assert_eq!(node.trace.steps_duration(), None);
+        }
+    }
+
+    let mut insp =
+        TracingInspector::new(TracingInspectorConfig::default_geth().record_step_durations());
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+    for node in nodes {
+        assert!(!node.trace.steps.is_empty());
+        assert!(node.trace.steps.iter().all(|step| step.duration.is_some()));
+        let total = node.trace.steps_duration().unwrap();
+        let max = node.trace.steps.iter().filter_map(|step| step.duration).max().unwrap();
+        assert!(total >= max);
+    }
+}
+
+#[test]
+fn test_record_op_counts() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    let callee_code = bytes!("600160005500");
+    let address = address!("00000000000000000000000000000000000000aa");
+    // PUSH1 0x00 (x5) PUSH20 0xbb GAS CALL POP STOP
+    let code = bytes!("600060006000600060007300000000000000000000000000000000000000bb5af15000");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, code);
+
+    // opcodes are counted without recording steps
+    let mut insp = TracingInspector::new(TracingInspectorConfig::none().record_op_counts());
+    let res = evm.clone().call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+    assert!(nodes.iter().all(|node| node.trace.steps.is_empty()));
+    assert_eq!(nodes[0].trace.op_count, 10);
+    assert_eq!(nodes[1].trace.op_count, 4);
+
+    // the counts match the recorded steps
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth().record_op_counts());
+    evm.call(address, Bytes::new(), &mut insp).unwrap();
+    for node in insp.traces().nodes() {
+        assert_eq!(node.trace.op_count, node.trace.steps.len() as u64);
+    }
+}
+
+#[test]
+fn test_bigram_inspector() {
+    // PUSH1 0x01 PUSH1 0x02 PUSH1 0x00 MSTORE POP STOP
+    let code = bytes!("600160026000525000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp = BigramInspector::default();
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let bigrams = insp.bigrams();
+    assert_eq!(bigrams.len(), 4);
+    assert_eq!(bigrams[&(OpCode::PUSH1, OpCode::PUSH1)], 2);
+    assert_eq!(bigrams[&(OpCode::PUSH1, OpCode::MSTORE)], 1);
+    assert_eq!(bigrams[&(OpCode::MSTORE, OpCode::POP)], 1);
+    assert_eq!(bigrams[&(OpCode::POP, OpCode::STOP)], 1);
+
+    assert_eq!(
+        insp.json(),
+        serde_json::json!({
+            "PUSH1-PUSH1": 2,
+            "PUSH1-MSTORE": 1,
+            "MSTORE-POP": 1,
+            "POP-STOP": 1,
+        })
+    );
+}
+
+#[test]
+fn test_node_at_trace_address() {
+    let root = address!("00000000000000000000000000000000000000aa");
+    let target = address!("00000000000000000000000000000000000000bb");
+    let other = address!("00000000000000000000000000000000000000cc");
+    let sha256 = address!("0000000000000000000000000000000000000002");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    evm.set_code(root, [call(sha256), call(target), vec![0x00]].concat().into());
+    evm.set_code(target, [call(sha256), call(other), vec![0x00]].concat().into());
+    evm.set_code(other, bytes!("00"));
+
+    // precompile calls are recorded, but excluded from the call graph
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(root, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    let arena = insp.traces();
+    assert_eq!(arena.nodes().len(), 5);
+
+    let address_at = |path: &[usize]| arena.node_at_trace_address(path).map(|n| n.trace.address);
+    assert_eq!(address_at(&[]), Some(root));
+    assert_eq!(address_at(&[0]), Some(target));
+    assert_eq!(address_at(&[0, 0]), Some(other));
+    assert_eq!(address_at(&[1]), None);
+    assert_eq!(address_at(&[0, 1]), None);
+    assert_eq!(address_at(&[0, 0, 0]), None);
+
+    // all parity trace addresses resolve to their call
+    let traces = insp.clone().into_parity_builder().into_transaction_traces();
+    assert_eq!(traces.len(), 3);
+    for trace in traces {
+        let node = arena.node_at_trace_address(&trace.trace_address).unwrap();
+        assert_eq!(trace.action.as_call().unwrap().to, node.trace.address);
+    }
+}
+
+#[test]
+fn test_memory_gas() {
+    // PUSH1 0x01 PUSH2 0x0400 MSTORE PUSH1 0x00 MLOAD POP PUSH1 0x20 PUSH2 0x0800 RETURN
+    let code = bytes!("600161040052600051506020610800f3");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let trace = &insp.traces().nodes()[0].trace;
+    // MSTORE expands the memory to 33 words, RETURN to 65 words
+    let memory_gas = |words: u64| 3 * words + words * words / 512;
+    assert_eq!(trace.memory_gas, memory_gas(65));
+
+    let mstore = trace.steps.iter().find(|step| step.op == OpCode::MSTORE).unwrap();
+    assert_eq!(mstore.gas_cost, 3 + memory_gas(33));
+}
+
+#[test]
+fn test_max_memory() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x01 PUSH2 0x0400 MSTORE STOP
+    let callee_code = bytes!("600161040052");
+    let address = address!("00000000000000000000000000000000000000aa");
+    // PUSH1 0x01 PUSH1 0x40 MSTORE, CALL <callee>, POP STOP
+    let code = [
+        &hex!("6001604052")[..],
+        &hex!("60006000600060006000"),
+        &[0x73],
+        &callee[..],
+        &hex!("5af15000"),
+    ]
+    .concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, code.into());
+
+    // neither steps nor memory snapshots are recorded
+    let mut insp = TracingInspector::new(TracingInspectorConfig::none().record_max_memory());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+    // the memory of the subcall doesn't count towards the caller's peak
+    assert_eq!(nodes[0].trace.max_memory, 3 * 32);
+    assert_eq!(nodes[1].trace.max_memory, 33 * 32);
+
+    // not recorded by default
+    let mut insp = TracingInspector::new(TracingInspectorConfig::none());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    assert!(insp.traces().nodes().iter().all(|node| node.trace.max_memory == 0));
+}
+
+#[test]
+fn test_static_violations() {
+    let address = address!("00000000000000000000000000000000000000aa");
+    let target = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x00 (x4) PUSH20 <target> GAS STATICCALL POP STOP
+    let code = [&hex!("6000600060006000")[..], &[0x73], &target[..], &hex!("5afa5000")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code.into());
+    // PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    evm.set_code(target, bytes!("600160005500"));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let violations = insp.traces().static_violations().collect::<Vec<_>>();
+    assert_eq!(violations.len(), 1);
+    let (node, step) = violations[0];
+    assert_eq!(node.trace.address, target);
+    assert_eq!(step.op, OpCode::SSTORE);
+    assert_eq!(step.status, InstructionResult::StateChangeDuringStaticCall);
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serde_camel_case() {
+    // PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    let code = bytes!("600160005500");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let node = serde_json::to_value(&insp.traces().nodes()[0]).unwrap();
+    let trace = &node["trace"];
+    assert!(trace.get("gasUsed").is_some());
+    assert!(trace.get("gasLimit").is_some());
+    assert!(trace.get("interpreterGasLimit").is_some());
+    assert!(trace.get("gas_used").is_none());
+
+    let step = &trace["steps"][2];
+    assert_eq!(step["op"], opcode::SSTORE);
+    assert!(step.get("gasRemaining").is_some());
+    assert!(step.get("gasCost").is_some());
+    assert!(step.get("storageChange").is_some());
+    assert!(step.get("gas_cost").is_none());
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_serde_snake_case_compat() {
+    use revm_inspectors::tracing::types::CallTraceNode;
+
+    fn to_snake_case(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(map) => map
+                .into_iter()
+                .map(|(key, value)| {
+                    // enum variants, e.g. of the ordering, are not renamed
+                    if key.starts_with(|c: char| c.is_ascii_uppercase()) {
+                        return (key, to_snake_case(value));
+                    }
+                    let mut snake = String::new();
+                    for c in key.chars() {
+                        if c.is_ascii_uppercase() {
+                            snake.push('_');
+                        }
+                        snake.push(c.to_ascii_lowercase());
+                    }
+                    (snake, to_snake_case(value))
+                })
+                .collect(),
+            serde_json::Value::Array(values) => values.into_iter().map(to_snake_case).collect(),
+            value => value,
+        }
+    }
+
+    // PUSH1 0x01 PUSH1 0x00 SSTORE PUSH1 0x00 PUSH1 0x00 LOG0 STOP
+    let code = bytes!("60016000556000600060a000");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth().with_state_diffs());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    // traces serialized with the snake_case field names of earlier versions can be deserialized
+    let node = &insp.traces().nodes()[0];
+    let old = to_snake_case(serde_json::to_value(node).unwrap());
+    assert!(old["trace"].get("gas_used").is_some());
+    assert!(old["trace"]["steps"][2].get("storage_change").is_some());
+    let decoded: CallTraceNode = serde_json::from_value(old).unwrap();
+    assert_eq!(&decoded, node);
+}
+
+#[test]
+fn test_frame_callback() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    let address = address!("00000000000000000000000000000000000000aa");
+    // CALL <callee>, POP STOP
+    let code =
+        [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af15000")].concat();
+
+    let mut evm = TestEvm::new();
+    // PUSH1 0x00 DUP1 REVERT
+    evm.set_code(callee, bytes!("600080fd"));
+    evm.set_code(address, code.into());
+
+    let mut frames = Vec::new();
+    let mut insp = FrameCallbackInspector::new(
+        TracingInspector::new(TracingInspectorConfig::default_parity()),
+        |node| frames.push(node.clone()),
+    );
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+    let nodes = insp.into_inspector().into_traces().into_nodes();
+
+    // the subcall completes first
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].trace.address, callee);
+    assert!(!frames[0].trace.success);
+    assert_eq!(frames[1].trace.address, address);
+    assert!(frames[1].trace.success);
+    assert_eq!(frames[0], nodes[1]);
+    assert_eq!(frames[1], nodes[0]);
+}
+
+#[test]
+fn test_gas_by_address_exclude_precompiles() {
+    let identity = Address::with_last_byte(4);
+    let address = address!("00000000000000000000000000000000000000aa");
+    // CALL <identity> without input, POP STOP
+    let code =
+        [&hex!("60006000600060006000")[..], &[0x73], &identity[..], &hex!("5af15000")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code.into());
+
+    let mut insp = TracingInspector::new(
+        TracingInspectorConfig::default_parity().set_exclude_precompile_calls(true),
+    );
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let traces = insp.traces();
+    let gas = traces.gas_by_address(false);
+    assert_eq!(gas.len(), 2);
+    // the identity precompile costs 15 gas without input
+    assert_eq!(gas[&identity], 15);
+    assert_eq!(gas.values().sum::<u64>(), traces.nodes()[0].trace.gas_used);
+
+    let without_precompiles = traces.gas_by_address(true);
+    assert_eq!(without_precompiles.len(), 1);
+    assert_eq!(without_precompiles[&address], gas[&address]);
+}
+
+#[test]
+fn test_tenderly_json() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    let address = address!("00000000000000000000000000000000000000aa");
+    // CALL <callee>, POP STOP
+    let code =
+        [&hex!("60006000600060006000")[..], &[0x73], &callee[..], &hex!("5af15000")].concat();
+
+    let mut evm = TestEvm::new();
+    // PUSH1 0x00 DUP1 REVERT
+    evm.set_code(callee, bytes!("600080fd"));
+    evm.set_code(address, code.into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    let json = insp.traces().tenderly_json();
+
+    assert_eq!(json["call_type"], "CALL");
+    assert_eq!(json["from"], evm.env.tx.caller.to_string().to_lowercase());
+    assert_eq!(json["to"], address.to_string().to_lowercase());
+    assert_eq!(json["gas"], nodes[0].trace.gas_limit);
+    assert_eq!(json["gas_used"], nodes[0].trace.gas_used);
+    assert_eq!(json["input"], "0x");
+    assert_eq!(json["value"], "0");
+    assert!(json["error"].is_null());
+
+    let calls = json["calls"].as_array().unwrap();
+    assert_eq!(calls.len(), 1);
+    let call = &calls[0];
+    assert_eq!(call["from"], address.to_string().to_lowercase());
+    assert_eq!(call["to"], callee.to_string().to_lowercase());
+    assert_eq!(call["gas_used"], nodes[1].trace.gas_used);
+    assert_eq!(call["error"], "execution reverted");
+    assert_eq!(call["calls"], serde_json::json!([]));
+}
+
+#[test]
+fn test_revert_path() {
+    let a = address!("00000000000000000000000000000000000000aa");
+    let b = address!("00000000000000000000000000000000000000bb");
+    let c = address!("00000000000000000000000000000000000000cc");
+    // CALL <target>, then revert with the returned data
+    let bubble = |target: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &target[..], &hex!("5af13d600060003e3d6000fd")]
+            .concat()
+    };
+    // PUSH1 0x64 PUSH1 0x0c PUSH1 0x00 CODECOPY PUSH1 0x64 PUSH1 0x00 REVERT <Error("boom")>
+    let revert =
+        [&hex!("6064600c60003960646000fd")[..], &Revert::from("boom").abi_encode()].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(a, bubble(b).into());
+    evm.set_code(b, bubble(c).into());
+    evm.set_code(c, revert.into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(a, hex!("aabbccdd").into(), &mut insp).unwrap();
+    assert!(!res.is_success());
+
+    let traces = insp.traces_mut();
+    let path = traces.revert_path().unwrap();
+    assert_eq!(path.iter().map(|node| node.trace.address).collect::<Vec<_>>(), [a, b, c]);
+
+    traces.apply_labels(&HashMap::from([
+        (a, "A".to_string()),
+        (b, "B".to_string()),
+        (c, "C".to_string()),
+    ]));
+    assert_eq!(
+        traces.revert_path_message().unwrap(),
+        "A.0xaabbccdd() -> B.fallback() -> C.fallback() reverted: boom"
+    );
+}
+
+#[test]
+fn test_revert_path_caught_revert() {
+    let a = address!("00000000000000000000000000000000000000aa");
+    let b = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x64 PUSH1 <offset> PUSH1 0x00 CODECOPY PUSH1 0x64 PUSH1 0x00 REVERT <Error(reason)>
+    let revert = |offset: u8, reason: &str| {
+        [
+            &hex!("6064")[..],
+            &[0x60, offset + 12],
+            &hex!("60003960646000fd"),
+            &Revert::from(reason).abi_encode(),
+        ]
+        .concat()
+    };
+    // PUSH1 0x00 (x5) PUSH20 <b> GAS CALL POP, then revert with a different reason
+    let call = [&hex!("60006000600060006000")[..], &[0x73], &b[..], &hex!("5af150")].concat();
+    let caller = [call.clone(), revert(call.len() as u8, "bang")].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(a, caller.into());
+    evm.set_code(b, revert(0, "boom").into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(a, Bytes::new(), &mut insp).unwrap();
+    assert!(!res.is_success());
+
+    // the caught revert of the subcall is not the origin
+    let traces = insp.traces();
+    assert!(traces.nodes()[1].trace.is_revert());
+    let path = traces.revert_path().unwrap();
+    assert_eq!(path.iter().map(|node| node.trace.address).collect::<Vec<_>>(), [a]);
+    assert!(traces.revert_path_message().unwrap().ends_with("reverted: bang"));
+}
+
+#[test]
+fn test_log_counters_without_recording_logs() {
+    let callee = address!("00000000000000000000000000000000000000bb");
+    // PUSH1 0x07 PUSH1 0x05 PUSH1 0x00 LOG1 STOP
+    let callee_code = bytes!("600760056000a100");
+    // PUSH1 0x03 PUSH1 0x00 LOG0
+    // PUSH1 0x01 PUSH1 0x02 PUSH1 0x20 PUSH1 0x00 LOG2
+    // PUSH1 0x00 (x5) PUSH20 <callee> GAS CALL STOP
+    let caller_code = [
+        &hex!("60036000a0")[..],
+        &hex!("6001600260206000a2"),
+        &hex!("60006000600060006000"),
+        &[0x73],
+        &callee[..],
+        &hex!("5af100"),
+    ]
+    .concat();
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(callee, callee_code);
+    evm.set_code(address, caller_code.into());
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::none());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+    assert!(nodes.iter().all(|node| node.logs.is_empty()));
+    assert_eq!(nodes[0].trace.log_count, 2);
+    assert_eq!(nodes[0].trace.log_data_size, 3 + 32);
+    assert_eq!(nodes[1].trace.log_count, 1);
+    assert_eq!(nodes[1].trace.log_data_size, 5);
+}
+
+#[test]
+fn test_verify_create_addresses() {
+    // the first contract created by a known sender
+    let sender = address!("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+    assert_eq!(sender.create(0), address!("cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d"));
+
+    let factory = address!("00000000000000000000000000000000000000aa");
+    // PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 CREATE PUSH1 0x00 PUSH1 0x00 PUSH1 0x00 CREATE STOP
+    let code = bytes!("600060006000f0600060006000f000");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(factory, code);
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::none().verify_create_addresses());
+    let res = evm.call(factory, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[1].trace.address, factory.create(0));
+    assert_eq!(nodes[2].trace.address, factory.create(1));
+}
+
+#[test]
+fn test_eof_extcall_light_failure() {
+    use revm::primitives::{
+        eof::{EofBody, TypesSection},
+        AccountInfo, Bytecode, SpecId,
+    };
+
+    let caller = address!("00000000000000000000000000000000000000aa");
+    let target = address!("00000000000000000000000000000000000000bb");
+
+    // PUSH1 0x01 PUSH0 PUSH0 PUSH20 <target> EXTCALL PUSH0 SSTORE STOP
+    let code = [&hex!("60015f5f73")[..], &target[..], &hex!("f85f5500")].concat();
+    let eof = EofBody {
+        types_section: vec![TypesSection::new(0, 0x80, 4)],
+        code_section: vec![code.into()],
+        ..Default::default()
+    }
+    .into_eof();
+
+    let mut evm = TestEvm::new();
+    evm.env.handler_cfg.spec_id = SpecId::PRAGUE_EOF;
+    let code = Bytecode::Eof(eof.into());
+    evm.db.insert_account_info(caller, AccountInfo::new(U256::ZERO, 0, code.hash_slow(), code));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(caller, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 2);
+    assert!(!nodes[0].trace.is_eof);
+    assert_eq!(nodes[0].trace.ext_call_status(), None);
+
+    // the caller has no balance to transfer
+    let call = &nodes[1].trace;
+    assert!(call.is_eof);
+    assert_eq!(call.status, InstructionResult::OutOfFunds);
+    assert!(call.is_light_failure());
+    assert_eq!(call.ext_call_status(), Some(1));
+    assert_eq!(call.gas_used, 0);
+    assert!(call.output.is_empty());
+
+    // the status is pushed onto the caller's stack
+    let sstore = nodes[0].trace.steps.iter().find(|step| step.op.get() == opcode::SSTORE).unwrap();
+    assert_eq!(sstore.stack.as_ref().unwrap()[0], U256::from(1));
+}
+
+#[test]
+fn test_delegated_to() {
+    use revm::primitives::SpecId;
+
+    let eoa = address!("00000000000000000000000000000000000000aa");
+    let implementation = address!("00000000000000000000000000000000000000bb");
+    let caller = address!("00000000000000000000000000000000000000cc");
+    let designator = |address: Address| [&hex!("ef0100")[..], &address[..]].concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(eoa, designator(implementation).into());
+    // PUSH1 0x01 STOP
+    evm.set_code(implementation, bytes!("600100"));
+    // PUSH1 0x00 (x5) PUSH20 <eoa> GAS CALL POP PUSH1 0x00 (x5) PUSH20 <implementation> GAS CALL
+    // STOP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+    evm.set_code(caller, [call(eoa), call(implementation), vec![0x00]].concat().into());
+
+    // delegations are only recorded if enabled and for Prague
+    let config = TracingInspectorConfig::default_parity().record_delegations();
+    let mut insp = TracingInspector::new(config.clone());
+    let _ = evm.call(eoa, Bytes::new(), &mut insp).unwrap();
+    assert_eq!(insp.traces().nodes()[0].trace.delegated_to, None);
+
+    evm.env.handler_cfg.spec_id = SpecId::PRAGUE;
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let _ = evm.call(eoa, Bytes::new(), &mut insp).unwrap();
+    assert_eq!(insp.traces().nodes()[0].trace.delegated_to, None);
+
+    // this version of revm does not execute delegated code yet, only the designator is traced
+    let mut insp = TracingInspector::new(config.clone());
+    let _ = evm.call(eoa, Bytes::new(), &mut insp).unwrap();
+    assert_eq!(insp.traces().nodes()[0].trace.delegated_to, Some(implementation));
+
+    let mut insp = TracingInspector::new(config);
+    let _ = evm.call(caller, Bytes::new(), &mut insp).unwrap();
+    let nodes = insp.traces().nodes();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[0].trace.delegated_to, None);
+    assert_eq!(nodes[1].trace.delegated_to, Some(implementation));
+    assert_eq!(nodes[2].trace.delegated_to, None);
+}
+
+#[test]
+fn test_repeated_calls() {
+    let address = address!("00000000000000000000000000000000000000aa");
+    let callee = address!("00000000000000000000000000000000000000bb");
+    let other = address!("00000000000000000000000000000000000000cc");
+
+    // PUSH1 0x00 (x5) PUSH20 <address> GAS CALL POP
+    let call = |address: Address| {
+        [&hex!("60006000600060006000")[..], &[0x73], &address[..], &hex!("5af150")].concat()
+    };
+    // PUSH1 0x03 JUMPDEST <call callee> PUSH1 0x01 SWAP1 SUB DUP1 PUSH1 0x02 JUMPI <call other>
+    // STOP
+    let code =
+        [&hex!("60035b")[..], &call(callee), &hex!("6001900380600257"), &call(other), &[0x00]]
+            .concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code.into());
+    // PUSH1 0x01 STOP
+    evm.set_code(callee, bytes!("600100"));
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let traces = insp.traces();
+    assert_eq!(traces.nodes().len(), 5);
+    let repeated = traces.repeated_calls();
+    assert_eq!(repeated.len(), 1);
+    let (parent, call, count) = repeated[0];
+    assert_eq!(parent, 0);
+    assert_eq!(call.idx, 1);
+    assert_eq!(call.trace.address, callee);
+    assert_eq!(count, 3);
+}
+
+#[test]
+fn test_access_list_warm_sload() {
+    let address = address!("00000000000000000000000000000000000000aa");
+    // PUSH1 0x01 SLOAD POP PUSH1 0x02 SLOAD POP PUSH1 0x02 SLOAD STOP
+    let code = bytes!("600154506002545060025400");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+    evm.env.tx.access_list = vec![(address, vec![U256::from(1)])];
+
+    let mut insp = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let res = evm.call(address, Bytes::new(), &mut insp).unwrap();
+    assert!(res.is_success());
+
+    let sloads = insp.traces().nodes()[0]
+        .trace
+        .steps
+        .iter()
+        .filter(|step| step.op.get() == opcode::SLOAD)
+        .map(|step| step.is_cold_sload())
+        .collect::<Vec<_>>();
+    // the slot in the access list is warm, the other slot is only cold on its first load
+    assert_eq!(sloads, [false, true, false]);
+}
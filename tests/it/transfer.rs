@@ -1,19 +1,19 @@
 //! Transfer tests
 
-use alloy_primitives::{hex, Address, U256};
+use alloy_primitives::{address, hex, keccak256, Address, B256, I256, U256};
 use revm::{
     db::{CacheDB, EmptyDB},
     primitives::{
-        BlockEnv, CfgEnv, CfgEnvWithHandlerCfg, EnvWithHandlerCfg, ExecutionResult, HandlerCfg,
-        Output, SpecId, TransactTo, TxEnv,
+        AccountInfo, BlobExcessGasAndPrice, BlockEnv, CfgEnv, CfgEnvWithHandlerCfg,
+        EnvWithHandlerCfg, ExecutionResult, HandlerCfg, Output, SpecId, TransactTo, TxEnv,
     },
     DatabaseCommit,
 };
 
-use crate::utils::inspect;
+use crate::utils::{inspect, TestEvm};
 use revm_inspectors::{
     tracing::{TracingInspector, TracingInspectorConfig},
-    transfer::{TransferInspector, TransferKind, TransferOperation},
+    transfer::{FeeKind, TokenStandard, TransferInspector, TransferKind, TransferOperation},
 };
 
 #[test]
@@ -116,3 +116,352 @@ fn test_internal_transfers() {
         }
     );
 }
+
+#[test]
+fn test_transfer_balance_reconciliation() {
+    // same `Transfer` contract as in `test_internal_transfers`
+    let code = hex!("608060405234801561001057600080fd5b5060ef8061001f6000396000f3fe608060405260043610601c5760003560e01c8063830c29ae146021575b600080fd5b6030602c366004608b565b6032565b005b600080826001600160a01b03163460405160006040518083038185875af1925050503d8060008114607e576040519150601f19603f3d011682016040523d82523d6000602084013e6083565b606091505b505050505050565b600060208284031215609c57600080fd5b81356001600160a01b038116811460b257600080fd5b939250505056fea26469706673582212201654bdbf09c088897c9b02f3ba9df280b136ef99c3a05ca5a21d9a10fd912d3364736f6c634300080d0033");
+    let deployer = Address::ZERO;
+    let coinbase = address!("00000000000000000000000000000000000c0ffe");
+
+    let mut db = CacheDB::new(EmptyDB::default());
+
+    let cfg = CfgEnvWithHandlerCfg::new(CfgEnv::default(), HandlerCfg::new(SpecId::LONDON));
+
+    let env = EnvWithHandlerCfg::new_with_cfg_env(
+        cfg.clone(),
+        BlockEnv::default(),
+        TxEnv {
+            caller: deployer,
+            gas_limit: 1000000,
+            transact_to: TransactTo::Create,
+            data: code.into(),
+            ..Default::default()
+        },
+    );
+
+    let mut insp = TransferInspector::new(false);
+    let (res, _) = inspect(&mut db, env, &mut insp).unwrap();
+    let addr = match res.result {
+        ExecutionResult::Success { output, .. } => match output {
+            Output::Create(_, addr) => addr.unwrap(),
+            _ => panic!("Create failed"),
+        },
+        _ => panic!("Execution failed"),
+    };
+    db.commit(res.state);
+
+    let acc = db.load_account(deployer).unwrap();
+    acc.info.balance = U256::from(u64::MAX);
+
+    let block_env = BlockEnv { coinbase, basefee: U256::from(1), ..Default::default() };
+    let tx_env = TxEnv {
+        caller: deployer,
+        gas_limit: 100000000,
+        gas_price: U256::from(2),
+        transact_to: TransactTo::Call(addr),
+        data: hex!("830c29ae0000000000000000000000000000000000000000000000000000000000000000")
+            .into(),
+        value: U256::from(10),
+        ..Default::default()
+    };
+
+    let mut insp = TransferInspector::new(false);
+    let env = EnvWithHandlerCfg::new_with_cfg_env(cfg.clone(), block_env.clone(), tx_env.clone());
+    let (res, env) = inspect(&mut db, env, &mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    let discrepancies = insp.balance_discrepancies(&res, &env, &db).unwrap();
+    assert!(discrepancies.is_empty(), "{discrepancies:?}");
+
+    // the top level transfer is not recorded, so it can't be reconciled
+    let mut insp = TransferInspector::internal_only();
+    let env = EnvWithHandlerCfg::new_with_cfg_env(cfg, block_env, tx_env);
+    let (res, env) = inspect(&mut db, env, &mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    let discrepancies = insp.balance_discrepancies(&res, &env, &db).unwrap();
+    assert_eq!(discrepancies.len(), 2);
+    assert_eq!(discrepancies[&deployer], I256::try_from(-10).unwrap());
+    assert_eq!(discrepancies[&addr], I256::try_from(10).unwrap());
+}
+
+#[test]
+fn test_fee_transfers() {
+    let caller = address!("283b5b7d75e3e6b84b8e2161e8a468d733bbbe8d");
+    let to = address!("15dd773dad3f630773a0e771e9b221f4c8b9b939");
+    let coinbase = address!("00000000000000000000000000000000000c0ffe");
+
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(
+        caller,
+        AccountInfo { balance: U256::from(u64::MAX), ..Default::default() },
+    );
+
+    let cfg = CfgEnvWithHandlerCfg::new(CfgEnv::default(), HandlerCfg::new(SpecId::CANCUN));
+    let env = EnvWithHandlerCfg::new_with_cfg_env(
+        cfg,
+        BlockEnv {
+            coinbase,
+            basefee: U256::from(100),
+            blob_excess_gas_and_price: Some(BlobExcessGasAndPrice::new(0)),
+            ..Default::default()
+        },
+        TxEnv {
+            caller,
+            gas_limit: 1000000,
+            transact_to: TransactTo::Call(to),
+            gas_price: U256::from(150),
+            gas_priority_fee: Some(U256::from(10)),
+            blob_hashes: vec!["0x01af2fd94f17364bc8ef371c4c90c3a33855ff972d10b9c03d0445b3fca063ea"
+                .parse()
+                .unwrap()],
+            max_fee_per_blob_gas: Some(U256::from(1000)),
+            ..Default::default()
+        },
+    );
+
+    let mut insp = TransferInspector::new(false);
+    let (res, env) = inspect(&mut db, env, &mut insp).unwrap();
+    assert!(res.result.is_success());
+    let gas_used = res.result.gas_used();
+    assert_eq!(gas_used, 21_000);
+
+    let fees = TransferInspector::fee_transfers(&env, gas_used);
+    assert_eq!(
+        fees,
+        [
+            TransferOperation {
+                kind: TransferKind::Fee(FeeKind::BaseFee),
+                from: caller,
+                to: Address::ZERO,
+                value: U256::from(100 * 21_000),
+            },
+            TransferOperation {
+                kind: TransferKind::Fee(FeeKind::PriorityFee),
+                from: caller,
+                to: coinbase,
+                value: U256::from(10 * 21_000),
+            },
+            // a single blob at the minimum blob gas price of 1
+            TransferOperation {
+                kind: TransferKind::Fee(FeeKind::BlobFee),
+                from: caller,
+                to: Address::ZERO,
+                value: U256::from(131_072),
+            },
+        ]
+    );
+
+    // the fees add up to the sender's balance change
+    let paid = fees.iter().map(|fee| fee.value).sum::<U256>();
+    assert_eq!(U256::from(u64::MAX) - res.state[&caller].info.balance, paid);
+}
+
+#[test]
+fn test_token_transfers() {
+    let token = address!("00000000000000000000000000000000000000aa");
+    let from = address!("0000000000000000000000000000000000000011");
+    let to = address!("0000000000000000000000000000000000000022");
+    let signature = keccak256("Transfer(address,address,uint256)");
+
+    // PUSH20 <address>
+    let push_address = |address: Address| [&[0x73], &address[..]].concat();
+    // PUSH32 <word>
+    let push_word = |word: B256| [&[0x7f], &word[..]].concat();
+    let code = [
+        // PUSH2 0x03e8 PUSH1 0x00 MSTORE
+        hex!("6103e8600052").to_vec(),
+        // ERC-20: LOG3(0x00, 0x20, signature, from, to)
+        push_address(to),
+        push_address(from),
+        push_word(signature),
+        hex!("60206000a3").to_vec(),
+        // ERC-721: LOG4(0x00, 0x00, signature, from, to, 7)
+        hex!("6007").to_vec(),
+        push_address(to),
+        push_address(from),
+        push_word(signature),
+        hex!("60006000a4").to_vec(),
+        // malformed: LOG2(0x00, 0x20, signature, from)
+        push_address(from),
+        push_word(signature),
+        hex!("60206000a2").to_vec(),
+        // STOP
+        vec![0x00],
+    ]
+    .concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(token, code.into());
+
+    let mut insp = TransferInspector::internal_only();
+    evm.clone().call(token, Default::default(), &mut insp).unwrap();
+    assert!(insp.transfers().is_empty());
+
+    let mut insp = TransferInspector::internal_only().with_token_transfers(true);
+    let res = evm.call(token, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+    assert_eq!(
+        insp.into_transfers(),
+        vec![
+            TransferOperation {
+                kind: TransferKind::Token { token, standard: TokenStandard::Erc20 },
+                from,
+                to,
+                value: U256::from(1000),
+            },
+            TransferOperation {
+                kind: TransferKind::Token { token, standard: TokenStandard::Erc721 },
+                from,
+                to,
+                value: U256::from(7),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_net_balance_changes() {
+    let a = address!("0000000000000000000000000000000000000011");
+    let b = address!("00000000000000000000000000000000000000bb");
+    let c = address!("00000000000000000000000000000000000000cc");
+
+    let mut evm = TestEvm::new();
+    evm.db.insert_account_info(a, AccountInfo { balance: U256::from(100), ..Default::default() });
+    // PUSH1 0x00 (x4) CALLVALUE PUSH20 <c> GAS CALL POP STOP
+    let code = [&hex!("6000600060006000")[..], &[0x34, 0x73], &c[..], &hex!("5af15000")].concat();
+    evm.set_code(b, code.into());
+    evm.env.tx.caller = a;
+    evm.env.tx.value = U256::from(10);
+
+    let mut insp = TransferInspector::new(false);
+    let res = evm.call(b, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+    assert_eq!(insp.transfers().len(), 2);
+
+    let changes = insp.net_balance_changes();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[&a], I256::try_from(-10).unwrap());
+    assert_eq!(changes[&b], I256::ZERO);
+    assert_eq!(changes[&c], I256::try_from(10).unwrap());
+}
+
+#[test]
+fn test_balance_discrepancies_reverted_call() {
+    let sender = address!("0000000000000000000000000000000000000011");
+    let root = address!("00000000000000000000000000000000000000aa");
+    let child = address!("00000000000000000000000000000000000000bb");
+    let target = address!("00000000000000000000000000000000000000cc");
+    // PUSH1 0x00 (x4) PUSH1 <value> PUSH20 <address> GAS CALL POP
+    let call = |value: u8, address: Address| {
+        [&hex!("600060006000600060")[..], &[value, 0x73], &address[..], &hex!("5af150")].concat()
+    };
+
+    let mut evm = TestEvm::new();
+    evm.db.insert_account_info(
+        sender,
+        AccountInfo { balance: U256::from(100), ..Default::default() },
+    );
+    evm.set_code(root, [call(5, child), vec![0x00]].concat().into());
+    // the child transfers value and then reverts: PUSH1 0x00 PUSH1 0x00 REVERT
+    evm.set_code(child, [call(1, target), hex!("60006000fd").to_vec()].concat().into());
+    evm.env.tx.caller = sender;
+    evm.env.tx.transact_to = TransactTo::Call(root);
+    evm.env.tx.value = U256::from(10);
+
+    let mut insp = TransferInspector::new(false);
+    let (res, env) = evm.inspect(&mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    // the transfers of the reverted call are collected, but marked as reverted
+    assert_eq!(insp.transfers().len(), 3);
+    assert!(!insp.is_reverted(0));
+    assert!(insp.is_reverted(1));
+    assert!(insp.is_reverted(2));
+
+    let discrepancies = insp.balance_discrepancies(&res, &env, &evm.db).unwrap();
+    assert!(discrepancies.is_empty(), "{discrepancies:?}");
+}
+
+#[test]
+fn test_balance_discrepancies_selfdestruct_to_self() {
+    let contract = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.env.handler_cfg.spec_id = SpecId::SHANGHAI;
+    // ADDRESS SELFDESTRUCT
+    evm.set_code(contract, hex!("30ff").into());
+    evm.db.load_account(contract).unwrap().info.balance = U256::from(7);
+    evm.env.tx.transact_to = TransactTo::Call(contract);
+
+    let mut insp = TransferInspector::new(false);
+    let (res, env) = evm.inspect(&mut insp).unwrap();
+    assert!(res.result.is_success());
+
+    // the balance is burned, even though the transfer nets to zero
+    assert_eq!(
+        insp.transfers(),
+        [TransferOperation {
+            kind: TransferKind::SelfDestruct,
+            from: contract,
+            to: contract,
+            value: U256::from(7),
+        }]
+    );
+    assert_eq!(res.state[&contract].info.balance, U256::ZERO);
+    let discrepancies = insp.balance_discrepancies(&res, &env, &evm.db).unwrap();
+    assert!(discrepancies.is_empty(), "{discrepancies:?}");
+}
+
+#[test]
+fn test_token_transfers_reverted() {
+    let caller = address!("00000000000000000000000000000000000000aa");
+    let token = address!("00000000000000000000000000000000000000bb");
+    let from = address!("0000000000000000000000000000000000000011");
+    let to = address!("0000000000000000000000000000000000000022");
+    let signature = keccak256("Transfer(address,address,uint256)");
+
+    // PUSH2 0x03e8 PUSH1 0x00 MSTORE, LOG3(0x00, 0x20, signature, from, to)
+    let transfer = [
+        &hex!("6103e8600052")[..],
+        &[0x73],
+        &to[..],
+        &[0x73],
+        &from[..],
+        &[0x7f],
+        &signature[..],
+        &hex!("60206000a3"),
+    ]
+    .concat();
+    // the token emits the transfer and then reverts: PUSH1 0x00 PUSH1 0x00 REVERT
+    let token_code = [transfer.clone(), hex!("60006000fd").to_vec()].concat();
+    // the caller catches the revert and emits a transfer itself:
+    // PUSH1 0x00 (x5) PUSH20 <token> GAS CALL POP <transfer> STOP
+    let caller_code = [
+        &hex!("60006000600060006000")[..],
+        &[0x73],
+        &token[..],
+        &hex!("5af150"),
+        &transfer,
+        &[0x00],
+    ]
+    .concat();
+
+    let mut evm = TestEvm::new();
+    evm.set_code(token, token_code.into());
+    evm.set_code(caller, caller_code.into());
+
+    let mut insp = TransferInspector::internal_only().with_token_transfers(true);
+    let res = evm.call(caller, Default::default(), &mut insp).unwrap();
+    assert!(res.is_success());
+    assert_eq!(
+        insp.into_transfers(),
+        vec![TransferOperation {
+            kind: TransferKind::Token { token: caller, standard: TokenStandard::Erc20 },
+            from,
+            to,
+            value: U256::from(1000),
+        }]
+    );
+}
@@ -4,8 +4,8 @@ use revm::{
     db::{CacheDB, EmptyDB},
     inspector_handle_register,
     primitives::{
-        BlockEnv, EVMError, Env, EnvWithHandlerCfg, ExecutionResult, HandlerCfg, Output,
-        ResultAndState, SpecId, TransactTo, TxEnv,
+        AccountInfo, BlockEnv, Bytecode, EVMError, Env, EnvWithHandlerCfg, ExecutionResult,
+        HandlerCfg, Output, ResultAndState, SpecId, TransactTo, TxEnv,
     },
     Database, DatabaseCommit, GetInspector,
 };
@@ -40,6 +40,13 @@ impl TestEvm {
         Self { db, env }
     }
 
+    /// Inserts an account with the given runtime code.
+    pub fn set_code(&mut self, address: Address, code: Bytes) {
+        let code = Bytecode::new_raw(code);
+        let info = AccountInfo::new(U256::ZERO, 0, code.hash_slow(), code);
+        self.db.insert_account_info(address, info);
+    }
+
     pub fn deploy<I: for<'a> GetInspector<&'a mut TestDb>>(
         &mut self,
         data: Bytes,
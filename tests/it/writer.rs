@@ -1,19 +1,28 @@
 use crate::utils::{write_traces, TestEvm};
-use alloy_primitives::{bytes, Bytes, U256};
+use alloy_primitives::{address, bytes, Bytes, U256};
+use alloy_rpc_types::trace::geth::CallConfig;
 use alloy_sol_types::{sol, SolCall};
 use expect_test::expect;
-use revm_inspectors::tracing::{types::DecodedCallData, TracingInspector, TracingInspectorConfig};
+use revm_inspectors::{
+    tracing::{
+        types::{CallTraceNode, DecodedCallData},
+        MarkdownTraceWriter, StreamingTraceWriter, TraceWriter, TracingInspector,
+        TracingInspectorConfig,
+    },
+    ColorChoice,
+};
+use std::collections::HashMap;
+
+// solc testdata/Counter.sol --via-ir --optimize --bin
+sol!("testdata/Counter.sol");
+static COUNTER_BYTECODE: Bytes = bytes!("60808060405234601557610415908161001a8239f35b5f80fdfe6080806040526004361015610012575f80fd5b5f905f3560e01c9081630aa7318514610347575080633fb5c1cb14610326578063526f6fc5146102cb57806377fa5d9e1461026e5780638381f58a14610252578063943ee48c146101a85780639db265eb1461014e578063d09de08a146101325763f267ce9e14610081575f80fd5b346101245780600319360112610124576100996103ba565b303b1561012457604051639db265eb60e01b81528190818160048183305af180156101275761010f575b50607b90547f5ae719eb0250b8686767e291df04bec55e7f45a5997e120be020424da1896d766060604051602081526009602082015268343490333937b6901960b91b6040820152a380f35b8161011991610384565b61012457805f6100c3565b80fd5b6040513d84823e3d90fd5b503461012457806003193601126101245761014b6103ba565b80f35b503461012457806003193601126101245780547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600960208201526868692066726f6d203360b81b6040820152a280f35b503461024e575f36600319011261024e575f547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600960208201526868692066726f6d203160b81b6040820152a2303b1561024e57604051637933e74f60e11b81525f8160048183305af1801561024357610230575b5061014b6103ba565b61023c91505f90610384565b5f80610227565b6040513d5f823e3d90fd5b5f80fd5b3461024e575f36600319011261024e5760205f54604051908152f35b3461024e575f36600319011261024e57607b5f547f5ae719eb0250b8686767e291df04bec55e7f45a5997e120be020424da1896d76606060405160208152600c60208201526b343490333937b6903637b39960a11b6040820152a3005b3461024e575f36600319011261024e575f547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600c60208201526b68692066726f6d206c6f673160a01b6040820152a2005b3461024e57602036600319011261024e576004355f55602060405160018152f35b3461024e575f36600319011261024e576080905f54815260406020820152600c60408201526b068692066726f6d206c6f67360a41b6060820152a0005b90601f8019910116810190811067ffffffffffffffff8211176103a657604052565b634e487b7160e01b5f52604160045260245ffd5b5f545f1981146103cb576001015f55565b634e487b7160e01b5f52601160045260245ffdfea26469706673582212203a43412c8d5d5c94f96a961dffefb472490c9ba0f0710d008b49565e95e44daf64736f6c634300081a0033");
 
 #[test]
 fn test_basic_trace_printing() {
-    // solc testdata/Counter.sol --via-ir --optimize --bin
-    sol!("testdata/Counter.sol");
-    static BYTECODE: Bytes = bytes!("60808060405234601557610415908161001a8239f35b5f80fdfe6080806040526004361015610012575f80fd5b5f905f3560e01c9081630aa7318514610347575080633fb5c1cb14610326578063526f6fc5146102cb57806377fa5d9e1461026e5780638381f58a14610252578063943ee48c146101a85780639db265eb1461014e578063d09de08a146101325763f267ce9e14610081575f80fd5b346101245780600319360112610124576100996103ba565b303b1561012457604051639db265eb60e01b81528190818160048183305af180156101275761010f575b50607b90547f5ae719eb0250b8686767e291df04bec55e7f45a5997e120be020424da1896d766060604051602081526009602082015268343490333937b6901960b91b6040820152a380f35b8161011991610384565b61012457805f6100c3565b80fd5b6040513d84823e3d90fd5b503461012457806003193601126101245761014b6103ba565b80f35b503461012457806003193601126101245780547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600960208201526868692066726f6d203360b81b6040820152a280f35b503461024e575f36600319011261024e575f547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600960208201526868692066726f6d203160b81b6040820152a2303b1561024e57604051637933e74f60e11b81525f8160048183305af1801561024357610230575b5061014b6103ba565b61023c91505f90610384565b5f80610227565b6040513d5f823e3d90fd5b5f80fd5b3461024e575f36600319011261024e5760205f54604051908152f35b3461024e575f36600319011261024e57607b5f547f5ae719eb0250b8686767e291df04bec55e7f45a5997e120be020424da1896d76606060405160208152600c60208201526b343490333937b6903637b39960a11b6040820152a3005b3461024e575f36600319011261024e575f547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600c60208201526b68692066726f6d206c6f673160a01b6040820152a2005b3461024e57602036600319011261024e576004355f55602060405160018152f35b3461024e575f36600319011261024e576080905f54815260406020820152600c60408201526b068692066726f6d206c6f67360a41b6060820152a0005b90601f8019910116810190811067ffffffffffffffff8211176103a657604052565b634e487b7160e01b5f52604160045260245ffd5b5f545f1981146103cb576001015f55565b634e487b7160e01b5f52601160045260245ffdfea26469706673582212203a43412c8d5d5c94f96a961dffefb472490c9ba0f0710d008b49565e95e44daf64736f6c634300081a0033");
-
     let mut evm = TestEvm::new();
 
     let mut tracer = TracingInspector::new(TracingInspectorConfig::all().disable_steps());
-    let address = evm.deploy(BYTECODE.clone(), &mut tracer).unwrap();
+    let address = evm.deploy(COUNTER_BYTECODE.clone(), &mut tracer).unwrap();
 
     let mut s = write_traces(&tracer);
     patch_output(&mut s);
@@ -148,6 +157,138 @@ fn test_basic_trace_printing() {
     "#]].assert_eq(&s);
 }
 
+#[test]
+fn test_markdown_trace_printing() {
+    let mut evm = TestEvm::new();
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::all().disable_steps());
+    let address = evm.deploy(COUNTER_BYTECODE.clone(), &mut tracer).unwrap();
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::all().disable_steps());
+    let r = evm.call(address, Counter::nest1Call {}.abi_encode().into(), &mut tracer).unwrap();
+    assert!(r.is_success());
+
+    let mut w = MarkdownTraceWriter::new(Vec::<u8>::new());
+    w.write_arena(tracer.traces()).unwrap();
+    let s = String::from_utf8(w.into_writer()).unwrap();
+    expect![[r#"
+        - **CALL** `0xBd770416a3345F91E4B34576cb804a576fa48EB1::943ee48c` gas used: 30656, status: `Return`
+          - **CALL** `0xBd770416a3345F91E4B34576cb804a576fa48EB1::f267ce9e` gas used: 25675, status: `Return`
+            - **CALL** `0xBd770416a3345F91E4B34576cb804a576fa48EB1::9db265eb` gas used: 2337, status: `Return`
+    "#]]
+    .assert_eq(&s);
+}
+
+#[test]
+fn test_json_trace_printing() {
+    let mut evm = TestEvm::new();
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::all().disable_steps());
+    let address = evm.deploy(COUNTER_BYTECODE.clone(), &mut tracer).unwrap();
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::all().disable_steps());
+    let r = evm.call(address, Counter::nest1Call {}.abi_encode().into(), &mut tracer).unwrap();
+    assert!(r.is_success());
+    patch_traces(0, &mut tracer);
+
+    let mut w = TraceWriter::new(Vec::<u8>::new());
+    w.write_arena_json(tracer.traces()).unwrap();
+    let value: serde_json::Value = serde_json::from_slice(&w.into_writer()).unwrap();
+
+    assert_eq!(value["kind"], "CALL");
+    assert_eq!(value["to"], "0xbd770416a3345f91e4b34576cb804a576fa48eb1");
+    assert_eq!(value["value"], "0");
+    assert_eq!(value["gas_used"], 30656);
+    assert_eq!(value["status"], "Return");
+    assert_eq!(value["input"], "0x943ee48c");
+    assert_eq!(value["label"], "Counter");
+    assert_eq!(value["function"], "nest1");
+    assert_eq!(value["args"], serde_json::json!([]));
+    assert_eq!(value["logs"][0]["name"], "Log1");
+
+    let calls = value["calls"].as_array().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0]["function"], "nest2");
+    assert_eq!(calls[0]["from"], "0xbd770416a3345f91e4b34576cb804a576fa48eb1");
+    assert_eq!(calls[0]["calls"][0]["function"], "nest3");
+    assert_eq!(calls[0]["calls"][0]["calls"], serde_json::json!([]));
+}
+
+#[test]
+fn test_streaming_trace_printing() {
+    let mut evm = TestEvm::new();
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::all().disable_steps());
+    let address = evm.deploy(COUNTER_BYTECODE.clone(), &mut tracer).unwrap();
+
+    let mut streaming = StreamingTraceWriter::new(
+        TracingInspector::new(TracingInspectorConfig::all().disable_steps()),
+        TraceWriter::new(Vec::<u8>::new()).use_colors(ColorChoice::Never),
+    );
+    let r = evm.call(address, Counter::nest1Call {}.abi_encode().into(), &mut streaming).unwrap();
+    assert!(r.is_success());
+    assert!(streaming.error().is_none());
+
+    // frames are written as they complete, the innermost call first
+    let (_, w) = streaming.into_parts();
+    let mut s = String::from_utf8(w.into_writer()).unwrap();
+    patch_output(&mut s);
+    expect![[r#"
+        .   │   ├─ [2337] 0xBd770416a3345F91E4B34576cb804a576fa48EB1::9db265eb() ← [Return] 
+            ├─ [25675] 0xBd770416a3345F91E4B34576cb804a576fa48EB1::f267ce9e() ← [Return] 
+          [30656] 0xBd770416a3345F91E4B34576cb804a576fa48EB1::943ee48c() ← [Return] 
+    "#]]
+    .assert_eq(&s);
+}
+
+#[test]
+fn test_step_trace_printing() {
+    // PUSH1 0x01 PUSH1 0x00 SSTORE STOP
+    let code = bytes!("600160005500");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let r = evm.call(address, Bytes::new(), &mut tracer).unwrap();
+    assert!(r.is_success());
+
+    let mut w = TraceWriter::new(Vec::<u8>::new()).use_colors(ColorChoice::Never).write_steps(true);
+    w.write_arena(tracer.traces()).unwrap();
+    let mut s = String::from_utf8(w.into_writer()).unwrap();
+    patch_output(&mut s);
+    expect![[r#"
+        . [22106] 0x00000000000000000000000000000000000000AA::fallback()
+            ├─ 0  PUSH1  3  []
+            ├─ 2  PUSH1  3  [0x1]
+            ├─ 4  SSTORE  22100  [0x0, 0x1]
+            ├─ 5  STOP  0  []
+            └─ ← [Stop] 
+    "#]]
+    .assert_eq(&s);
+
+    // steps without stack snapshots
+    let mut tracer =
+        TracingInspector::new(TracingInspectorConfig::default_geth().disable_stack_snapshots());
+    let r = evm.call(address, Bytes::new(), &mut tracer).unwrap();
+    assert!(r.is_success());
+
+    let mut w = TraceWriter::new(Vec::<u8>::new()).use_colors(ColorChoice::Never).write_steps(true);
+    w.write_arena(tracer.traces()).unwrap();
+    let mut s = String::from_utf8(w.into_writer()).unwrap();
+    patch_output(&mut s);
+    expect![[r#"
+        . [2206] 0x00000000000000000000000000000000000000AA::fallback()
+            ├─ 0  PUSH1  3
+            ├─ 2  PUSH1  3
+            ├─ 4  SSTORE  2200
+            ├─ 5  STOP  0
+            └─ ← [Stop] 
+    "#]]
+    .assert_eq(&s);
+}
+
 // (name, address)
 const LABELS: &[(&str, &str)] = &[("Counter", "0xBd770416a3345F91E4B34576cb804a576fa48EB1")];
 
@@ -174,14 +315,10 @@ const EVENT_SIGNATURES: &[(&str, &str, &[&str])] = &[
 
 #[test]
 fn test_decoded_trace_printing() {
-    // solc testdata/Counter.sol --via-ir --optimize --bin
-    sol!("testdata/Counter.sol");
-    static BYTECODE: Bytes = bytes!("60808060405234601557610415908161001a8239f35b5f80fdfe6080806040526004361015610012575f80fd5b5f905f3560e01c9081630aa7318514610347575080633fb5c1cb14610326578063526f6fc5146102cb57806377fa5d9e1461026e5780638381f58a14610252578063943ee48c146101a85780639db265eb1461014e578063d09de08a146101325763f267ce9e14610081575f80fd5b346101245780600319360112610124576100996103ba565b303b1561012457604051639db265eb60e01b81528190818160048183305af180156101275761010f575b50607b90547f5ae719eb0250b8686767e291df04bec55e7f45a5997e120be020424da1896d766060604051602081526009602082015268343490333937b6901960b91b6040820152a380f35b8161011991610384565b61012457805f6100c3565b80fd5b6040513d84823e3d90fd5b503461012457806003193601126101245761014b6103ba565b80f35b503461012457806003193601126101245780547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600960208201526868692066726f6d203360b81b6040820152a280f35b503461024e575f36600319011261024e575f547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600960208201526868692066726f6d203160b81b6040820152a2303b1561024e57604051637933e74f60e11b81525f8160048183305af1801561024357610230575b5061014b6103ba565b61023c91505f90610384565b5f80610227565b6040513d5f823e3d90fd5b5f80fd5b3461024e575f36600319011261024e5760205f54604051908152f35b3461024e575f36600319011261024e57607b5f547f5ae719eb0250b8686767e291df04bec55e7f45a5997e120be020424da1896d76606060405160208152600c60208201526b343490333937b6903637b39960a11b6040820152a3005b3461024e575f36600319011261024e575f547f9d39c21a43a4dfcd7857f27f3399f31a24694b6cb361496355ab537d16f745ca606060405160208152600c60208201526b68692066726f6d206c6f673160a01b6040820152a2005b3461024e57602036600319011261024e576004355f55602060405160018152f35b3461024e575f36600319011261024e576080905f54815260406020820152600c60408201526b068692066726f6d206c6f67360a41b6060820152a0005b90601f8019910116810190811067ffffffffffffffff8211176103a657604052565b634e487b7160e01b5f52604160045260245ffd5b5f545f1981146103cb576001015f55565b634e487b7160e01b5f52601160045260245ffdfea26469706673582212203a43412c8d5d5c94f96a961dffefb472490c9ba0f0710d008b49565e95e44daf64736f6c634300081a0033");
-
     let mut evm = TestEvm::new();
 
     let mut tracer = TracingInspector::new(TracingInspectorConfig::all().disable_steps());
-    let address = evm.deploy(BYTECODE.clone(), &mut tracer).unwrap();
+    let address = evm.deploy(COUNTER_BYTECODE.clone(), &mut tracer).unwrap();
 
     let mut s = write_traces(&tracer);
     patch_output(&mut s);
@@ -372,3 +509,68 @@ fn patch_traces(patch: usize, t: &mut TracingInspector) {
         }
     }
 }
+
+#[test]
+fn test_trace_printing_max_bytes_len() {
+    // PUSH2 0x2710 PUSH1 0x00 RETURN
+    let code = bytes!("6127106000f3");
+    let address = address!("00000000000000000000000000000000000000aa");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(address, code);
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::default_parity());
+    let r = evm.call(address, Bytes::new(), &mut tracer).unwrap();
+    assert!(r.is_success());
+    assert_eq!(tracer.traces().nodes()[0].trace.output.len(), 10_000);
+
+    let mut w =
+        TraceWriter::new(Vec::<u8>::new()).use_colors(ColorChoice::Never).max_bytes_len(Some(32));
+    w.write_arena(tracer.traces()).unwrap();
+    let s = String::from_utf8(w.into_writer()).unwrap();
+
+    let line = s.lines().find(|line| line.contains("[Return]")).unwrap();
+    assert!(line.chars().count() < 120, "{line}");
+    assert!(line.ends_with(&format!("0x{}…(9.8KiB)", "00".repeat(32))), "{line}");
+}
+
+#[test]
+fn test_trace_printing_labels() {
+    let caller = address!("00000000000000000000000000000000000000aa");
+    let callee = address!("00000000000000000000000000000000000000bb");
+
+    let mut evm = TestEvm::new();
+    evm.set_code(
+        caller,
+        bytes!("600060006000600060007300000000000000000000000000000000000000bb5af100"),
+    );
+    evm.set_code(callee, bytes!("00"));
+
+    let mut tracer = TracingInspector::new(TracingInspectorConfig::default_geth());
+    let r = evm.call(caller, Bytes::new(), &mut tracer).unwrap();
+    assert!(r.is_success());
+
+    let labels = HashMap::from([(caller, "Caller".to_string()), (callee, "Callee".to_string())]);
+
+    let builder = tracer.clone().into_geth_builder().with_labels(&labels);
+    let labels_of = |nodes: &[CallTraceNode]| {
+        nodes.iter().map(|node| node.trace.decoded.label.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(labels_of(builder.nodes()), [Some("Caller".into()), Some("Callee".into())]);
+    let frame = builder.geth_call_traces(CallConfig::default(), r.gas_used());
+    assert_eq!(frame.to, Some(caller));
+    assert_eq!(frame.calls[0].to, Some(callee));
+
+    tracer.traces_mut().apply_labels(&labels);
+    let mut w = TraceWriter::new(Vec::<u8>::new()).use_colors(ColorChoice::Never);
+    w.write_arena(tracer.traces()).unwrap();
+    let mut s = String::from_utf8(w.into_writer()).unwrap();
+    patch_output(&mut s);
+    expect![[r#"
+        . [2620] Caller::fallback()
+            ├─ [0] Callee::fallback()
+            │   └─ ← [Stop] 
+            └─ ← [Stop] 
+    "#]]
+    .assert_eq(&s);
+}